@@ -1,8 +1,8 @@
 use nseqe::Node;
 use nseqe::action::Sleep;
 use nseqe::protocol::ip::{
-    Bind, Connect, ConnectPredicate, MessagesPredicate, ReceivePredicate, Send, SendMode, Wait,
-    WaitEvent,
+    Bind, BufferMatch, Connect, ConnectPredicate, MessagesPredicate, ReceivePredicate, Send,
+    SendMode, Wait, WaitEvent,
 };
 use tokio::task::JoinSet;
 use tracing::{Instrument, span};
@@ -78,6 +78,7 @@ fn create_node_1() -> Node {
             from: "192.168.1.11:0".parse().unwrap(),
             to: "192.168.1.10:3000".parse().unwrap(),
             buffer: vec![1, 1],
+            matching: BufferMatch::Exact,
         },
     ])));
 
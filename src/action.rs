@@ -9,23 +9,33 @@ use tracing::event;
 pub trait Action: Send + Sync {
     fn name(&self) -> String;
     async fn perform(&self, ctx: Ctx) -> Result<(), ActionError>;
+
+    /// Returns the sub-actions the action may perform with their weight, none by default.
+    fn children(&self) -> Vec<(u32, &dyn Action)> {
+        Vec::new()
+    }
 }
 
 /// ActionError defines the possible errors that can occur during action execution.
-#[derive(Debug, PartialEq, Clone, Error)]
+#[derive(Debug, PartialEq, Clone, Error, Serialize, Deserialize)]
 pub enum ActionError {
     #[error("Connection error")]
     ConnectError(String),
     #[error("Disconnection error")]
     DisconnectError,
     #[error("Bind error")]
-    BindError,
+    BindError(String),
     #[error("Send error")]
     SendError(String),
+    /// A non-blocking send found the socket buffer full, holds the number of bytes written.
+    #[error("Send would block")]
+    WouldBlock(usize),
     #[error("Sleep error")]
     SleepError,
     #[error("Wait error")]
     WaitError,
+    #[error("Assertion error")]
+    AssertError(String),
 }
 
 /// Sleep action represents a delay in the execution of the action sequence.
@@ -59,3 +69,261 @@ impl Action for Sleep {
         Ok(())
     }
 }
+
+/// SetPhase action records the current phase of the scenario,
+/// every event recorded afterwards is stamped with its name.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SetPhase {
+    name: String,
+}
+
+impl SetPhase {
+    pub fn new(name: &str) -> Self {
+        SetPhase {
+            name: name.to_string(),
+        }
+    }
+
+    pub fn phase(&self) -> &str {
+        &self.name
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for SetPhase {
+    fn name(&self) -> String {
+        "SET_PHASE".into()
+    }
+
+    /// Performs the set phase action by storing the phase name in the context.
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        event!(tracing::Level::INFO, "Entering phase {}", self.name);
+        ctx.lock().await.phase = Some(self.name.clone());
+
+        Ok(())
+    }
+}
+
+/// PhaseBarrier action waits until every node of the scenario taking part in the
+/// phase barrier `name` reached it, then enters the phase `name`.
+///
+/// The barrier must have been created with [`crate::Scenario::phase_barrier`].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PhaseBarrier {
+    name: String,
+}
+
+impl PhaseBarrier {
+    pub fn new(name: &str) -> Self {
+        PhaseBarrier {
+            name: name.to_string(),
+        }
+    }
+
+    pub fn phase(&self) -> &str {
+        &self.name
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for PhaseBarrier {
+    fn name(&self) -> String {
+        "PHASE_BARRIER".into()
+    }
+
+    /// Performs the phase barrier action by waiting on the barrier, then setting the phase.
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let barrier = {
+            let context = ctx.lock().await;
+            let barriers = context.phase_barriers.lock().unwrap();
+            barriers.get(&self.name).cloned()
+        };
+        let Some(barrier) = barrier else {
+            event!(
+                tracing::Level::ERROR,
+                "No phase barrier named {}",
+                self.name
+            );
+            return Err(ActionError::WaitError);
+        };
+
+        event!(
+            tracing::Level::INFO,
+            "Waiting at phase barrier {}",
+            self.name
+        );
+        barrier.wait().await;
+
+        event!(tracing::Level::INFO, "Entering phase {}", self.name);
+        ctx.lock().await.phase = Some(self.name.clone());
+
+        Ok(())
+    }
+}
+
+/// WeightedChoice action performs one of its sub-actions, picked at random
+/// with the node random number generator in proportion to their weights.
+#[derive(Serialize, Deserialize)]
+pub struct WeightedChoice {
+    choices: Vec<(u32, Box<dyn Action>)>,
+}
+
+impl WeightedChoice {
+    pub fn new(choices: Vec<(u32, Box<dyn Action>)>) -> Self {
+        WeightedChoice { choices }
+    }
+
+    pub fn choices(&self) -> &[(u32, Box<dyn Action>)] {
+        &self.choices
+    }
+
+    /// Returns the choice matching `draw`, a number below the total weight.
+    fn pick(&self, mut draw: u64) -> Option<&dyn Action> {
+        for (weight, action) in &self.choices {
+            if draw < *weight as u64 {
+                return Some(action.as_ref());
+            }
+            draw -= *weight as u64;
+        }
+
+        None
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for WeightedChoice {
+    fn name(&self) -> String {
+        "WEIGHTED_CHOICE".into()
+    }
+
+    fn children(&self) -> Vec<(u32, &dyn Action)> {
+        self.choices
+            .iter()
+            .map(|(weight, action)| (*weight, action.as_ref()))
+            .collect()
+    }
+
+    /// Performs the weighted choice action by drawing a sub-action and performing it.
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let total: u64 = self.choices.iter().map(|(weight, _)| *weight as u64).sum();
+        if total == 0 {
+            event!(tracing::Level::WARN, "No weighted choice to perform");
+            return Ok(());
+        }
+
+        let draw = ctx.lock().await.rng.below(total);
+        match self.pick(draw) {
+            Some(action) => {
+                event!(tracing::Level::INFO, "Picked {}", action.name());
+                action.perform(ctx).await
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// FailWith action always fails with `error`, without touching the network.
+///
+/// Meant to exercise error handling of scenarios deterministically.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FailWith {
+    error: ActionError,
+}
+
+impl FailWith {
+    pub fn new(error: ActionError) -> Self {
+        FailWith { error }
+    }
+
+    pub fn error(&self) -> &ActionError {
+        &self.error
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for FailWith {
+    fn name(&self) -> String {
+        "FAIL_WITH".into()
+    }
+
+    async fn perform(&self, _ctx: Ctx) -> Result<(), ActionError> {
+        event!(tracing::Level::INFO, "Failing with {:?}", self.error);
+        Err(self.error.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ExecutionMode, Node, NodeError};
+
+    use super::{Action, ActionError, FailWith, SetPhase, WeightedChoice};
+
+    #[tokio::test]
+    async fn test_weighted_choice_distribution() {
+        let node = Node::with_seed("test-node", 42);
+        let choice = WeightedChoice::new(vec![
+            (80, Box::new(SetPhase::new("small"))),
+            (20, Box::new(SetPhase::new("large"))),
+            (0, Box::new(SetPhase::new("never"))),
+        ]);
+
+        let mut small = 0;
+        for _ in 0..1000 {
+            choice.perform(node.ctx()).await.unwrap();
+            match node.ctx().lock().await.phase.as_deref() {
+                Some("small") => small += 1,
+                Some("large") => {}
+                other => panic!("Unexpected phase {:?}", other),
+            }
+        }
+
+        assert!((750..850).contains(&small), "{}", small);
+    }
+
+    #[test]
+    fn test_weighted_choice_serde() {
+        let choice = WeightedChoice::new(vec![(1, Box::new(SetPhase::new("small")))]);
+        let json = serde_json::to_string(&choice).unwrap();
+        let choice: WeightedChoice = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(choice.choices()[0].0, 1);
+        assert_eq!(choice.choices()[0].1.name(), "SET_PHASE");
+    }
+
+    #[tokio::test]
+    async fn test_fail_with() {
+        let node = Node::new("test-node");
+        let error = ActionError::SendError("injected".into());
+
+        // The error survives a serde round trip of the action
+        let action: Box<dyn Action> = Box::new(FailWith::new(error.clone()));
+        let json = serde_json::to_string(&action).unwrap();
+        let action: Box<dyn Action> = serde_json::from_str(&json).unwrap();
+        assert_eq!(action.name(), "FAIL_WITH");
+        assert_eq!(action.perform(node.ctx()).await, Err(error.clone()));
+
+        // A failing action does not stop the node, the next actions still run
+        let mut node = Node::new("test-node");
+        node.add_action(FailWith::new(error.clone()));
+        node.add_action(SetPhase::new("after"));
+        node.start().await;
+        assert_eq!(node.ctx().lock().await.phase.as_deref(), Some("after"));
+
+        // Unless it stops on errors, reporting the failed action
+        let mut node = Node::new("test-node");
+        node.set_execution_mode(ExecutionMode::StopOnError);
+        node.add_action(FailWith::new(error.clone()));
+        node.add_action(SetPhase::new("after"));
+        let result = node.run().await;
+        assert!(
+            matches!(&result, Err(NodeError::ActionFailed { index: 0, error: e }) if *e == error),
+            "{:?}",
+            result
+        );
+        assert_eq!(node.ctx().lock().await.phase, None);
+    }
+}
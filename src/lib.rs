@@ -1,6 +1,43 @@
 pub mod action;
+mod clock;
+mod macros;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod node;
 pub mod protocol;
+mod resolver;
+mod rng;
+mod scenario;
 
+pub use clock::Clock;
+pub use clock::MockClock;
+pub use clock::SystemClock;
+#[cfg(feature = "metrics")]
+pub use metrics::MetricsSink;
+#[cfg(feature = "metrics")]
+pub use metrics::NodeMetrics;
+#[cfg(feature = "metrics")]
+pub use metrics::PrometheusSink;
+pub use node::ActionOutcome;
+pub use node::BindSummary;
+pub use node::ConnectEvent;
+pub use node::Ctx;
+pub use node::DisconnectReason;
+pub use node::ExecutionMode;
 pub use node::Node;
+pub use node::NodeContext;
+pub use node::NodeError;
+pub use node::NodeReport;
 pub use node::ReceiveEvent;
+pub use node::ScenarioError;
+pub use resolver::Resolver;
+pub use resolver::StaticResolver;
+pub use resolver::SystemResolver;
+pub use rng::Rng;
+pub use scenario::Scenario;
+pub use scenario::ScenarioBuilder;
+pub use scenario::ScenarioStep;
+
+// Re-exported so that custom actions can be implemented against the same versions.
+pub use async_trait;
+pub use typetag;
@@ -0,0 +1,105 @@
+/// Builds the actions of a node from a compact syntax, as a `Vec<Box<dyn Action>>`
+/// to hand to [`crate::Node::add_boxed_action`].
+///
+/// ```
+/// let actions = nseqe::scenario! {
+///     bind "127.0.0.1:3000";
+///     sleep 1000;
+///     connect "127.0.0.2:0" -> "127.0.0.1:3000" timeout 1000;
+///     send unicast "127.0.0.2:0" -> "127.0.0.1:3000" [1, 2, 3];
+/// };
+/// assert_eq!(actions.len(), 4);
+/// ```
+///
+/// Send modes are written in snake case: `unicast`, `broadcast`, `multicast`,
+/// `udp_unicast` and `udp_connected`. Addresses are parsed when the actions are
+/// built, and an invalid one panics.
+#[macro_export]
+macro_rules! scenario {
+    (@collect [$($actions:expr,)*];) => {
+        <::std::vec::Vec<::std::boxed::Box<dyn $crate::action::Action>>>::from([$($actions),*])
+    };
+    (@collect [$($actions:expr,)*]; bind $to:literal; $($rest:tt)*) => {
+        $crate::scenario!(@collect [$($actions,)* $crate::scenario!(@boxed
+            $crate::protocol::ip::Bind::new($crate::scenario!(@addr $to))
+        ),]; $($rest)*)
+    };
+    (@collect [$($actions:expr,)*]; sleep $duration_ms:literal; $($rest:tt)*) => {
+        $crate::scenario!(@collect [$($actions,)* $crate::scenario!(@boxed
+            $crate::action::Sleep::new($duration_ms)
+        ),]; $($rest)*)
+    };
+    (@collect [$($actions:expr,)*]; connect $from:literal -> $to:literal timeout $timeout_ms:literal; $($rest:tt)*) => {
+        $crate::scenario!(@collect [$($actions,)* $crate::scenario!(@boxed
+            $crate::protocol::ip::Connect::new(
+                $crate::scenario!(@addr $from),
+                $crate::scenario!(@addr $to),
+                $timeout_ms,
+            )
+        ),]; $($rest)*)
+    };
+    (@collect [$($actions:expr,)*]; send $mode:ident $from:literal -> $to:literal [$($byte:expr),* $(,)?]; $($rest:tt)*) => {
+        $crate::scenario!(@collect [$($actions,)* $crate::scenario!(@boxed
+            $crate::protocol::ip::Send::new(
+                $crate::scenario!(@mode $mode),
+                $crate::scenario!(@addr $from),
+                $crate::scenario!(@addr $to),
+                ::std::vec![$($byte),*],
+            )
+        ),]; $($rest)*)
+    };
+    (@boxed $action:expr) => {
+        ::std::boxed::Box::new($action) as ::std::boxed::Box<dyn $crate::action::Action>
+    };
+    (@addr $addr:literal) => {
+        $addr
+            .parse::<::std::net::SocketAddr>()
+            .expect(concat!("invalid address ", $addr))
+    };
+    (@mode unicast) => { $crate::protocol::ip::SendMode::Unicast };
+    (@mode broadcast) => { $crate::protocol::ip::SendMode::Broadcast };
+    (@mode multicast) => { $crate::protocol::ip::SendMode::Multicast };
+    (@mode udp_unicast) => { $crate::protocol::ip::SendMode::UdpUnicast };
+    (@mode udp_connected) => { $crate::protocol::ip::SendMode::UdpConnected };
+    ($($body:tt)*) => {
+        $crate::scenario!(@collect []; $($body)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Node,
+        action::Sleep,
+        protocol::ip::{Bind, Connect, Send, SendMode},
+    };
+
+    #[test]
+    fn test_scenario_macro_matches_hand_built_node() {
+        let mut built = Node::new("built");
+        for action in crate::scenario! {
+            bind "127.0.0.1:3000";
+            sleep 1000;
+            connect "127.0.0.2:0" -> "127.0.0.1:3000" timeout 1000;
+            send unicast "127.0.0.2:0" -> "127.0.0.1:3000" [1, 2, 3];
+            send udp_unicast "127.0.0.2:0" -> "127.0.0.1:3000" [];
+        } {
+            built.add_boxed_action(action);
+        }
+
+        let client = "127.0.0.2:0".parse().unwrap();
+        let server = "127.0.0.1:3000".parse().unwrap();
+        let mut node = Node::new("node");
+        node.add_action(Bind::new(server));
+        node.add_action(Sleep::new(1000));
+        node.add_action(Connect::new(client, server, 1000));
+        node.add_action(Send::new(SendMode::Unicast, client, server, vec![1, 2, 3]));
+        node.add_action(Send::new(SendMode::UdpUnicast, client, server, vec![]));
+
+        assert_eq!(
+            serde_json::to_value(built.actions()).unwrap(),
+            serde_json::to_value(node.actions()).unwrap()
+        );
+        assert!(crate::scenario! {}.is_empty());
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Write},
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+    time,
+};
+use tracing::event;
+
+/// Largest request header accepted by the metrics endpoint.
+const MAX_REQUEST_HEADER: usize = 8 * 1024;
+
+/// Delay before accepting a metrics client again after a failure.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Destination of the live metrics of the nodes, registered with [`crate::Node::set_metrics_sink`].
+///
+/// Every metric is labeled with the name of the node reporting it.
+pub trait MetricsSink: Debug + Send + Sync {
+    /// Adds `value` to the counter `name`.
+    fn increment(&self, node: &str, name: &str, value: u64);
+    /// Sets the gauge `name` to `value`.
+    fn gauge(&self, node: &str, name: &str, value: f64);
+    /// Records a sample of the distribution `name`.
+    fn sample(&self, node: &str, name: &str, value: f64);
+}
+
+/// Sink of a node, reporting its metrics under its name.
+#[derive(Debug, Clone)]
+pub struct NodeMetrics {
+    node: String,
+    sink: Arc<dyn MetricsSink>,
+}
+
+impl NodeMetrics {
+    pub fn new(node: &str, sink: Arc<dyn MetricsSink>) -> Self {
+        NodeMetrics {
+            node: node.to_string(),
+            sink,
+        }
+    }
+
+    pub fn increment(&self, name: &str, value: u64) {
+        self.sink.increment(&self.node, name, value);
+    }
+
+    pub fn gauge(&self, name: &str, value: f64) {
+        self.sink.gauge(&self.node, name, value);
+    }
+
+    pub fn sample(&self, name: &str, value: f64) {
+        self.sink.sample(&self.node, name, value);
+    }
+}
+
+/// Values of the metrics of a [`PrometheusSink`], keyed by metric and node name.
+#[derive(Debug, Default)]
+struct PrometheusMetrics {
+    counters: BTreeMap<(String, String), u64>,
+    gauges: BTreeMap<(String, String), f64>,
+    /// Sum and count of the samples.
+    summaries: BTreeMap<(String, String), (f64, u64)>,
+}
+
+/// Sink keeping the metrics in memory and exposing them in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct PrometheusSink {
+    metrics: Mutex<PrometheusMetrics>,
+}
+
+impl PrometheusSink {
+    pub fn new() -> Self {
+        PrometheusSink::default()
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format, prefixed with `nseqe_`.
+    ///
+    /// The characters not allowed in metric names are replaced with `_`.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let mut out = String::new();
+
+        let mut last = None;
+        for ((name, node), value) in &metrics.counters {
+            if last != Some(name) {
+                let _ = writeln!(out, "# TYPE nseqe_{} counter", metric_name(name));
+                last = Some(name);
+            }
+            let _ = writeln!(
+                out,
+                "nseqe_{}{{node=\"{}\"}} {}",
+                metric_name(name),
+                label_value(node),
+                value
+            );
+        }
+
+        let mut last = None;
+        for ((name, node), value) in &metrics.gauges {
+            if last != Some(name) {
+                let _ = writeln!(out, "# TYPE nseqe_{} gauge", metric_name(name));
+                last = Some(name);
+            }
+            let _ = writeln!(
+                out,
+                "nseqe_{}{{node=\"{}\"}} {}",
+                metric_name(name),
+                label_value(node),
+                value
+            );
+        }
+
+        let mut last = None;
+        for ((name, node), (sum, count)) in &metrics.summaries {
+            if last != Some(name) {
+                let _ = writeln!(out, "# TYPE nseqe_{} summary", metric_name(name));
+                last = Some(name);
+            }
+            let _ = writeln!(
+                out,
+                "nseqe_{}_sum{{node=\"{}\"}} {}",
+                metric_name(name),
+                label_value(node),
+                sum
+            );
+            let _ = writeln!(
+                out,
+                "nseqe_{}_count{{node=\"{}\"}} {}",
+                metric_name(name),
+                label_value(node),
+                count
+            );
+        }
+
+        out
+    }
+
+    /// Serves the metrics on `GET /metrics` at `addr` until the returned task is aborted.
+    pub async fn serve(self: Arc<Self>, addr: SocketAddr) -> io::Result<JoinHandle<()>> {
+        let listener = TcpListener::bind(addr).await?;
+        event!(tracing::Level::INFO, "Serving metrics on {}", addr);
+
+        Ok(tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Out of file descriptors, retrying at once would spin
+                        event!(
+                            tracing::Level::WARN,
+                            "Error accepting a metrics client: {}",
+                            e
+                        );
+                        time::sleep(ACCEPT_BACKOFF).await;
+                        continue;
+                    }
+                };
+                let sink = Arc::clone(&self);
+                tokio::spawn(async move {
+                    if let Err(e) = sink.respond(stream).await {
+                        event!(
+                            tracing::Level::WARN,
+                            "Error serving metrics to {}: {}",
+                            peer,
+                            e
+                        );
+                    }
+                });
+            }
+        }))
+    }
+
+    /// Answers a single HTTP request, closing the connection afterwards.
+    ///
+    /// A request header longer than [`MAX_REQUEST_HEADER`] is answered with `400 Bad Request`.
+    async fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            if request.len() > MAX_REQUEST_HEADER {
+                break;
+            }
+            let read = stream.read(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..read]);
+        }
+
+        let response = if request.len() > MAX_REQUEST_HEADER {
+            "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        } else if request.starts_with(b"GET /metrics ") {
+            let body = self.render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        } else {
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+        };
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+/// Replaces the characters not allowed in a Prometheus metric name with `_`.
+///
+/// The name follows the `nseqe_` prefix, so it may start with a digit.
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Escapes a label value as the Prometheus text format requires.
+fn label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl MetricsSink for PrometheusSink {
+    fn increment(&self, node: &str, name: &str, value: u64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        *metrics
+            .counters
+            .entry((name.to_string(), node.to_string()))
+            .or_default() += value;
+    }
+
+    fn gauge(&self, node: &str, name: &str, value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        metrics
+            .gauges
+            .insert((name.to_string(), node.to_string()), value);
+    }
+
+    fn sample(&self, node: &str, name: &str, value: f64) {
+        let mut metrics = self.metrics.lock().unwrap();
+        let (sum, count) = metrics
+            .summaries
+            .entry((name.to_string(), node.to_string()))
+            .or_default();
+        *sum += value;
+        *count += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::{MAX_REQUEST_HEADER, MetricsSink, PrometheusSink};
+    use crate::{
+        Node, Scenario,
+        action::Sleep,
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect, Send, SendMode},
+        },
+    };
+
+    /// Sink recording every reported metric, in order.
+    #[derive(Debug, Default)]
+    struct MemorySink {
+        reported: std::sync::Mutex<Vec<(String, String, f64)>>,
+    }
+
+    impl MemorySink {
+        fn total(&self, node: &str, name: &str) -> f64 {
+            self.reported
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(n, m, _)| n == node && m == name)
+                .map(|(_, _, value)| value)
+                .sum()
+        }
+    }
+
+    impl MetricsSink for MemorySink {
+        fn increment(&self, node: &str, name: &str, value: u64) {
+            self.reported
+                .lock()
+                .unwrap()
+                .push((node.to_string(), name.to_string(), value as f64));
+        }
+
+        fn gauge(&self, node: &str, name: &str, value: f64) {
+            self.reported
+                .lock()
+                .unwrap()
+                .push((node.to_string(), name.to_string(), value));
+        }
+
+        fn sample(&self, node: &str, name: &str, value: f64) {
+            self.gauge(node, name, value);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scenario_reports_metrics() {
+        let server_addr: SocketAddr = "127.0.1.125:3000".parse().unwrap();
+        let client_addr: SocketAddr = "127.0.1.126:0".parse().unwrap();
+        let sink = Arc::new(MemorySink::default());
+
+        let mut server = Node::new("server");
+        server.add_action(Bind::new(server_addr).with_protocol(Protocol::Tcp));
+        server.add_action(Sleep::new(300));
+        server.set_metrics_sink(sink.clone()).await;
+
+        let mut client = Node::new("client");
+        client.add_action(Sleep::new(100));
+        client.add_action(Connect::new(client_addr, server_addr, 1000));
+        client.add_action(Send::new(
+            SendMode::Unicast,
+            client_addr,
+            server_addr,
+            b"hello".to_vec(),
+        ));
+        client.add_action(Sleep::new(100));
+        client.set_metrics_sink(sink.clone()).await;
+
+        let mut scenario = Scenario::new();
+        scenario.add_node(server);
+        scenario.add_node(client);
+        scenario.run().await;
+
+        assert_eq!(sink.total("client", "connections_total"), 1.0);
+        assert_eq!(sink.total("client", "bytes_sent_total"), 5.0);
+        assert_eq!(sink.total("server", "connections_total"), 1.0);
+        assert_eq!(sink.total("server", "bytes_received_total"), 5.0);
+        assert!(sink.total("server", "event_backlog") >= 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_endpoint() {
+        let addr: SocketAddr = "127.0.1.124:9100".parse().unwrap();
+        let sink = Arc::new(PrometheusSink::new());
+        sink.increment("client", "bytes_sent_total", 3);
+        sink.increment("client", "bytes_sent_total", 4);
+        sink.gauge("server", "event_backlog", 2.0);
+        sink.sample("client", "rtt_us", 100.0);
+        sink.sample("client", "rtt_us", 300.0);
+
+        let server = Arc::clone(&sink).serve(addr).await.unwrap();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        server.abort();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(
+            body,
+            "# TYPE nseqe_bytes_sent_total counter\n\
+             nseqe_bytes_sent_total{node=\"client\"} 7\n\
+             # TYPE nseqe_event_backlog gauge\n\
+             nseqe_event_backlog{node=\"server\"} 2\n\
+             # TYPE nseqe_rtt_us summary\n\
+             nseqe_rtt_us_sum{node=\"client\"} 400\n\
+             nseqe_rtt_us_count{node=\"client\"} 2\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_oversized_request() {
+        let addr: SocketAddr = "127.0.1.141:9100".parse().unwrap();
+        let sink = Arc::new(PrometheusSink::new());
+        let server = Arc::clone(&sink).serve(addr).await.unwrap();
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut request = b"GET /metrics HTTP/1.1\r\n".to_vec();
+        // One byte past the limit, all read by the server, which would reset the connection
+        // if it closed it with unread data
+        request.resize(MAX_REQUEST_HEADER + 1, b'a');
+        stream.write_all(&request).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        server.abort();
+
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{}",
+            response
+        );
+    }
+
+    #[test]
+    fn test_prometheus_escaping() {
+        let sink = PrometheusSink::new();
+        sink.increment("say \"hi\"\\\nbye", "bytes sent-total", 1);
+
+        assert_eq!(
+            sink.render(),
+            "# TYPE nseqe_bytes_sent_total counter\n\
+             nseqe_bytes_sent_total{node=\"say \\\"hi\\\"\\\\\\nbye\"} 1\n"
+        );
+    }
+}
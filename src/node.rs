@@ -1,58 +1,481 @@
-use std::{collections::HashMap, fmt::Debug, net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::Future,
+    io::{self, Read},
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio::{
-    net::TcpStream,
-    sync::{Mutex, Notify},
+    net::{TcpStream, UdpSocket, tcp::OwnedWriteHalf},
+    sync::{Barrier, Mutex, Notify, watch},
+    task::AbortHandle,
     time::Instant,
 };
-use tracing::{event, instrument};
+use tracing::{Instrument, event, instrument};
+
+#[cfg(feature = "metrics")]
+use crate::metrics::{MetricsSink, NodeMetrics};
+use crate::{
+    action::{Action, ActionError},
+    clock::{Clock, SystemClock},
+    resolver::{Resolver, SystemResolver},
+    rng::Rng,
+};
+
+/// Serializes an instant as the wall-clock time it stands for, so that the events
+/// saved by [`Node::save_state`] keep their timing once restored in another process.
+mod wall_clock {
+    use std::time::SystemTime;
 
-use crate::action::Action;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use tokio::time::Instant;
+
+    pub fn serialize<S: Serializer>(instant: &Instant, serializer: S) -> Result<S::Ok, S::Error> {
+        let (now, system_now) = (Instant::now(), SystemTime::now());
+        // Instants of a mock clock may be ahead of the real one
+        let time = match now.checked_duration_since(*instant) {
+            Some(elapsed) => system_now - elapsed,
+            None => system_now + (*instant - now),
+        };
+        time.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instant, D::Error> {
+        let time = SystemTime::deserialize(deserializer)?;
+        let (now, system_now) = (Instant::now(), SystemTime::now());
+        Ok(match system_now.duration_since(time) {
+            Ok(elapsed) => now.checked_sub(elapsed).unwrap_or(now),
+            Err(ahead) => now + ahead.duration(),
+        })
+    }
+}
 
 /// Represents an event that occurs when data is received over a network socket.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReceiveEvent {
+    #[serde(with = "wall_clock")]
     pub instant: Instant,
+    /// Recording order of the event on its node, orders the events sharing an instant.
+    pub seq: u64,
     pub from: SocketAddr,
     pub to: SocketAddr,
     pub buffer: Vec<u8>,
+    /// Set when the received data may have been cut to fit the read buffer.
+    pub truncated: bool,
+    /// Phase of the scenario during which the event occurred.
+    pub phase: Option<String>,
 }
 
 /// Represents an event that occurs when data is sent over a network socket.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendEvent {
+    #[serde(with = "wall_clock")]
     pub instant: Instant,
+    pub seq: u64,
     pub from: SocketAddr,
     pub to: SocketAddr,
     pub buffer: Vec<u8>,
+    /// Number of bytes sent, set even when the sent data is not kept in `buffer`.
+    pub len: usize,
+    /// Phase of the scenario during which the event occurred.
+    pub phase: Option<String>,
 }
 
 /// Represents an event that occurs when a connection is established between two sockets.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectEvent {
+    #[serde(with = "wall_clock")]
+    pub instant: Instant,
+    pub seq: u64,
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    /// Phase of the scenario during which the event occurred.
+    pub phase: Option<String>,
+}
+
+/// Represents an event that occurs when an incoming connection is refused by the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectEvent {
+    #[serde(with = "wall_clock")]
     pub instant: Instant,
+    pub seq: u64,
     pub from: SocketAddr,
     pub to: SocketAddr,
+    /// Phase of the scenario during which the event occurred.
+    pub phase: Option<String>,
+}
+
+/// Kernel statistics of a TCP connection, read with the `TcpInfo` action on Linux.
+#[derive(Debug, Clone)]
+pub struct TcpInfoSnapshot {
+    pub instant: Instant,
+    /// Smoothed round trip time estimate, in microseconds.
+    pub rtt_us: u32,
+    pub rttvar_us: u32,
+    /// Retransmissions of the segment currently unacknowledged.
+    pub retransmits: u8,
+    /// Segments retransmitted since the connection was opened.
+    pub total_retrans: u32,
+    /// Congestion window, in segments.
+    pub snd_cwnd: u32,
+}
+
+/// Why a connection accepted by the node was closed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum DisconnectReason {
+    /// The peer closed the connection gracefully.
+    Fin,
+    /// The peer reset the connection.
+    Rst,
+    /// The node closed the connection after the peer stayed idle too long.
+    IdleTimeout,
+    /// The node closed the connection after the peer sent bytes it could not frame.
+    FramingError,
+}
+
+/// Represents an event that occurs when a connection accepted by the node is closed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisconnectEvent {
+    #[serde(with = "wall_clock")]
+    pub instant: Instant,
+    pub seq: u64,
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    pub reason: DisconnectReason,
+    /// Phase of the scenario during which the event occurred.
+    pub phase: Option<String>,
+}
+
+/// Traffic counters of a node, snapshotted by the `CounterCheckpoint` action.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CounterSnapshot {
+    pub instant: Instant,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub send_events: usize,
+    pub receive_events: usize,
+    pub connect_events: usize,
+}
+
+/// Summary of a listener address bound by a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindSummary {
+    pub addr: SocketAddr,
+    /// Set when a TCP listener was bound on the address.
+    pub tcp_ready: bool,
+    /// Set when a UDP socket was bound on the address.
+    pub udp_ready: bool,
+    /// Connections accepted by the TCP listener.
+    pub accepted: u64,
+    /// Datagrams received by the UDP socket.
+    pub datagrams: u64,
+}
+
+/// Outcome of an action performed by [`Node::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionOutcome {
+    /// Index of the action in the actions of its run.
+    pub index: usize,
+    pub name: String,
+    pub duration: Duration,
+    pub result: Result<(), ActionError>,
+}
+
+/// Summary of the run of a node, returned by [`Node::report`] and [`Node::run`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeReport {
+    pub name: String,
+    /// Listeners of the node, in the order they were bound.
+    pub binds: Vec<BindSummary>,
+    /// Actions performed by the node, in order.
+    pub actions: Vec<ActionOutcome>,
+}
+
+impl NodeReport {
+    /// Returns the outcomes of the actions that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &ActionOutcome> {
+        self.actions
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+    }
+}
+
+/// Error preventing a node from running its actions.
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum NodeError {
+    #[error("Node runtime failed: {0}")]
+    RuntimeError(String),
+    #[error("Action {index} failed: {error}")]
+    ActionFailed { index: usize, error: ActionError },
+}
+
+/// Error loading the actions of a node from a scenario file.
+#[derive(Debug, PartialEq, Clone, Error)]
+pub enum ScenarioError {
+    #[error("Error reading scenario: {0}")]
+    ReadError(String),
+    #[error("Scenario is not a JSON array of actions: {0}")]
+    InvalidScenario(String),
+    #[error("Invalid action {index}: {message}")]
+    InvalidAction { index: usize, message: String },
+}
+
+/// How a node goes on after one of its actions failed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Performs the remaining actions anyway.
+    #[default]
+    Continue,
+    /// Stops at the failed action, leaving the remaining ones unperformed.
+    StopOnError,
+}
+
+/// Pending actions and recorded events of a node, saved by [`Node::save_state`].
+///
+/// Sockets cannot be saved, so a restored node has no listener nor open connection.
+/// The actions are borrowed from the node when saved.
+#[derive(Serialize, Deserialize)]
+struct NodeState<A = Vec<Box<dyn Action>>> {
+    actions: A,
+    receive_events: Vec<ReceiveEvent>,
+    send_events: Vec<SendEvent>,
+    connect_events: Vec<ConnectEvent>,
+    reject_events: Vec<RejectEvent>,
+    disconnect_events: Vec<DisconnectEvent>,
+    total_connections: u64,
+    phase: Option<String>,
+    next_seq: u64,
+}
+
+/// Raw frame captured by the `Capture` action, headers included.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    pub instant: Instant,
+    pub frame: Vec<u8>,
 }
 
 /// Represents the context of a node, containing its TCP streams and events.
 #[derive(Debug)]
 pub struct NodeContext {
     pub tcp_streams: HashMap<SocketAddr, TcpStream>,
+    /// Tasks reading the responses on the streams of `tcp_streams`, keyed by peer address.
+    pub response_readers: HashMap<SocketAddr, AbortHandle>,
+    /// Signaled when a stream is added to or removed from `tcp_streams` or `server_streams`.
+    pub stream_notifier: Arc<Notify>,
+    /// Connections opened by a connect pool, keyed by local address.
+    pub pool_streams: HashMap<SocketAddr, TcpStream>,
+    /// Write halves of the connections accepted by the node, keyed by peer address.
+    pub server_streams: HashMap<SocketAddr, OwnedWriteHalf>,
+    /// UDP sockets bound by the node, keyed by local address.
+    pub udp_listeners: HashMap<SocketAddr, Arc<UdpSocket>>,
+    /// Bytes read by the listeners of the node but not recorded as receive events yet.
+    pub unrecorded_bytes: usize,
+    /// Peers whose accepted connections are not read, with the instant they were paused.
+    pub paused_reads: HashMap<SocketAddr, Instant>,
+    /// Signaled when reads from a peer are resumed.
+    pub reads_notifier: Arc<Notify>,
+    /// Last TCP statistics read for each peer.
+    pub tcp_infos: HashMap<SocketAddr, TcpInfoSnapshot>,
+    /// Addresses of the TCP and UDP listeners bound by the node, with their actual port.
+    pub bound: Vec<SocketAddr>,
+    /// Instant each listener was bound, keyed by listener address.
+    pub bind_instants: HashMap<SocketAddr, Instant>,
+    /// Connections accepted by each TCP listener, keyed by listener address.
+    pub accepted_connections: HashMap<SocketAddr, u64>,
+    /// Datagrams received by each UDP listener, keyed by listener address.
+    pub received_datagrams: HashMap<SocketAddr, u64>,
+    /// UDP sockets connected by the node, keyed by peer address.
+    pub udp_sockets: HashMap<SocketAddr, Arc<UdpSocket>>,
     pub receive_events: Vec<ReceiveEvent>,
     pub receive_notifier: Arc<Notify>,
     pub send_events: Vec<SendEvent>,
+    /// Signaled when a send event is recorded.
+    pub send_notifier: Arc<Notify>,
     pub connect_events: Vec<ConnectEvent>,
     pub connect_notifier: Arc<Notify>,
+    pub reject_events: Vec<RejectEvent>,
+    pub disconnect_events: Vec<DisconnectEvent>,
+    /// Frames captured on a raw socket, only filled with the `raw` feature.
+    pub captured_frames: Vec<CapturedFrame>,
+    /// Number of connections established since the node started, including closed ones.
+    pub total_connections: u64,
+    /// Current phase of the scenario, stamped on every recorded event.
+    pub phase: Option<String>,
+    /// Random number generator of the node, seeded when the node is created.
+    pub rng: Rng,
+    /// Index of the action the node is performing, if it started one.
+    pub current_step: Option<usize>,
+    pub step_notifier: Arc<Notify>,
+    /// Phase barriers of the scenario the node belongs to.
+    pub phase_barriers: PhaseBarriers,
+    /// Clock stamping the recorded events, replaced by a [`crate::MockClock`] in tests.
+    pub clock: Arc<dyn Clock>,
+    /// Resolver of the host names given to the actions, replaced by a
+    /// [`crate::StaticResolver`] in tests.
+    pub resolver: Arc<dyn Resolver>,
+    /// Set to true once the node is cancelled or finished, stopping its background tasks.
+    pub cancelled: Arc<watch::Sender<bool>>,
+    /// Sequence number of the next recorded event.
+    pub next_seq: u64,
+    /// Peak number of receive events held by the node at once.
+    ///
+    /// The receive log is not capped, only the `CoalesceReceives` action shrinks it,
+    /// so the peak is the number of events received unless they were coalesced.
+    pub max_event_backlog: usize,
+    /// Counters snapshotted by the `CounterCheckpoint` action, keyed by label.
+    pub checkpoints: HashMap<String, CounterSnapshot>,
+    /// Sink the live metrics of the node are reported to, if one was registered.
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<NodeMetrics>,
+}
+
+impl NodeContext {
+    /// Returns the sequence number of a new event, increasing with every call.
+    pub fn next_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    /// Records a receive event, keeping track of the receive backlog peak.
+    pub fn push_receive_event(&mut self, event: ReceiveEvent) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.increment("bytes_received_total", event.buffer.len() as u64);
+            metrics.gauge("event_backlog", (self.receive_events.len() + 1) as f64);
+        }
+        self.receive_events.push(event);
+        self.max_event_backlog = self.max_event_backlog.max(self.receive_events.len());
+    }
+
+    /// Records a send event and signals every task waiting for one.
+    pub fn push_send_event(&mut self, event: SendEvent) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.increment("bytes_sent_total", event.len as u64);
+        }
+        self.send_events.push(event);
+        self.send_notifier.notify_waiters();
+    }
+
+    /// Removes the stream connected by the node to `peer`, stopping the task reading its
+    /// responses so that the connection is closed once the stream is dropped.
+    pub fn close_tcp_stream(&mut self, peer: &SocketAddr) -> Option<TcpStream> {
+        if let Some(reader) = self.response_readers.remove(peer) {
+            reader.abort();
+        }
+        let stream = self.tcp_streams.remove(peer)?;
+        self.stream_notifier.notify_waiters();
+        Some(stream)
+    }
+
+    /// Counts a connection established by the node.
+    pub fn record_connection(&mut self) {
+        self.total_connections += 1;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.increment("connections_total", 1);
+        }
+    }
+
+    /// Returns the current traffic counters of the node.
+    pub fn counters(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            instant: self.clock.now(),
+            bytes_sent: self.send_events.iter().map(|e| e.len as u64).sum(),
+            bytes_received: self
+                .receive_events
+                .iter()
+                .map(|e| e.buffer.len() as u64)
+                .sum(),
+            send_events: self.send_events.len(),
+            receive_events: self.receive_events.len(),
+            connect_events: self.connect_events.len(),
+        }
+    }
+
+    /// Summarizes the listeners bound by the node and the traffic they handled.
+    pub fn bind_summaries(&self) -> Vec<BindSummary> {
+        let mut summaries: Vec<BindSummary> = Vec::new();
+        for addr in &self.bound {
+            if summaries.iter().any(|summary| summary.addr == *addr) {
+                continue;
+            }
+            summaries.push(BindSummary {
+                addr: *addr,
+                tcp_ready: self.accepted_connections.contains_key(addr),
+                udp_ready: self.received_datagrams.contains_key(addr),
+                accepted: self.accepted_connections.get(addr).copied().unwrap_or(0),
+                datagrams: self.received_datagrams.get(addr).copied().unwrap_or(0),
+            });
+        }
+        summaries
+    }
+
+    /// Panics if the receive, send or connect events were not recorded
+    /// in non-decreasing instant order.
+    #[cfg(test)]
+    pub(crate) fn assert_monotonic(&self) {
+        fn check(kind: &str, instants: impl Iterator<Item = Instant>) {
+            let instants: Vec<Instant> = instants.collect();
+            if let Some(idx) = instants.windows(2).position(|pair| pair[1] < pair[0]) {
+                panic!(
+                    "{} event {} was recorded before the previous one ({:?} < {:?})",
+                    kind,
+                    idx + 1,
+                    instants[idx + 1],
+                    instants[idx]
+                );
+            }
+        }
+
+        check("Receive", self.receive_events.iter().map(|e| e.instant));
+        check("Send", self.send_events.iter().map(|e| e.instant));
+        check("Connect", self.connect_events.iter().map(|e| e.instant));
+    }
 }
 
 pub type Ctx = Arc<Mutex<NodeContext>>;
 
+/// Barriers shared by the nodes of a scenario, keyed by phase name.
+pub type PhaseBarriers = Arc<std::sync::Mutex<HashMap<String, Arc<Barrier>>>>;
+
+/// Runtime owned by a single node, shut down in the background when dropped
+/// so that it can be dropped from within another runtime.
+struct DedicatedRuntime(Option<tokio::runtime::Runtime>);
+
+impl DedicatedRuntime {
+    fn handle(&self) -> &tokio::runtime::Handle {
+        self.0
+            .as_ref()
+            .expect("runtime is only taken on drop")
+            .handle()
+    }
+}
+
+impl Drop for DedicatedRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.0.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
 /// Represents a node in the network, which can perform actions and maintain its context.
 pub struct Node {
     name: String,
     actions: Vec<Box<dyn Action>>,
     ctx: Ctx,
+    runtime: Option<Arc<DedicatedRuntime>>,
+    execution_mode: ExecutionMode,
+    /// Outcomes of the actions performed so far.
+    outcomes: Vec<ActionOutcome>,
 }
 
 impl Debug for Node {
@@ -63,24 +486,130 @@ impl Debug for Node {
 
 impl Node {
     pub fn new(name: &str) -> Self {
+        Node::with_rng(name, Rng::from_time())
+    }
+
+    /// Creates a node whose random choices are reproducible with the given seed.
+    pub fn with_seed(name: &str, seed: u64) -> Self {
+        Node::with_rng(name, Rng::new(seed))
+    }
+
+    fn with_rng(name: &str, rng: Rng) -> Self {
         Node {
             name: name.to_string(),
             actions: Vec::new(),
+            runtime: None,
+            execution_mode: ExecutionMode::default(),
+            outcomes: Vec::new(),
             ctx: Arc::new(Mutex::new(NodeContext {
                 tcp_streams: HashMap::new(),
+                response_readers: HashMap::new(),
+                stream_notifier: Arc::new(Notify::new()),
+                pool_streams: HashMap::new(),
+                server_streams: HashMap::new(),
+                udp_listeners: HashMap::new(),
+                unrecorded_bytes: 0,
+                paused_reads: HashMap::new(),
+                reads_notifier: Arc::new(Notify::new()),
+                tcp_infos: HashMap::new(),
+                bound: Vec::new(),
+                bind_instants: HashMap::new(),
+                accepted_connections: HashMap::new(),
+                received_datagrams: HashMap::new(),
+                udp_sockets: HashMap::new(),
                 receive_events: Vec::new(),
                 receive_notifier: Arc::new(Notify::new()),
                 send_events: Vec::new(),
+                send_notifier: Arc::new(Notify::new()),
                 connect_events: Vec::new(),
                 connect_notifier: Arc::new(Notify::new()),
+                reject_events: Vec::new(),
+                disconnect_events: Vec::new(),
+                captured_frames: Vec::new(),
+                total_connections: 0,
+                phase: None,
+                rng,
+                current_step: None,
+                phase_barriers: PhaseBarriers::default(),
+                step_notifier: Arc::new(Notify::new()),
+                clock: Arc::new(SystemClock),
+                resolver: Arc::new(SystemResolver),
+                cancelled: Arc::new(watch::Sender::new(false)),
+                next_seq: 0,
+                max_event_backlog: 0,
+                checkpoints: HashMap::new(),
+                #[cfg(feature = "metrics")]
+                metrics: None,
             })),
         }
     }
 
+    /// Creates a node performing the actions of a JSON array of tagged actions,
+    /// such as `[{"Sleep": {"duration_ms": 100}}]`.
+    pub fn from_reader<R: Read>(name: &str, reader: R) -> Result<Self, ScenarioError> {
+        let values: Vec<serde_json::Value> = serde_json::from_reader(reader).map_err(|e| {
+            if e.is_io() {
+                ScenarioError::ReadError(e.to_string())
+            } else {
+                ScenarioError::InvalidScenario(e.to_string())
+            }
+        })?;
+
+        let mut node = Node::new(name);
+        for (index, value) in values.into_iter().enumerate() {
+            let action: Box<dyn Action> =
+                serde_json::from_value(value).map_err(|e| ScenarioError::InvalidAction {
+                    index,
+                    message: e.to_string(),
+                })?;
+            node.actions.push(action);
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Loaded node {} with {} actions",
+            name,
+            node.actions.len()
+        );
+        Ok(node)
+    }
+
+    /// Creates a node performing the actions of the JSON scenario file at `path`,
+    /// see [`Node::from_reader`].
+    pub fn from_json_file(name: &str, path: impl AsRef<Path>) -> Result<Self, ScenarioError> {
+        let file = std::fs::File::open(path.as_ref()).map_err(|e| {
+            ScenarioError::ReadError(format!("{} ({})", path.as_ref().display(), e))
+        })?;
+        Node::from_reader(name, io::BufReader::new(file))
+    }
+
+    /// Runs the actions of the node, and the listeners they spawn, on a runtime with a
+    /// single worker thread of its own, so that a busy node cannot starve the others.
+    pub fn with_dedicated_runtime(mut self) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name(format!("node-{}", self.name))
+            .enable_all()
+            .build()?;
+        self.runtime = Some(Arc::new(DedicatedRuntime(Some(runtime))));
+        Ok(self)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Reports the live metrics of the node to `sink`, labeled with the node name.
+    #[cfg(feature = "metrics")]
+    pub async fn set_metrics_sink(&self, sink: Arc<dyn MetricsSink>) {
+        self.ctx.lock().await.metrics = Some(NodeMetrics::new(&self.name, sink));
+    }
+
+    /// Sets whether [`Node::run`] goes on after a failed action.
+    pub fn set_execution_mode(&mut self, mode: ExecutionMode) {
+        self.execution_mode = mode;
+    }
+
     pub fn add_action<T>(&mut self, action: T)
     where
         T: Action + 'static,
@@ -88,30 +617,658 @@ impl Node {
         self.actions.push(Box::new(action));
     }
 
+    /// Adds an already boxed action, such as one deserialized from a scenario file.
+    pub fn add_boxed_action(&mut self, action: Box<dyn Action>) {
+        self.actions.push(action);
+    }
+
+    /// Returns the actions that have not been performed yet.
+    pub fn actions(&self) -> &[Box<dyn Action>] {
+        &self.actions
+    }
+
     pub fn ctx(&self) -> Ctx {
         Arc::clone(&self.ctx)
     }
 
-    /// Starts the node, executing all its actions in order.
-    #[instrument(name = "node_start", level = "info", skip(self), fields(node = %self.name))]
-    pub async fn start(&mut self) {
-        for action in self.actions.drain(..) {
-            #[instrument(name = "action", level = "info", skip(ctx), fields(action = %action.name()))]
-            async fn run_action(action: &dyn Action, ctx: Ctx) {
-                if let Err(e) = action.perform(ctx).await {
-                    event!(tracing::Level::ERROR, "Error performing action: {:?}", e);
+    /// Returns a summary of what the node did so far.
+    pub async fn report(&self) -> NodeReport {
+        NodeReport {
+            name: self.name.clone(),
+            binds: self.ctx.lock().await.bind_summaries(),
+            actions: self.outcomes.clone(),
+        }
+    }
+
+    /// Saves the actions not performed yet and the recorded events to `path`,
+    /// so that the run can be continued by [`Node::restore_state`].
+    pub async fn save_state(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let state = {
+            let context = self.ctx.lock().await;
+            NodeState {
+                actions: self.actions.as_slice(),
+                receive_events: context.receive_events.clone(),
+                send_events: context.send_events.clone(),
+                connect_events: context.connect_events.clone(),
+                reject_events: context.reject_events.clone(),
+                disconnect_events: context.disconnect_events.clone(),
+                total_connections: context.total_connections,
+                phase: context.phase.clone(),
+                next_seq: context.next_seq,
+            }
+        };
+        tokio::fs::write(path, serde_json::to_vec(&state)?).await
+    }
+
+    /// Replaces the actions and the recorded events of the node with the ones
+    /// saved to `path` by [`Node::save_state`].
+    pub async fn restore_state(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let state: NodeState = serde_json::from_slice(&tokio::fs::read(path).await?)?;
+        self.actions = state.actions;
+
+        let mut context = self.ctx.lock().await;
+        context.max_event_backlog = context.max_event_backlog.max(state.receive_events.len());
+        context.receive_events = state.receive_events;
+        context.send_events = state.send_events;
+        context.connect_events = state.connect_events;
+        context.reject_events = state.reject_events;
+        context.disconnect_events = state.disconnect_events;
+        context.total_connections = state.total_connections;
+        context.phase = state.phase;
+        context.next_seq = state.next_seq;
+
+        event!(
+            tracing::Level::INFO,
+            "Restored node {} with {} pending actions",
+            self.name,
+            self.actions.len()
+        );
+        Ok(())
+    }
+
+    /// Stops the background tasks of the node, such as heartbeats.
+    pub async fn cancel(&self) {
+        self.ctx.lock().await.cancelled.send_replace(true);
+    }
+
+    /// Returns a future resolving once the node has started the action at index `step`,
+    /// or failing after `timeout`.
+    ///
+    /// The future does not borrow the node, so it can be awaited while the node runs.
+    pub fn await_step(
+        &self,
+        step: usize,
+        timeout: Duration,
+    ) -> impl Future<Output = Result<(), ActionError>> + std::marker::Send + 'static {
+        let ctx = self.ctx();
+
+        async move {
+            let notifier = ctx.lock().await.step_notifier.clone();
+            let reached = async {
+                loop {
+                    let notified = notifier.notified();
+                    tokio::pin!(notified);
+                    notified.as_mut().enable();
+
+                    if ctx
+                        .lock()
+                        .await
+                        .current_step
+                        .is_some_and(|current| current >= step)
+                    {
+                        return;
+                    }
+                    notified.await;
                 }
+            };
+
+            tokio::time::timeout(timeout, reached)
+                .await
+                .map_err(|_| ActionError::WaitError)
+        }
+    }
+
+    /// Returns a Graphviz digraph of the actions of the node, in execution order.
+    ///
+    /// The sub-actions of an action, such as the choices of a
+    /// [`crate::action::WeightedChoice`], are drawn in a cluster with edges labeled by
+    /// their weight.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {:?} {{\n", self.name);
+
+        for (idx, action) in self.actions.iter().enumerate() {
+            write_dot_action(
+                &mut dot,
+                &idx.to_string(),
+                &format!("{}: {}", idx, action.name()),
+                action.as_ref(),
+                1,
+            );
+        }
+
+        for idx in 1..self.actions.len() {
+            dot.push_str(&format!("    step{} -> step{};\n", idx - 1, idx));
+        }
+
+        dot.push('}');
+        dot
+    }
+
+    /// Starts the node, executing all its actions in order. Failures are only logged,
+    /// use [`Node::run`] to get them.
+    pub async fn start(&mut self) {
+        if let Err(e) = self.run().await {
+            event!(tracing::Level::ERROR, "{}", e);
+        }
+    }
+
+    /// Performs the actions of the node in order and returns the report of the run
+    /// with the outcome of each action.
+    ///
+    /// In [`ExecutionMode::StopOnError`], the run stops at the first failed action and
+    /// returns its error, the outcomes so far being kept in [`Node::report`].
+    #[instrument(name = "node_start", level = "info", skip(self), fields(node = %self.name))]
+    pub async fn run(&mut self) -> Result<NodeReport, NodeError> {
+        #[instrument(name = "action", level = "info", skip(ctx), fields(action = %action.name()))]
+        async fn run_action(action: &dyn Action, step: usize, ctx: Ctx) -> ActionOutcome {
+            {
+                let mut context = ctx.lock().await;
+                context.current_step = Some(step);
+                context.step_notifier.notify_waiters();
             }
 
-            run_action(&*action, self.ctx.clone()).await;
+            let started = Instant::now();
+            let result = action.perform(ctx).await;
+            if let Err(e) = &result {
+                event!(tracing::Level::ERROR, "Error performing action: {:?}", e);
+            }
+
+            ActionOutcome {
+                index: step,
+                name: action.name(),
+                duration: started.elapsed(),
+                result,
+            }
+        }
+
+        let actions: Vec<Box<dyn Action>> = self.actions.drain(..).collect();
+        let ctx = self.ctx.clone();
+        let mode = self.execution_mode;
+        let run = async move {
+            let mut outcomes = Vec::with_capacity(actions.len());
+            for (step, action) in actions.into_iter().enumerate() {
+                let outcome = run_action(&*action, step, ctx.clone()).await;
+                let failed = outcome.result.is_err();
+                outcomes.push(outcome);
+                if failed && mode == ExecutionMode::StopOnError {
+                    event!(
+                        tracing::Level::WARN,
+                        "Stopping after failed action {}",
+                        step
+                    );
+                    break;
+                }
+            }
+            outcomes
         }
+        .in_current_span();
+
+        let outcomes = match &self.runtime {
+            Some(runtime) => runtime.handle().spawn(run).await,
+            None => Ok(run.await),
+        };
 
         event!(tracing::Level::INFO, "All actions performed");
+        self.cancel().await;
         event!(tracing::Level::INFO, "Node {} finished", self.name);
         event!(
             tracing::Level::INFO,
             "Received messages: {:?}",
             self.ctx.lock().await.receive_events
         );
+
+        let outcomes = outcomes.map_err(|e| NodeError::RuntimeError(e.to_string()))?;
+        let failed = match outcomes.last() {
+            Some(ActionOutcome {
+                index,
+                result: Err(error),
+                ..
+            }) if self.execution_mode == ExecutionMode::StopOnError => {
+                Some(NodeError::ActionFailed {
+                    index: *index,
+                    error: error.clone(),
+                })
+            }
+            _ => None,
+        };
+        self.outcomes.extend(outcomes);
+        match failed {
+            Some(error) => Err(error),
+            None => Ok(self.report().await),
+        }
+    }
+}
+
+/// Writes the Graphviz node `step{id}` of `action`, in a `cluster_{id}` subgraph with its
+/// sub-actions if it has any.
+fn write_dot_action(dot: &mut String, id: &str, label: &str, action: &dyn Action, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let children = action.children();
+    if children.is_empty() {
+        dot.push_str(&format!("{}step{} [label=\"{}\"];\n", indent, id, label));
+        return;
+    }
+
+    dot.push_str(&format!("{}subgraph cluster_{} {{\n", indent, id));
+    dot.push_str(&format!(
+        "{}    step{} [label=\"{}\"];\n",
+        indent, id, label
+    ));
+    for (idx, (weight, child)) in children.into_iter().enumerate() {
+        let child_id = format!("{}_{}", id, idx);
+        write_dot_action(dot, &child_id, &child.name(), child, depth + 1);
+        dot.push_str(&format!(
+            "{}    step{} -> step{} [label=\"{}\"];\n",
+            indent, id, child_id, weight
+        ));
+    }
+    dot.push_str(&format!("{}}}\n", indent));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::{io::AsyncWriteExt, net::UdpSocket, task::JoinSet, time::Instant};
+
+    use crate::{
+        action::{Action, ActionError, SetPhase, Sleep, WeightedChoice},
+        protocol::{
+            Protocol,
+            ip::{
+                AssertChunkCadence, Bind, BufferMatch, Connect, MessagesPredicate,
+                ReceivePredicate, Send, SendMode, Wait, WaitEvent,
+            },
+        },
+    };
+
+    use super::{BindSummary, ExecutionMode, Node, NodeError, ScenarioError};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_receives_are_monotonic() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new("127.0.1.8:3000".parse().unwrap())
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut set = JoinSet::new();
+        for i in 0..8u8 {
+            set.spawn(async move {
+                let socket = UdpSocket::bind("127.0.1.9:0").await.unwrap();
+                let mut stream = tokio::net::TcpStream::connect("127.0.1.8:3000")
+                    .await
+                    .unwrap();
+                for _ in 0..10 {
+                    socket.send_to(&[i], "127.0.1.8:49999").await.unwrap();
+                    stream.write_all(&[i]).await.unwrap();
+                }
+            });
+        }
+        set.join_all().await;
+
+        for _ in 0..50 {
+            if ctx.lock().await.receive_events.len() >= 80 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let context = ctx.lock().await;
+        assert!(context.receive_events.len() >= 80);
+        assert_eq!(context.connect_events.len(), 8);
+        context.assert_monotonic();
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut node = Node::new("test-node");
+        node.add_action(Bind::new("127.0.0.1:3000".parse().unwrap()));
+        node.add_action(Sleep::new(100));
+        node.add_action(Connect::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:4000".parse().unwrap(),
+            1000,
+        ));
+
+        let expected = [
+            "digraph \"test-node\" {",
+            "    step0 [label=\"0: BIND\"];",
+            "    step1 [label=\"1: SLEEP\"];",
+            "    step2 [label=\"2: CONNECT\"];",
+            "    step0 -> step1;",
+            "    step1 -> step2;",
+            "}",
+        ]
+        .join("\n");
+
+        assert_eq!(node.to_dot(), expected);
+    }
+
+    #[test]
+    fn test_to_dot_weighted_choice() {
+        let mut node = Node::new("test-node");
+        node.add_action(Sleep::new(100));
+        node.add_action(WeightedChoice::new(vec![
+            (3, Box::new(SetPhase::new("often"))),
+            (1, Box::new(Sleep::new(10))),
+        ]));
+
+        let expected = [
+            "digraph \"test-node\" {",
+            "    step0 [label=\"0: SLEEP\"];",
+            "    subgraph cluster_1 {",
+            "        step1 [label=\"1: WEIGHTED_CHOICE\"];",
+            "        step1_0 [label=\"SET_PHASE\"];",
+            "        step1 -> step1_0 [label=\"3\"];",
+            "        step1_1 [label=\"SLEEP\"];",
+            "        step1 -> step1_1 [label=\"1\"];",
+            "    }",
+            "    step0 -> step1;",
+            "}",
+        ]
+        .join("\n");
+
+        assert_eq!(node.to_dot(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_await_step() {
+        let server = "127.0.1.45:3000".parse().unwrap();
+
+        let mut a = Node::new("a");
+        a.add_action(Bind::new(server).with_protocol(Protocol::Udp));
+        a.add_action(Sleep::new(200));
+        a.add_action(Sleep::new(200));
+
+        let mut b = Node::new("b");
+        b.add_action(Send::new(
+            SendMode::UdpUnicast,
+            "127.0.1.46:0".parse().unwrap(),
+            server,
+            vec![1],
+        ));
+
+        // B only sends once A has started its third action
+        let reached = a.await_step(2, Duration::from_secs(1));
+        let never = a.await_step(3, Duration::from_millis(100));
+        let start = Instant::now();
+        let a_ctx = a.ctx();
+        let mut set = JoinSet::new();
+        set.spawn(async move { a.start().await });
+        set.spawn(async move {
+            reached.await.unwrap();
+            b.start().await;
+        });
+        set.join_all().await;
+
+        let received = a_ctx.lock().await.receive_events.clone();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].instant.duration_since(start) >= Duration::from_millis(200));
+        assert_eq!(never.await, Err(ActionError::WaitError));
+    }
+
+    /// Blocks the thread running it, as a CPU-heavy action would.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Busy {
+        duration_ms: u64,
+    }
+
+    #[async_trait::async_trait]
+    #[typetag::serde]
+    impl Action for Busy {
+        fn name(&self) -> String {
+            "BUSY".into()
+        }
+
+        async fn perform(&self, _ctx: crate::Ctx) -> Result<(), ActionError> {
+            std::thread::sleep(Duration::from_millis(self.duration_ms));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedicated_runtime() {
+        let server: std::net::SocketAddr = "127.0.1.64:3000".parse().unwrap();
+
+        let mut busy = Node::new("busy").with_dedicated_runtime().unwrap();
+        busy.add_action(Busy { duration_ms: 500 });
+        let mut receiver = Node::new("receiver").with_dedicated_runtime().unwrap();
+        receiver.add_action(Bind::new(server).with_protocol(Protocol::Udp));
+        receiver.add_action(Sleep::new(700));
+        let ctx = receiver.ctx();
+
+        let mut scenario = crate::Scenario::new();
+        scenario.add_node(busy);
+        scenario.add_node(receiver);
+
+        // Send from a thread of its own, unaffected by any runtime being blocked
+        let send = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let socket = std::net::UdpSocket::bind("127.0.1.65:0").unwrap();
+            let sent = Instant::now();
+            socket.send_to(b"ping", server).unwrap();
+            sent
+        });
+        scenario.run().await;
+        let sent = send.join().unwrap();
+
+        // The receive is recorded while the busy node still blocks its own thread
+        let received = ctx.lock().await.receive_events.clone();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].instant.duration_since(sent) < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_report_bind_summaries() {
+        let server: std::net::SocketAddr = "127.0.1.105:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        for protocol in [Protocol::Tcp, Protocol::Udp] {
+            Bind::new(server)
+                .with_protocol(protocol)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+        }
+        while ctx.lock().await.bound.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let _stream = tokio::net::TcpStream::connect(server).await.unwrap();
+        let socket = UdpSocket::bind("127.0.1.106:0").await.unwrap();
+        socket.send_to(b"datagram", server).await.unwrap();
+        for _ in 0..50 {
+            let binds = node.report().await.binds;
+            if binds[0].accepted == 1 && binds[0].datagrams == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let report = node.report().await;
+        assert_eq!(report.name, "test-node");
+        assert_eq!(
+            report.binds,
+            vec![BindSummary {
+                addr: server,
+                tcp_ready: true,
+                udp_ready: true,
+                accepted: 1,
+                datagrams: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_and_restore_state() {
+        let client: std::net::SocketAddr = "127.0.1.118:0".parse().unwrap();
+        let first: std::net::SocketAddr = "127.0.1.117:3000".parse().unwrap();
+        let second: std::net::SocketAddr = "127.0.1.117:3001".parse().unwrap();
+        let received = move |to, buffer: &[u8]| {
+            Wait::new(WaitEvent::Messages(
+                ReceivePredicate::new(vec![MessagesPredicate {
+                    from: client,
+                    to,
+                    buffer: buffer.to_vec(),
+                    matching: BufferMatch::Exact,
+                }])
+                .with_timeout(1000),
+            ))
+        };
+        let send = |to, buffer: &[u8]| Send::new(SendMode::UdpUnicast, client, to, buffer.to_vec());
+
+        // First half of the scenario
+        let mut node = Node::new("test-node");
+        node.add_action(Bind::new(first).with_protocol(Protocol::Udp));
+        node.add_action(Sleep::new(50));
+        node.add_action(send(first, b"one"));
+        node.add_action(received(first, b"one"));
+        node.start().await;
+        assert_eq!(node.ctx().lock().await.receive_events.len(), 1);
+
+        // Second half, saved before it runs
+        node.add_action(Bind::new(second).with_protocol(Protocol::Udp));
+        node.add_action(Sleep::new(50));
+        let path = std::env::temp_dir().join(format!(
+            "nseqe-test-save-and-restore-state-{}.json",
+            std::process::id()
+        ));
+        node.save_state(&path).await.unwrap();
+
+        let mut restored = Node::new("restored-node");
+        restored.restore_state(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(restored.actions().len(), 2);
+        let saved = node.ctx().lock().await.receive_events.clone();
+        let events = restored.ctx().lock().await.receive_events.clone();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].buffer, saved[0].buffer);
+        assert!(events[0].instant.duration_since(saved[0].instant) < Duration::from_millis(5));
+        assert!(saved[0].instant.duration_since(events[0].instant) < Duration::from_millis(5));
+
+        restored.add_action(send(second, b"two"));
+        restored.add_action(received(second, b"two"));
+        restored.add_action(AssertChunkCadence::new(client, 10_000, 2));
+        let ctx = restored.ctx();
+        let mut results = Vec::new();
+        for action in restored.actions() {
+            results.push(action.perform(ctx.clone()).await);
+        }
+        assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_failed_actions() {
+        let mut node = Node::new("test-node");
+        node.add_action(Sleep::new(10));
+        // Nothing listens on this address
+        node.add_action(Connect::new(
+            "127.0.1.119:0".parse().unwrap(),
+            "127.0.1.120:3000".parse().unwrap(),
+            1000,
+        ));
+        node.add_action(Sleep::new(10));
+
+        let report = node.run().await.unwrap();
+        assert_eq!(report.name, "test-node");
+        assert_eq!(report.actions.len(), 3);
+        assert!(report.actions[0].result.is_ok());
+        assert!(report.actions[0].duration >= Duration::from_millis(10));
+        assert!(report.actions[2].result.is_ok());
+
+        let failures: Vec<_> = report.failures().collect();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].index, 1);
+        assert_eq!(failures[0].name, "CONNECT");
+        assert!(matches!(
+            failures[0].result,
+            Err(ActionError::ConnectError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stop_on_error() {
+        let server: SocketAddr = "127.0.1.122:3000".parse().unwrap();
+        let _held = std::net::TcpListener::bind(server).unwrap();
+
+        let failing_node = |mode| {
+            let mut node = Node::new("test-node");
+            node.set_execution_mode(mode);
+            node.add_action(Bind::new(server).with_protocol(Protocol::Tcp));
+            node.add_action(Send::new(
+                SendMode::UdpUnicast,
+                "127.0.1.123:0".parse().unwrap(),
+                "127.0.1.122:3001".parse().unwrap(),
+                vec![1, 2, 3],
+            ));
+            node
+        };
+
+        let mut node = failing_node(ExecutionMode::StopOnError);
+        assert!(matches!(
+            node.run().await,
+            Err(NodeError::ActionFailed {
+                index: 0,
+                error: ActionError::BindError(_)
+            })
+        ));
+        assert_eq!(node.report().await.actions.len(), 1);
+        assert!(node.ctx().lock().await.send_events.is_empty());
+
+        let mut node = failing_node(ExecutionMode::Continue);
+        let report = node.run().await.unwrap();
+        assert_eq!(report.actions.len(), 2);
+        assert_eq!(node.ctx().lock().await.send_events.len(), 1);
+    }
+
+    #[test]
+    fn test_from_json_file() {
+        let mut node = Node::new("test-node");
+        node.add_action(Bind::new("127.0.1.127:3000".parse().unwrap()));
+        node.add_action(Sleep::new(100));
+        node.add_action(Send::new(
+            SendMode::UdpUnicast,
+            "127.0.1.128:0".parse().unwrap(),
+            "127.0.1.127:3000".parse().unwrap(),
+            vec![1, 2, 3],
+        ));
+
+        let path = std::env::temp_dir().join(format!(
+            "nseqe-test-from-json-file-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(&path, serde_json::to_vec(node.actions()).unwrap()).unwrap();
+        let loaded = Node::from_json_file("loaded-node", &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.name(), "loaded-node");
+        assert_eq!(
+            serde_json::to_value(loaded.actions()).unwrap(),
+            serde_json::to_value(node.actions()).unwrap()
+        );
+
+        let invalid = r#"[{"Sleep": {"duration_ms": 100}}, {"Teleport": {}}]"#;
+        assert!(matches!(
+            Node::from_reader("test-node", invalid.as_bytes()),
+            Err(ScenarioError::InvalidAction { index: 1, .. })
+        ));
+        assert!(matches!(
+            Node::from_reader("test-node", r#"{"Sleep": {}}"#.as_bytes()),
+            Err(ScenarioError::InvalidScenario(_))
+        ));
     }
 }
@@ -0,0 +1,1933 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{Ctx, DisconnectReason, ReceiveEvent},
+    protocol::ip::wait::addr_match,
+};
+
+/// Asserts the number of connections established since the node started,
+/// counting accepted and outgoing connections, including the closed ones.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertTotalConnections {
+    expected: u64,
+}
+
+impl AssertTotalConnections {
+    pub fn new(expected: u64) -> Self {
+        AssertTotalConnections { expected }
+    }
+
+    pub fn expected(&self) -> u64 {
+        self.expected
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertTotalConnections {
+    fn name(&self) -> String {
+        "ASSERT_TOTAL_CONNECTIONS".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let total = ctx.lock().await.total_connections;
+        event!(
+            tracing::Level::INFO,
+            "Asserting {} total connections, found {}",
+            self.expected,
+            total
+        );
+
+        if total != self.expected {
+            return Err(ActionError::AssertError(format!(
+                "Expected {} total connections, found {}",
+                self.expected, total
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the node never held more than `max` receive events at once.
+///
+/// See [`crate::NodeContext::max_event_backlog`], the receive log having no cap.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertBacklogBelow {
+    max: usize,
+}
+
+impl AssertBacklogBelow {
+    pub fn new(max: usize) -> Self {
+        AssertBacklogBelow { max }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertBacklogBelow {
+    fn name(&self) -> String {
+        "ASSERT_BACKLOG_BELOW".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let peak = ctx.lock().await.max_event_backlog;
+        event!(
+            tracing::Level::INFO,
+            "Asserting a receive backlog of at most {} events, peaked at {}",
+            self.max,
+            peak
+        );
+
+        if peak > self.max {
+            return Err(ActionError::AssertError(format!(
+                "Receive backlog peaked at {} events, above {}",
+                peak, self.max
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the ordered concatenation of the messages received from `from`
+/// on `to` is exactly `expected`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertTranscript {
+    from: SocketAddr,
+    to: SocketAddr,
+    expected: Vec<u8>,
+}
+
+impl AssertTranscript {
+    pub fn new(from: SocketAddr, to: SocketAddr, expected: Vec<u8>) -> Self {
+        AssertTranscript { from, to, expected }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn expected(&self) -> &[u8] {
+        &self.expected
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertTranscript {
+    fn name(&self) -> String {
+        "ASSERT_TRANSCRIPT".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let transcript = {
+            let context = ctx.lock().await;
+            transcript(self.from, self.to, &context.receive_events)
+        };
+
+        match transcript_diff(&self.expected, &transcript) {
+            None => {
+                event!(
+                    tracing::Level::INFO,
+                    "Transcript from {} to {} matches",
+                    self.from,
+                    self.to
+                );
+                Ok(())
+            }
+            Some(diff) => {
+                event!(
+                    tracing::Level::ERROR,
+                    "Transcript from {} to {} differs: {}",
+                    self.from,
+                    self.to,
+                    diff
+                );
+                Err(ActionError::AssertError(diff))
+            }
+        }
+    }
+}
+
+/// Concatenates, in instant then recording order, the buffers received from `from` on `to`.
+fn transcript(from: SocketAddr, to: SocketAddr, events: &[ReceiveEvent]) -> Vec<u8> {
+    let mut matching: Vec<&ReceiveEvent> = events
+        .iter()
+        .filter(|e| addr_match(from, e.from) && addr_match(to, e.to))
+        .collect();
+    matching.sort_by_key(|e| (e.instant, e.seq));
+
+    matching
+        .iter()
+        .flat_map(|e| e.buffer.iter().copied())
+        .collect()
+}
+
+/// Describes the first difference between two transcripts, if any.
+fn transcript_diff(expected: &[u8], actual: &[u8]) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let offset = expected
+        .iter()
+        .zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or(expected.len().min(actual.len()));
+
+    // Show a few bytes around the first difference
+    let start = offset.saturating_sub(4);
+    let window =
+        |bytes: &[u8]| bytes[start.min(bytes.len())..(offset + 4).min(bytes.len())].to_vec();
+
+    Some(format!(
+        "transcripts differ at byte {} (expected {} bytes, got {}): expected {:02x?}, got {:02x?}",
+        offset,
+        expected.len(),
+        actual.len(),
+        window(expected),
+        window(actual)
+    ))
+}
+
+/// Asserts the standard deviation of the inter-arrival delays of the messages
+/// received from `from` stays under `max_stddev_ms`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertJitter {
+    from: SocketAddr,
+    max_stddev_ms: f64,
+    min_count: usize,
+}
+
+impl AssertJitter {
+    pub fn new(from: SocketAddr, max_stddev_ms: f64, min_count: usize) -> Self {
+        AssertJitter {
+            from,
+            max_stddev_ms,
+            min_count,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn max_stddev_ms(&self) -> f64 {
+        self.max_stddev_ms
+    }
+
+    pub fn min_count(&self) -> usize {
+        self.min_count
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertJitter {
+    fn name(&self) -> String {
+        "ASSERT_JITTER".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let instants: Vec<Instant> = {
+            let context = ctx.lock().await;
+            context
+                .receive_events
+                .iter()
+                .filter(|e| addr_match(self.from, e.from))
+                .map(|e| e.instant)
+                .collect()
+        };
+
+        if instants.len() < self.min_count.max(2) {
+            return Err(ActionError::AssertError(format!(
+                "Expected at least {} messages from {} to compute the jitter, got {}",
+                self.min_count.max(2),
+                self.from,
+                instants.len()
+            )));
+        }
+
+        let jitter = jitter_ms(&instants);
+        event!(
+            tracing::Level::INFO,
+            "Jitter of messages from {} is {:.3}ms",
+            self.from,
+            jitter
+        );
+
+        if jitter > self.max_stddev_ms {
+            return Err(ActionError::AssertError(format!(
+                "Jitter of messages from {} is {:.3}ms, above {:.3}ms",
+                self.from, jitter, self.max_stddev_ms
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the standard deviation, in milliseconds, of the delays between consecutive instants.
+fn jitter_ms(instants: &[Instant]) -> f64 {
+    let deltas: Vec<f64> = instants
+        .windows(2)
+        .map(|pair| pair[1].duration_since(pair[0]).as_secs_f64() * 1000.0)
+        .collect();
+
+    if deltas.is_empty() {
+        return 0.0;
+    }
+
+    let mean = deltas.iter().sum::<f64>() / deltas.len() as f64;
+    let variance = deltas.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / deltas.len() as f64;
+    variance.sqrt()
+}
+
+/// Asserts at least `min_chunks` messages were received from `from`, each one
+/// at most `max_gap_ms` after the previous one, to check a server streams steadily.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertChunkCadence {
+    from: SocketAddr,
+    max_gap_ms: u64,
+    min_chunks: usize,
+}
+
+impl AssertChunkCadence {
+    pub fn new(from: SocketAddr, max_gap_ms: u64, min_chunks: usize) -> Self {
+        AssertChunkCadence {
+            from,
+            max_gap_ms,
+            min_chunks,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn max_gap_ms(&self) -> u64 {
+        self.max_gap_ms
+    }
+
+    pub fn min_chunks(&self) -> usize {
+        self.min_chunks
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertChunkCadence {
+    fn name(&self) -> String {
+        "ASSERT_CHUNK_CADENCE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let mut instants: Vec<(Instant, u64)> = {
+            let context = ctx.lock().await;
+            context
+                .receive_events
+                .iter()
+                .filter(|e| addr_match(self.from, e.from))
+                .map(|e| (e.instant, e.seq))
+                .collect()
+        };
+        instants.sort();
+
+        if instants.len() < self.min_chunks {
+            return Err(ActionError::AssertError(format!(
+                "Expected at least {} chunks from {}, got {}",
+                self.min_chunks,
+                self.from,
+                instants.len()
+            )));
+        }
+
+        let max_gap = Duration::from_millis(self.max_gap_ms);
+        if let Some((idx, gap)) = instants
+            .windows(2)
+            .map(|pair| pair[1].0 - pair[0].0)
+            .enumerate()
+            .find(|(_, gap)| *gap > max_gap)
+        {
+            return Err(ActionError::AssertError(format!(
+                "Chunk {} from {} arrived {:?} after the previous one, expected at most {}ms",
+                idx + 1,
+                self.from,
+                gap,
+                self.max_gap_ms
+            )));
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "{} chunks from {} arrived at most {}ms apart",
+            instants.len(),
+            self.from,
+            self.max_gap_ms
+        );
+        Ok(())
+    }
+}
+
+/// Asserts the messages received from `from` did not arrive faster than `max_bps` bytes per second.
+///
+/// The rate is measured between the first and the last matching message,
+/// a single message is always accepted.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertReceiveRate {
+    from: SocketAddr,
+    max_bps: u64,
+}
+
+impl AssertReceiveRate {
+    pub fn new(from: SocketAddr, max_bps: u64) -> Self {
+        AssertReceiveRate { from, max_bps }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn max_bps(&self) -> u64 {
+        self.max_bps
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertReceiveRate {
+    fn name(&self) -> String {
+        "ASSERT_RECEIVE_RATE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+        let events: Vec<&ReceiveEvent> = context
+            .receive_events
+            .iter()
+            .filter(|e| addr_match(self.from, e.from))
+            .collect();
+
+        let (Some(first), Some(last)) = (events.first(), events.last()) else {
+            return Ok(());
+        };
+        if events.len() < 2 {
+            event!(
+                tracing::Level::DEBUG,
+                "Single message from {}, receive rate not measured",
+                self.from
+            );
+            return Ok(());
+        }
+
+        let bytes: usize = events.iter().map(|e| e.buffer.len()).sum();
+        let span = last.instant.duration_since(first.instant).as_secs_f64();
+        let rate = bytes as f64 / span;
+        event!(
+            tracing::Level::INFO,
+            "Received {} bytes from {} in {:.3}s ({:.0} B/s)",
+            bytes,
+            self.from,
+            span,
+            rate
+        );
+
+        if rate > self.max_bps as f64 {
+            return Err(ActionError::AssertError(format!(
+                "Received {:.0} B/s from {}, above {} B/s",
+                rate, self.from, self.max_bps
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the connections to `to` were not established faster than `max_cps` per second.
+///
+/// The rate is measured between consecutive connections, from the shortest gap,
+/// so that a burst fails the assertion however long the connections last overall.
+/// A single connection is always accepted. A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertConnectRate {
+    to: SocketAddr,
+    max_cps: f64,
+}
+
+impl AssertConnectRate {
+    pub fn new(to: SocketAddr, max_cps: f64) -> Self {
+        AssertConnectRate { to, max_cps }
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn max_cps(&self) -> f64 {
+        self.max_cps
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertConnectRate {
+    fn name(&self) -> String {
+        "ASSERT_CONNECT_RATE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+        let mut instants: Vec<Instant> = context
+            .connect_events
+            .iter()
+            .filter(|e| addr_match(self.to, e.to))
+            .map(|e| e.instant)
+            .collect();
+        instants.sort();
+
+        let Some(gap) = instants
+            .windows(2)
+            .map(|pair| pair[1].duration_since(pair[0]))
+            .min()
+        else {
+            event!(
+                tracing::Level::DEBUG,
+                "Fewer than two connections to {}, connect rate not measured",
+                self.to
+            );
+            return Ok(());
+        };
+
+        // Simultaneous connections are an infinite rate
+        let rate = 1.0 / gap.as_secs_f64();
+        event!(
+            tracing::Level::INFO,
+            "{} connections to {}, {:?} apart at least ({:.1}/s)",
+            instants.len(),
+            self.to,
+            gap,
+            rate
+        );
+
+        if rate > self.max_cps {
+            return Err(ActionError::AssertError(format!(
+                "{:.1} connections per second to {}, above {:.1}",
+                rate, self.to, self.max_cps
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the node never connected to `to`, neither in the recorded connect
+/// events nor in the streams still open. A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertNeverConnected {
+    to: SocketAddr,
+}
+
+impl AssertNeverConnected {
+    pub fn new(to: SocketAddr) -> Self {
+        AssertNeverConnected { to }
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertNeverConnected {
+    fn name(&self) -> String {
+        "ASSERT_NEVER_CONNECTED".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+
+        if let Some(connect) = context
+            .connect_events
+            .iter()
+            .find(|e| addr_match(self.to, e.to))
+        {
+            return Err(ActionError::AssertError(format!(
+                "Connection from {} to {} was established at {:?}",
+                connect.from, connect.to, connect.instant
+            )));
+        }
+
+        if let Some(peer) = context
+            .tcp_streams
+            .keys()
+            .find(|peer| addr_match(self.to, **peer))
+        {
+            return Err(ActionError::AssertError(format!(
+                "A stream to {} is open",
+                peer
+            )));
+        }
+
+        event!(tracing::Level::INFO, "Never connected to {}", self.to);
+        Ok(())
+    }
+}
+
+/// Asserts no message received from `from` contains `needle`.
+///
+/// With `with_stream`, the messages are also concatenated so that a needle
+/// split across several messages is found too.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertNotContains {
+    from: SocketAddr,
+    needle: Vec<u8>,
+    #[serde(default)]
+    stream: bool,
+}
+
+impl AssertNotContains {
+    pub fn new(from: SocketAddr, needle: Vec<u8>) -> Self {
+        AssertNotContains {
+            from,
+            needle,
+            stream: false,
+        }
+    }
+
+    /// Also searches the concatenation of the received messages.
+    pub fn with_stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn needle(&self) -> &[u8] {
+        &self.needle
+    }
+
+    pub fn stream(&self) -> bool {
+        self.stream
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertNotContains {
+    fn name(&self) -> String {
+        "ASSERT_NOT_CONTAINS".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if self.needle.is_empty() {
+            return Ok(());
+        }
+
+        let context = ctx.lock().await;
+        let mut events: Vec<&ReceiveEvent> = context
+            .receive_events
+            .iter()
+            .filter(|e| addr_match(self.from, e.from))
+            .collect();
+        events.sort_by_key(|e| (e.instant, e.seq));
+
+        let find = |haystack: &[u8]| {
+            haystack
+                .windows(self.needle.len())
+                .position(|window| window == self.needle.as_slice())
+        };
+
+        for (idx, event) in events.iter().enumerate() {
+            if let Some(offset) = find(&event.buffer) {
+                return Err(ActionError::AssertError(format!(
+                    "Message {} from {} to {} at {:?} contains {:02x?} at byte {}",
+                    idx, event.from, event.to, event.instant, self.needle, offset
+                )));
+            }
+        }
+
+        if self.stream {
+            let stream: Vec<u8> = events
+                .iter()
+                .flat_map(|e| e.buffer.iter().copied())
+                .collect();
+            if let Some(offset) = find(&stream) {
+                return Err(ActionError::AssertError(format!(
+                    "Stream from {} contains {:02x?} at byte {}",
+                    self.from, self.needle, offset
+                )));
+            }
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "No message from {} contains {:02x?}",
+            self.from,
+            self.needle
+        );
+        Ok(())
+    }
+}
+
+/// Asserts the first connection to the listener bound on `to` was established at
+/// most `max_ms` after the listener was bound. A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertConnectAfterBindWithin {
+    to: SocketAddr,
+    max_ms: u64,
+}
+
+impl AssertConnectAfterBindWithin {
+    pub fn new(to: SocketAddr, max_ms: u64) -> Self {
+        AssertConnectAfterBindWithin { to, max_ms }
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn max_ms(&self) -> u64 {
+        self.max_ms
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertConnectAfterBindWithin {
+    fn name(&self) -> String {
+        "ASSERT_CONNECT_AFTER_BIND_WITHIN".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+
+        let Some((addr, bound_at)) = context
+            .bind_instants
+            .iter()
+            .filter(|(addr, _)| addr_match(self.to, **addr))
+            .min_by_key(|(_, instant)| **instant)
+        else {
+            return Err(ActionError::AssertError(format!(
+                "No listener bound on {}",
+                self.to
+            )));
+        };
+
+        let Some(connect) = context
+            .connect_events
+            .iter()
+            .filter(|e| e.to == *addr && e.instant >= *bound_at)
+            .min_by_key(|e| (e.instant, e.seq))
+        else {
+            return Err(ActionError::AssertError(format!(
+                "No connection to {} since it was bound",
+                addr
+            )));
+        };
+
+        let delay = connect.instant - *bound_at;
+        event!(
+            tracing::Level::INFO,
+            "First connection to {} established {:?} after binding",
+            addr,
+            delay
+        );
+
+        if delay > Duration::from_millis(self.max_ms) {
+            return Err(ActionError::AssertError(format!(
+                "First connection to {} established {:?} after binding, expected at most {}ms",
+                addr, delay, self.max_ms
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the messages received from `from`, concatenated, have a Shannon entropy
+/// of at least `min_bits_per_byte`, to check a peer output looks encrypted or random.
+/// A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertEntropyAbove {
+    from: SocketAddr,
+    min_bits_per_byte: f64,
+}
+
+impl AssertEntropyAbove {
+    pub fn new(from: SocketAddr, min_bits_per_byte: f64) -> Self {
+        AssertEntropyAbove {
+            from,
+            min_bits_per_byte,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn min_bits_per_byte(&self) -> f64 {
+        self.min_bits_per_byte
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertEntropyAbove {
+    fn name(&self) -> String {
+        "ASSERT_ENTROPY_ABOVE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let mut counts = [0u64; 256];
+        let mut total = 0u64;
+        for event in ctx
+            .lock()
+            .await
+            .receive_events
+            .iter()
+            .filter(|e| addr_match(self.from, e.from))
+        {
+            for byte in &event.buffer {
+                counts[*byte as usize] += 1;
+            }
+            total += event.buffer.len() as u64;
+        }
+
+        let entropy = shannon_entropy(&counts, total);
+        event!(
+            tracing::Level::INFO,
+            "Entropy of the {} bytes received from {}: {:.3} bits per byte",
+            total,
+            self.from,
+            entropy
+        );
+
+        if entropy < self.min_bits_per_byte {
+            return Err(ActionError::AssertError(format!(
+                "Entropy of the {} bytes received from {} is {:.3} bits per byte, expected at least {}",
+                total, self.from, entropy, self.min_bits_per_byte
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the Shannon entropy in bits per byte of `total` bytes with the given
+/// occurrence count of each byte value, 0 when there are no bytes.
+fn shannon_entropy(counts: &[u64; 256], total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / total as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Asserts the first connection coming from `first` was established before
+/// the first one coming from `second`. A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertConnectOrder {
+    first: SocketAddr,
+    second: SocketAddr,
+}
+
+impl AssertConnectOrder {
+    pub fn new(first: SocketAddr, second: SocketAddr) -> Self {
+        AssertConnectOrder { first, second }
+    }
+
+    pub fn first(&self) -> &SocketAddr {
+        &self.first
+    }
+
+    pub fn second(&self) -> &SocketAddr {
+        &self.second
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertConnectOrder {
+    fn name(&self) -> String {
+        "ASSERT_CONNECT_ORDER".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+        let earliest = |peer: SocketAddr| {
+            context
+                .connect_events
+                .iter()
+                .filter(|e| addr_match(peer, e.from))
+                .map(|e| (e.instant, e.seq))
+                .min()
+                .ok_or_else(|| ActionError::AssertError(format!("No connection from {}", peer)))
+        };
+        let (first, second) = (earliest(self.first)?, earliest(self.second)?);
+
+        event!(
+            tracing::Level::INFO,
+            "First connection from {} at {:?}, from {} at {:?}",
+            self.first,
+            first.0,
+            self.second,
+            second.0
+        );
+
+        // Connections at the same instant are ordered by their recording order
+        if first >= second {
+            return Err(ActionError::AssertError(format!(
+                "{} connected {:?} after {}",
+                self.first,
+                first.0.duration_since(second.0),
+                self.second
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the last connection accepted from `peer` was closed for the given reason.
+/// A port of 0 matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertCloseReason {
+    peer: SocketAddr,
+    reason: DisconnectReason,
+}
+
+impl AssertCloseReason {
+    pub fn new(peer: SocketAddr, reason: DisconnectReason) -> Self {
+        AssertCloseReason { peer, reason }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+
+    pub fn reason(&self) -> DisconnectReason {
+        self.reason
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertCloseReason {
+    fn name(&self) -> String {
+        "ASSERT_CLOSE_REASON".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+        let Some(disconnect) = context
+            .disconnect_events
+            .iter()
+            .filter(|e| addr_match(self.peer, e.from))
+            .max_by_key(|e| (e.instant, e.seq))
+        else {
+            return Err(ActionError::AssertError(format!(
+                "No connection from {} was closed",
+                self.peer
+            )));
+        };
+
+        event!(
+            tracing::Level::INFO,
+            "Connection from {} closed with {:?}",
+            disconnect.from,
+            disconnect.reason
+        );
+
+        if disconnect.reason != self.reason {
+            return Err(ActionError::AssertError(format!(
+                "Connection from {} closed with {:?}, expected {:?}",
+                disconnect.from, disconnect.reason, self.reason
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the node bound exactly the `expected` listeners, no more, no less.
+/// A port of 0 matches any port, such as an ephemeral one.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertBound {
+    expected: Vec<SocketAddr>,
+}
+
+impl AssertBound {
+    pub fn new(expected: Vec<SocketAddr>) -> Self {
+        AssertBound { expected }
+    }
+
+    pub fn expected(&self) -> &[SocketAddr] {
+        &self.expected
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertBound {
+    fn name(&self) -> String {
+        "ASSERT_BOUND".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let bound = ctx.lock().await.bound.clone();
+        event!(tracing::Level::INFO, "Bound addresses: {:?}", bound);
+
+        let missing: Vec<&SocketAddr> = self
+            .expected
+            .iter()
+            .filter(|expected| !bound.iter().any(|addr| addr_match(**expected, *addr)))
+            .collect();
+        let unexpected: Vec<&SocketAddr> = bound
+            .iter()
+            .filter(|addr| {
+                !self
+                    .expected
+                    .iter()
+                    .any(|expected| addr_match(*expected, **addr))
+            })
+            .collect();
+
+        if !missing.is_empty() || !unexpected.is_empty() {
+            return Err(ActionError::AssertError(format!(
+                "Bound addresses differ, missing {:?}, unexpected {:?}",
+                missing, unexpected
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the messages received from `from` carry consecutive sequence numbers,
+/// read as a `width` bytes unsigned integer at `offset` of each buffer.
+///
+/// Every number must be the previous one plus one, wrapping around at the maximum
+/// value of `width` bytes. The first violation is reported.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertSequence {
+    from: SocketAddr,
+    offset: usize,
+    width: usize,
+    big_endian: bool,
+}
+
+impl AssertSequence {
+    pub fn new(from: SocketAddr, offset: usize, width: usize, big_endian: bool) -> Self {
+        AssertSequence {
+            from,
+            offset,
+            width,
+            big_endian,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Reads the sequence number of `buffer`, if it is long enough.
+    fn sequence(&self, buffer: &[u8]) -> Option<u64> {
+        let bytes = buffer.get(self.offset..self.offset.checked_add(self.width)?)?;
+        let fold = |acc: u64, byte: &u8| (acc << 8) | *byte as u64;
+
+        Some(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertSequence {
+    fn name(&self) -> String {
+        "ASSERT_SEQUENCE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if !(1..=8).contains(&self.width) {
+            return Err(ActionError::AssertError(format!(
+                "Sequence width must be between 1 and 8 bytes, got {}",
+                self.width
+            )));
+        }
+        let mask = u64::MAX >> (64 - 8 * self.width);
+
+        let context = ctx.lock().await;
+        let mut previous: Option<u64> = None;
+        for (index, event) in context
+            .receive_events
+            .iter()
+            .filter(|e| addr_match(self.from, e.from))
+            .enumerate()
+        {
+            let Some(sequence) = self.sequence(&event.buffer) else {
+                return Err(ActionError::AssertError(format!(
+                    "Message {} from {} is {} bytes long, too short for a sequence number at {}..{}",
+                    index,
+                    event.from,
+                    event.buffer.len(),
+                    self.offset,
+                    self.offset + self.width
+                )));
+            };
+
+            if let Some(previous) = previous {
+                let expected = previous.wrapping_add(1) & mask;
+                if sequence != expected {
+                    return Err(ActionError::AssertError(format!(
+                        "Message {} from {} has sequence number {} after {}, expected {}",
+                        index, event.from, sequence, previous, expected
+                    )));
+                }
+            }
+            previous = Some(sequence);
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Sequence numbers from {} are consecutive",
+            self.from
+        );
+
+        Ok(())
+    }
+}
+
+/// Asserts the node strictly alternated sending to and receiving from `peer`,
+/// as a half-duplex protocol would. Sends and receives are ordered by instant,
+/// and two consecutive sends or receives fail the assertion.
+///
+/// A port of 0 in `peer` matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertAlternating {
+    peer: SocketAddr,
+}
+
+impl AssertAlternating {
+    pub fn new(peer: SocketAddr) -> Self {
+        AssertAlternating { peer }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertAlternating {
+    fn name(&self) -> String {
+        "ASSERT_ALTERNATING".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        // (instant, seq, is_send) of every exchange with the peer
+        let mut exchanges: Vec<(Instant, u64, bool)> = {
+            let context = ctx.lock().await;
+            context
+                .send_events
+                .iter()
+                .filter(|e| addr_match(self.peer, e.to))
+                .map(|e| (e.instant, e.seq, true))
+                .chain(
+                    context
+                        .receive_events
+                        .iter()
+                        .filter(|e| addr_match(self.peer, e.from))
+                        .map(|e| (e.instant, e.seq, false)),
+                )
+                .collect()
+        };
+        exchanges.sort_by_key(|(instant, seq, _)| (*instant, *seq));
+
+        let kind = |is_send: bool| if is_send { "sends" } else { "receives" };
+        if let Some(index) = exchanges.windows(2).position(|pair| pair[0].2 == pair[1].2) {
+            return Err(ActionError::AssertError(format!(
+                "Two consecutive {} with {} at exchanges {} and {}",
+                kind(exchanges[index].2),
+                self.peer,
+                index,
+                index + 1
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asserts the frames decoded from `from` are exactly `expected_frames`, in order.
+///
+/// Each receive event is taken as one frame, which holds when the receiving
+/// [`super::Bind`] decodes length-prefixed frames. A port of 0 in `from` matches any port.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertFrameSequence {
+    from: SocketAddr,
+    expected_frames: Vec<Vec<u8>>,
+}
+
+impl AssertFrameSequence {
+    pub fn new(from: SocketAddr, expected_frames: Vec<Vec<u8>>) -> Self {
+        AssertFrameSequence {
+            from,
+            expected_frames,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn expected_frames(&self) -> &[Vec<u8>] {
+        &self.expected_frames
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertFrameSequence {
+    fn name(&self) -> String {
+        "ASSERT_FRAME_SEQUENCE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let frames: Vec<Vec<u8>> = {
+            let context = ctx.lock().await;
+            let mut events: Vec<&ReceiveEvent> = context
+                .receive_events
+                .iter()
+                .filter(|e| addr_match(self.from, e.from))
+                .collect();
+            events.sort_by_key(|e| (e.instant, e.seq));
+            events.iter().map(|e| e.buffer.clone()).collect()
+        };
+
+        if let Some(index) = frames
+            .iter()
+            .zip(&self.expected_frames)
+            .position(|(frame, expected)| frame != expected)
+        {
+            return Err(ActionError::AssertError(format!(
+                "Frame {} from {} is {:02x?}, expected {:02x?}",
+                index, self.from, frames[index], self.expected_frames[index]
+            )));
+        }
+
+        if frames.len() != self.expected_frames.len() {
+            return Err(ActionError::AssertError(format!(
+                "Expected {} frames from {}, got {}",
+                self.expected_frames.len(),
+                self.from,
+                frames.len()
+            )));
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "{} frames from {} in the expected order",
+            frames.len(),
+            self.from
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use tokio::{io::AsyncWriteExt, time::Instant};
+
+    use crate::{
+        DisconnectReason, MockClock, Node, ReceiveEvent, Rng,
+        action::{Action, ActionError},
+        node::{ConnectEvent, SendEvent},
+        protocol::{
+            Protocol,
+            ip::{Bind, CoalesceReceives, Connect, Framing},
+        },
+    };
+
+    use super::{
+        AssertAlternating, AssertBacklogBelow, AssertBound, AssertChunkCadence, AssertCloseReason,
+        AssertConnectAfterBindWithin, AssertConnectOrder, AssertConnectRate, AssertEntropyAbove,
+        AssertFrameSequence, AssertJitter, AssertNeverConnected, AssertNotContains,
+        AssertReceiveRate, AssertSequence, AssertTotalConnections, AssertTranscript, transcript,
+        transcript_diff,
+    };
+
+    #[tokio::test]
+    async fn test_assert_total_connections_counts_closed_connections() {
+        let server: SocketAddr = "127.0.1.4:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server).perform(ctx.clone()).await.unwrap();
+
+        let connect = Connect::new("127.0.1.5:0".parse().unwrap(), server, 1000);
+        connect.perform(ctx.clone()).await.unwrap();
+
+        // Disconnect by dropping the stream, then reconnect
+        ctx.lock().await.tcp_streams.remove(&server);
+        connect.perform(ctx.clone()).await.unwrap();
+
+        // Let the listener record both accepted connections
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // Both sides share the context: two outgoing and two accepted connections
+        assert_eq!(ctx.lock().await.tcp_streams.len(), 1);
+        assert!(
+            AssertTotalConnections::new(4)
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertTotalConnections::new(1).perform(ctx).await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assert_backlog_below() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let push = |context: &mut crate::NodeContext, count: usize| {
+            for _ in 0..count {
+                let event = ReceiveEvent {
+                    instant: context.clock.now(),
+                    seq: context.next_seq(),
+                    from,
+                    to: "127.0.0.1:4000".parse().unwrap(),
+                    buffer: vec![1],
+                    truncated: false,
+                    phase: None,
+                };
+                context.push_receive_event(event);
+            }
+        };
+
+        // The flood is coalesced into a single event, then a few more arrive
+        push(&mut *ctx.lock().await, 100);
+        CoalesceReceives::new(from, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        push(&mut *ctx.lock().await, 10);
+
+        {
+            let context = ctx.lock().await;
+            assert_eq!(context.receive_events.len(), 11);
+            assert_eq!(context.max_event_backlog, 100);
+        }
+
+        assert!(
+            AssertBacklogBelow::new(100)
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertBacklogBelow::new(99).perform(ctx).await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assert_frame_sequence() {
+        let server: SocketAddr = "127.0.1.99:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.100:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::LengthPrefixed { header_bytes: 2 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let socket = tokio::net::TcpSocket::new_v4().unwrap();
+        socket.bind(client).unwrap();
+        let mut stream = socket.connect(server).await.unwrap();
+        stream
+            .write_all(&[0, 1, b'a', 0, 2, b'b', b'c', 0, 1, b'd'])
+            .await
+            .unwrap();
+
+        for _ in 0..50 {
+            if ctx.lock().await.receive_events.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let frames = |frames: &[&[u8]]| frames.iter().map(|f| f.to_vec()).collect();
+        assert!(
+            AssertFrameSequence::new(client, frames(&[b"a", b"bc", b"d"]))
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertFrameSequence::new(client, frames(&[b"a", b"d", b"bc"]))
+                .perform(ctx.clone())
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+        assert!(matches!(
+            AssertFrameSequence::new(client, frames(&[b"a", b"bc"]))
+                .perform(ctx)
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assert_transcript() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        let hello = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from,
+            to,
+            buffer: b"HELLO ".to_vec(),
+            truncated: false,
+            phase: None,
+        };
+        ctx.lock().await.receive_events.push(hello.clone());
+
+        let transcript =
+            AssertTranscript::new("127.0.0.1:0".parse().unwrap(), to, b"HELLO WORLD".to_vec());
+        assert!(matches!(
+            transcript.perform(ctx.clone()).await,
+            Err(ActionError::AssertError(_))
+        ));
+
+        ctx.lock().await.receive_events.push(ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            buffer: b"WORLD".to_vec(),
+            ..hello
+        });
+        assert!(transcript.perform(ctx).await.is_ok());
+    }
+
+    #[test]
+    fn test_transcript_diff() {
+        assert_eq!(transcript_diff(b"abc", b"abc"), None);
+
+        let diff = transcript_diff(b"abcdef", b"abXdef").unwrap();
+        assert!(diff.starts_with("transcripts differ at byte 2"));
+
+        let diff = transcript_diff(b"abcdef", b"abc").unwrap();
+        assert!(diff.starts_with("transcripts differ at byte 3 (expected 6 bytes, got 3)"));
+    }
+
+    fn receive_events_at(from: SocketAddr, offsets_ms: &[u64]) -> Vec<ReceiveEvent> {
+        let start = Instant::now();
+        offsets_ms
+            .iter()
+            .map(|offset| ReceiveEvent {
+                instant: start + Duration::from_millis(*offset),
+                seq: 0,
+                from,
+                to: "127.0.0.1:4000".parse().unwrap(),
+                buffer: vec![1],
+                truncated: false,
+                phase: None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_assert_jitter() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let jitter = AssertJitter::new(from, 2.0, 5);
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 20, 30, 40]);
+        assert!(jitter.perform(node.ctx()).await.is_ok());
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 1, 2, 30, 40]);
+        assert!(jitter.perform(node.ctx()).await.is_err());
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 20]);
+        assert!(jitter.perform(node.ctx()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assert_chunk_cadence() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let cadence = AssertChunkCadence::new(from, 15, 4);
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 20, 30, 40]);
+        assert!(cadence.perform(node.ctx()).await.is_ok());
+
+        // The stream stalls between the second and third chunks
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 200, 210]);
+        match cadence.perform(node.ctx()).await {
+            Err(ActionError::AssertError(report)) => {
+                assert!(report.starts_with("Chunk 2 from"), "{}", report)
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 20]);
+        assert!(cadence.perform(node.ctx()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assert_receive_rate() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let rate = AssertReceiveRate::new(from, 1000);
+
+        // 5 bytes over 40ms: 125 B/s
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 10, 20, 30, 40]);
+        assert!(rate.perform(node.ctx()).await.is_ok());
+
+        // 3 bytes over 1ms: 3000 B/s
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0, 0, 1]);
+        assert!(rate.perform(node.ctx()).await.is_err());
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = receive_events_at(from, &[0]);
+        assert!(rate.perform(node.ctx()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_assert_never_connected() {
+        let allowed: SocketAddr = "127.0.1.31:3000".parse().unwrap();
+        let forbidden: SocketAddr = "127.0.1.32:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(allowed).perform(ctx.clone()).await.unwrap();
+        Bind::new(forbidden).perform(ctx.clone()).await.unwrap();
+
+        Connect::new("127.0.1.33:0".parse().unwrap(), allowed, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        let never = AssertNeverConnected::new("127.0.1.32:0".parse().unwrap());
+        assert!(never.perform(ctx.clone()).await.is_ok());
+
+        Connect::new("127.0.1.33:0".parse().unwrap(), forbidden, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            never.perform(ctx).await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assert_not_contains() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let not_contains =
+            AssertNotContains::new("127.0.0.1:0".parse().unwrap(), b"secret".to_vec());
+
+        let node = Node::new("test-node");
+        let mut events = receive_events_at(from, &[0, 10]);
+        events[0].buffer = b"hello sec".to_vec();
+        events[1].buffer = b"ret world".to_vec();
+        node.ctx().lock().await.receive_events = events;
+
+        assert!(not_contains.perform(node.ctx()).await.is_ok());
+        assert!(matches!(
+            not_contains
+                .clone()
+                .with_stream(true)
+                .perform(node.ctx())
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+
+        let node = Node::new("test-node");
+        let mut events = receive_events_at(from, &[0, 10]);
+        events[1].buffer = b"the secret is 42".to_vec();
+        node.ctx().lock().await.receive_events = events;
+
+        match not_contains.perform(node.ctx()).await {
+            Err(ActionError::AssertError(report)) => {
+                assert!(
+                    report.starts_with("Message 1 from 127.0.0.1:3000"),
+                    "{}",
+                    report
+                )
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assert_connect_after_bind_within() {
+        let client: SocketAddr = "127.0.1.116:0".parse().unwrap();
+
+        for (server, delay_ms, expected_ok) in [
+            ("127.0.1.115:3000", 10, true),
+            ("127.0.1.115:3001", 100, false),
+        ] {
+            let server: SocketAddr = server.parse().unwrap();
+            // Server and client share the context, as two nodes of the same process
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+            let clock = MockClock::new();
+            ctx.lock().await.clock = Arc::new(clock.clone());
+
+            let within = AssertConnectAfterBindWithin::new(server, 50);
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+            assert!(matches!(
+                within.perform(ctx.clone()).await,
+                Err(ActionError::AssertError(_))
+            ));
+
+            clock.advance(Duration::from_millis(delay_ms));
+            Connect::new(client, server, 1000)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                within.perform(ctx).await.is_ok(),
+                expected_ok,
+                "connected {}ms after binding",
+                delay_ms
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assert_entropy_above() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let entropy_above = AssertEntropyAbove::new("127.0.0.1:0".parse().unwrap(), 7.0);
+
+        let node = Node::new("test-node");
+        let mut rng = Rng::new(42);
+        let mut events = receive_events_at(from, &[0, 10]);
+        for event in &mut events {
+            event.buffer = (0..4096).map(|_| rng.below(256) as u8).collect();
+        }
+        node.ctx().lock().await.receive_events = events;
+        assert!(entropy_above.perform(node.ctx()).await.is_ok());
+
+        let node = Node::new("test-node");
+        let mut events = receive_events_at(from, &[0, 10]);
+        for event in &mut events {
+            event.buffer = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n".repeat(50);
+        }
+        node.ctx().lock().await.receive_events = events;
+        assert!(matches!(
+            entropy_above.perform(node.ctx()).await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_assert_connect_order() {
+        let a: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let b: SocketAddr = "127.0.0.2:3000".parse().unwrap();
+        let start = Instant::now();
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.connect_events = [(b, 20), (a, 10), (b, 5)]
+            .iter()
+            .map(|(from, offset)| ConnectEvent {
+                instant: start + Duration::from_millis(*offset),
+                seq: 0,
+                from: *from,
+                to: "127.0.0.3:4000".parse().unwrap(),
+                phase: None,
+            })
+            .collect();
+
+        let a_any: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let b_any: SocketAddr = "127.0.0.2:0".parse().unwrap();
+        assert!(
+            AssertConnectOrder::new(b_any, a_any)
+                .perform(node.ctx())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertConnectOrder::new(a_any, b_any)
+                .perform(node.ctx())
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+        assert!(
+            AssertConnectOrder::new(a_any, "127.0.0.4:0".parse().unwrap())
+                .perform(node.ctx())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assert_close_reason() {
+        let server: SocketAddr = "127.0.1.49:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.50:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        for reason in [DisconnectReason::Fin, DisconnectReason::Rst] {
+            let socket = tokio::net::TcpSocket::new_v4().unwrap();
+            socket.bind(client).unwrap();
+            if reason == DisconnectReason::Rst {
+                // Closing with a zero linger resets the connection
+                socket.set_linger(Some(Duration::ZERO)).unwrap();
+            }
+            let mut stream = socket.connect(server).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            if reason == DisconnectReason::Fin {
+                tokio::io::AsyncWriteExt::shutdown(&mut stream)
+                    .await
+                    .unwrap();
+            }
+            drop(stream);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            assert!(
+                AssertCloseReason::new(client, reason)
+                    .perform(ctx.clone())
+                    .await
+                    .is_ok(),
+                "{:?}",
+                reason
+            );
+        }
+
+        let other = "127.0.1.51:0".parse().unwrap();
+        assert!(
+            AssertCloseReason::new(other, DisconnectReason::Fin)
+                .perform(ctx)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assert_bound() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new("127.0.1.54:3000".parse().unwrap())
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Bind::new("127.0.1.54:0".parse().unwrap())
+            .with_protocol(Protocol::Udp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let tcp: SocketAddr = "127.0.1.54:3000".parse().unwrap();
+        let ephemeral: SocketAddr = "127.0.1.54:0".parse().unwrap();
+        assert!(
+            AssertBound::new(vec![tcp, ephemeral])
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertBound::new(vec![tcp, ephemeral, "127.0.1.55:3000".parse().unwrap()])
+                .perform(ctx.clone())
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+        assert!(AssertBound::new(vec![tcp]).perform(ctx).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_assert_sequence() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let with_sequences = |sequences: &[u16]| {
+            let mut events = receive_events_at(from, &vec![0; sequences.len()]);
+            for (event, sequence) in events.iter_mut().zip(sequences) {
+                event.buffer = [&[0xAA][..], &sequence.to_be_bytes()].concat();
+            }
+            events
+        };
+
+        for (sequences, ok) in [
+            (&[1, 2, 3, 4][..], true),
+            (&[0xFFFE, 0xFFFF, 0, 1][..], true),
+            (&[1, 2, 4, 5][..], false),
+            (&[1, 2, 2, 3][..], false),
+            (&[2, 1][..], false),
+        ] {
+            let node = Node::new("test-node");
+            node.ctx().lock().await.receive_events = with_sequences(sequences);
+            let result = AssertSequence::new(from, 1, 2, true)
+                .perform(node.ctx())
+                .await;
+            assert_eq!(result.is_ok(), ok, "{:?}: {:?}", sequences, result);
+        }
+
+        // Read as little endian, 1 then 2 become 256 then 512
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = with_sequences(&[1, 2]);
+        assert!(
+            AssertSequence::new(from, 1, 2, false)
+                .perform(node.ctx())
+                .await
+                .is_err()
+        );
+
+        // Buffers too short for the sequence number fail
+        assert!(
+            AssertSequence::new(from, 2, 2, true)
+                .perform(node.ctx())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_assert_alternating() {
+        let peer: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+        for (sends, ok) in [(&[0, 20, 40][..], true), (&[0, 5, 40][..], false)] {
+            let node = Node::new("test-node");
+            {
+                let ctx = node.ctx();
+                let mut context = ctx.lock().await;
+                // Receives at 10 and 30ms after the first send
+                context.receive_events = receive_events_at(peer, &[10, 30]);
+                let start = context.receive_events[0].instant - Duration::from_millis(10);
+                context.send_events = sends
+                    .iter()
+                    .map(|offset| SendEvent {
+                        instant: start + Duration::from_millis(*offset),
+                        seq: 0,
+                        from: "127.0.0.1:4000".parse().unwrap(),
+                        to: peer,
+                        buffer: vec![1],
+                        len: 1,
+                        phase: None,
+                    })
+                    .collect();
+            }
+
+            let result = AssertAlternating::new(peer).perform(node.ctx()).await;
+            assert_eq!(result.is_ok(), ok, "{:?}: {:?}", sends, result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assert_connect_rate() {
+        let to: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let connects_at = |offsets_ms: &[u64]| {
+            let start = Instant::now();
+            offsets_ms
+                .iter()
+                .map(|offset| ConnectEvent {
+                    instant: start + Duration::from_millis(*offset),
+                    seq: 0,
+                    from: "127.0.0.1:4000".parse().unwrap(),
+                    to,
+                    phase: None,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // 10 connections per second allowed
+        for (offsets, ok) in [
+            (&[0, 100, 200, 300][..], true),
+            (&[0][..], true),
+            // A burst is not hidden by a long tail
+            (&[0, 10, 20, 400][..], false),
+            (&[0, 400, 1000, 1100][..], true),
+            (&[0, 10, 20][..], false),
+            (&[0, 0][..], false),
+        ] {
+            let node = Node::new("test-node");
+            node.ctx().lock().await.connect_events = connects_at(offsets);
+            let result = AssertConnectRate::new(to, 10.0).perform(node.ctx()).await;
+            assert_eq!(result.is_ok(), ok, "{:?}: {:?}", offsets, result);
+        }
+    }
+
+    #[test]
+    fn test_transcript_orders_equal_instants_by_seq() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let mut events = receive_events_at(from, &[0, 0]);
+        events[1].instant = events[0].instant;
+        for (event, (seq, buffer)) in events.iter_mut().zip([(1, b"later"), (0, b"first")]) {
+            event.seq = seq;
+            event.buffer = buffer.to_vec();
+        }
+
+        assert_eq!(
+            transcript(from, "127.0.0.1:4000".parse().unwrap(), &events),
+            b"firstlater".to_vec()
+        );
+    }
+}
@@ -1,32 +1,430 @@
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, sync::Arc};
-use tokio::net::UdpSocket;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::net::{UdpSocket, tcp::OwnedReadHalf};
 
-use tokio::io::AsyncReadExt;
 use tracing::{Instrument, event, instrument};
 
-use crate::node::ConnectEvent;
+use crate::node::{ConnectEvent, DisconnectEvent, DisconnectReason, NodeContext, RejectEvent};
+use crate::protocol::{Protocol, ip::addr_match};
+use crate::resolver::resolve_endpoint;
 use crate::{
     action::{Action, ActionError},
     node::{Ctx, ReceiveEvent},
 };
 
+/// Default size of the buffer used to read incoming data.
+const DEFAULT_BUFFER_SIZE: usize = 1024;
+
+/// Port of the UDP listener when no protocol is given to the bind action.
+const DEFAULT_UDP_PORT: u16 = 49999;
+
+/// Delay after which datagrams held for reordering are released anyway.
+const REORDER_FLUSH_DELAY: Duration = Duration::from_millis(50);
+
+/// Largest payload accepted in a length-prefixed frame, a larger header closing the connection.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+fn default_buffer_size() -> usize {
+    DEFAULT_BUFFER_SIZE
+}
+
+/// Callback deciding whether a received message is recorded.
+#[derive(Clone)]
+pub struct ReceiveFilter(Arc<dyn Fn(&ReceiveEvent) -> bool + std::marker::Send + Sync>);
+
+impl std::fmt::Debug for ReceiveFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ReceiveFilter")
+    }
+}
+
+impl PartialEq for ReceiveFilter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Delimits the messages read on accepted TCP connections.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Framing {
+    /// Records the bytes of every read as they come.
+    #[default]
+    None,
+    /// Records one message per frame, made of a big-endian length header
+    /// of `header_bytes` bytes followed by the payload, without the header.
+    ///
+    /// `header_bytes` must be between 1 and 8, and a header announcing more than
+    /// 16 MiB closes the connection.
+    LengthPrefixed { header_bytes: usize },
+    /// Records one message per `delimiter` terminated line, for line-oriented
+    /// protocols, with the delimiter at its end if `keep_delimiter` is set.
+    Delimiter {
+        delimiter: Vec<u8>,
+        #[serde(default)]
+        keep_delimiter: bool,
+    },
+    /// Records the bytes read as one message once no more bytes arrive
+    /// for `idle_ms`, however many reads they took.
+    Coalesce { idle_ms: u64 },
+}
+
 /// Represents a bind action that binds a TCP socket to a specific address.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Bind {
     to: std::net::SocketAddr,
+    #[serde(default = "default_buffer_size")]
+    buffer_size: usize,
+    #[serde(default)]
+    protocol: Option<Protocol>,
+    #[serde(default)]
+    accept_allowlist: Option<Vec<IpAddr>>,
+    #[serde(default)]
+    reorder_window: Option<usize>,
+    #[serde(default)]
+    dedup_connects: bool,
+    #[serde(default)]
+    bind_retries: u32,
+    #[serde(default)]
+    bind_retry_delay_ms: u64,
+    #[serde(default)]
+    idle_timeout_ms: Option<u64>,
+    #[serde(skip)]
+    receive_filter: Option<ReceiveFilter>,
+    #[serde(default)]
+    ssm_source: Option<Ipv4Addr>,
+    #[serde(default)]
+    fixed_record_size: Option<usize>,
+    #[serde(default)]
+    framing: Framing,
+    #[serde(default)]
+    host: Option<String>,
 }
 
 impl Bind {
     /// Creates a new `Bind` action with the specified address.
     pub fn new(to: std::net::SocketAddr) -> Self {
-        Bind { to }
+        Bind {
+            to,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            protocol: None,
+            accept_allowlist: None,
+            reorder_window: None,
+            dedup_connects: false,
+            bind_retries: 0,
+            bind_retry_delay_ms: 0,
+            idle_timeout_ms: None,
+            receive_filter: None,
+            ssm_source: None,
+            fixed_record_size: None,
+            framing: Framing::None,
+            host: None,
+        }
+    }
+
+    /// Restricts the listener to a single protocol bound on `to`.
+    ///
+    /// Without a protocol, a TCP listener is bound on `to` and a UDP listener
+    /// on the same ip with port 49999.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Sets the size of the buffer used to read incoming data, 1024 bytes by default.
+    ///
+    /// UDP datagrams larger than this size are still truncated, the recorded
+    /// event is then flagged as such.
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Only accepts TCP connections coming from one of the given ips,
+    /// the other ones are closed as soon as they are accepted.
+    pub fn with_accept_allowlist(mut self, allowlist: Vec<IpAddr>) -> Self {
+        self.accept_allowlist = Some(allowlist);
+        self
+    }
+
+    /// Holds up to `window` UDP datagrams and records them in an order
+    /// shuffled with the node random number generator.
+    ///
+    /// Held datagrams are released when the window is full or when no
+    /// other datagram arrives shortly after.
+    pub fn with_reorder_window(mut self, window: usize) -> Self {
+        self.reorder_window = Some(window);
+        self
+    }
+
+    /// Does not record a connect event identical to an active one, from the same
+    /// peer address and port to the same local address, with no disconnect event
+    /// recorded since, as when a client reconnects before its previous connection
+    /// was seen closed. Distinct clients sharing an ip are all recorded.
+    pub fn with_dedup_connects(mut self, dedup_connects: bool) -> Self {
+        self.dedup_connects = dedup_connects;
+        self
+    }
+
+    /// Retries binding the TCP listener up to `retries` times, waiting `delay_ms`
+    /// between attempts, for addresses that are released shortly after.
+    pub fn with_bind_retries(mut self, retries: u32, delay_ms: u64) -> Self {
+        self.bind_retries = retries;
+        self.bind_retry_delay_ms = delay_ms;
+        self
+    }
+
+    /// Closes the accepted TCP connections that receive nothing for `timeout_ms`.
+    pub fn with_idle_timeout(mut self, timeout_ms: u64) -> Self {
+        self.idle_timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Only records the received messages for which `filter` returns true,
+    /// the other ones are dropped as soon as they are read.
+    ///
+    /// The filter is not serialized.
+    pub fn with_receive_filter(
+        mut self,
+        filter: Box<dyn Fn(&ReceiveEvent) -> bool + std::marker::Send + Sync>,
+    ) -> Self {
+        self.receive_filter = Some(ReceiveFilter(Arc::from(filter)));
+        self
+    }
+
+    /// Joins the source-specific multicast group `to` on the UDP listener,
+    /// only receiving the datagrams sent by `source`.
+    ///
+    /// `to` must be an IPv4 multicast address, usually in 232.0.0.0/8.
+    pub fn with_ssm_source(mut self, source: Ipv4Addr) -> Self {
+        self.ssm_source = Some(source);
+        self
+    }
+
+    /// Records the bytes read on accepted TCP connections as events of exactly
+    /// `size` bytes, however the reads are split. A trailing partial record is
+    /// recorded when the connection ends.
+    pub fn with_fixed_record_size(mut self, size: usize) -> Self {
+        self.fixed_record_size = Some(size);
+        self
+    }
+
+    /// Delimits the messages read on accepted TCP connections with `framing`,
+    /// taking precedence over a fixed record size. A trailing partial frame is
+    /// recorded as is when the connection ends.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Binds the `host:port` endpoint resolved by the node resolver instead of `to`.
+    pub fn with_host(mut self, endpoint: &str) -> Self {
+        self.host = Some(endpoint.to_string());
+        self
     }
 
     /// Returns the address to which the socket will be bound.
     pub fn to(&self) -> &std::net::SocketAddr {
         &self.to
     }
+
+    /// Returns the size of the buffer used to read incoming data.
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Returns the protocol the listener is restricted to, if any.
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.protocol
+    }
+
+    /// Returns the ips allowed to connect, if restricted.
+    pub fn accept_allowlist(&self) -> Option<&[IpAddr]> {
+        self.accept_allowlist.as_deref()
+    }
+
+    /// Returns the number of UDP datagrams shuffled together, if any.
+    pub fn reorder_window(&self) -> Option<usize> {
+        self.reorder_window
+    }
+
+    /// Returns true if connect events of already connected peers are suppressed.
+    pub fn dedup_connects(&self) -> bool {
+        self.dedup_connects
+    }
+
+    /// Returns the number of times binding the TCP listener is retried.
+    pub fn bind_retries(&self) -> u32 {
+        self.bind_retries
+    }
+
+    /// Returns the delay between two bind attempts.
+    pub fn bind_retry_delay_ms(&self) -> u64 {
+        self.bind_retry_delay_ms
+    }
+
+    /// Returns the idle time after which accepted connections are closed, if any.
+    pub fn idle_timeout_ms(&self) -> Option<u64> {
+        self.idle_timeout_ms
+    }
+
+    /// Returns the source of the joined source-specific multicast group, if any.
+    pub fn ssm_source(&self) -> Option<Ipv4Addr> {
+        self.ssm_source
+    }
+
+    /// Returns the size of the records read on TCP connections, if fixed.
+    pub fn fixed_record_size(&self) -> Option<usize> {
+        self.fixed_record_size
+    }
+
+    /// Returns how the messages read on TCP connections are delimited.
+    pub fn framing(&self) -> &Framing {
+        &self.framing
+    }
+
+    /// Returns the endpoint bound instead of `to`, if any.
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Binds a TCP socket to `to` and starts listening on it.
+    fn listen_tcp(&self) -> Result<tokio::net::TcpListener, ActionError> {
+        let socket = match self.to {
+            SocketAddr::V4(_) => tokio::net::TcpSocket::new_v4(),
+            SocketAddr::V6(_) => tokio::net::TcpSocket::new_v6(),
+        }
+        .map_err(|e| {
+            ActionError::BindError(format!("Error creating socket for {} ({})", self.to, e))
+        })?;
+
+        socket.set_reuseaddr(true).map_err(|e| {
+            ActionError::BindError(format!("Error configuring socket for {} ({})", self.to, e))
+        })?;
+        socket.bind(self.to).map_err(|e| {
+            ActionError::BindError(format!("Error binding socket to {} ({})", self.to, e))
+        })?;
+
+        socket
+            .listen(1024)
+            .map_err(|e| ActionError::BindError(format!("Error listening on {} ({})", self.to, e)))
+    }
+
+    /// Checks the framing can delimit messages, it would buffer a connection forever otherwise.
+    fn check_framing(&self) -> Result<(), ActionError> {
+        match &self.framing {
+            Framing::LengthPrefixed { header_bytes } if !(1..=8).contains(header_bytes) => {
+                Err(ActionError::BindError(format!(
+                    "Length header of {} bytes is not supported, expected 1 to 8",
+                    header_bytes
+                )))
+            }
+            Framing::Delimiter { delimiter, .. } if delimiter.is_empty() => Err(
+                ActionError::BindError("The record delimiter must not be empty".into()),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes the complete records from the bytes read on a TCP connection.
+    ///
+    /// Fails if the bytes cannot be framed, the connection should be closed then.
+    fn split_records(&self, pending: &mut Vec<u8>) -> Result<Vec<Vec<u8>>, String> {
+        match &self.framing {
+            Framing::LengthPrefixed { header_bytes } => {
+                return split_frames(pending, *header_bytes);
+            }
+            Framing::Delimiter {
+                delimiter,
+                keep_delimiter,
+            } => return Ok(split_lines(pending, delimiter, *keep_delimiter)),
+            // Recorded by the reader once the connection is idle
+            Framing::Coalesce { .. } => return Ok(Vec::new()),
+            Framing::None => {}
+        }
+
+        Ok(match self.fixed_record_size {
+            Some(size) if size > 0 => {
+                let complete = pending.len() - pending.len() % size;
+                pending
+                    .drain(..complete)
+                    .collect::<Vec<u8>>()
+                    .chunks(size)
+                    .map(<[u8]>::to_vec)
+                    .collect()
+            }
+            _ => vec![std::mem::take(pending)],
+        })
+    }
+
+    /// Returns true if the received message should be recorded.
+    fn keeps(&self, event: &ReceiveEvent) -> bool {
+        self.receive_filter
+            .as_ref()
+            .is_none_or(|filter| (filter.0)(event))
+    }
+
+    /// Returns true if a TCP connection coming from `ip` should be accepted.
+    fn accepts(&self, ip: IpAddr) -> bool {
+        self.accept_allowlist
+            .as_ref()
+            .is_none_or(|allowlist| allowlist.contains(&ip))
+    }
+}
+
+/// Removes the complete length-prefixed frames from `pending`, returning their payloads.
+///
+/// Fails on a header announcing more than [`MAX_FRAME_SIZE`] bytes, as sent by a
+/// broken or hostile peer.
+fn split_frames(pending: &mut Vec<u8>, header_bytes: usize) -> Result<Vec<Vec<u8>>, String> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while pending.len() - start >= header_bytes {
+        let len = pending[start..start + header_bytes]
+            .iter()
+            .fold(0u64, |len, byte| (len << 8) | u64::from(*byte));
+        let end = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= MAX_FRAME_SIZE)
+            .and_then(|len| (start + header_bytes).checked_add(len))
+            .ok_or_else(|| format!("Frame of {} bytes exceeds {} bytes", len, MAX_FRAME_SIZE))?;
+        if pending.len() < end {
+            break;
+        }
+
+        frames.push(pending[start + header_bytes..end].to_vec());
+        start = end;
+    }
+    pending.drain(..start);
+
+    Ok(frames)
+}
+
+/// Removes the complete `delimiter` terminated lines from `pending`, returning them
+/// with or without their delimiter.
+fn split_lines(pending: &mut Vec<u8>, delimiter: &[u8], keep_delimiter: bool) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    if delimiter.is_empty() {
+        return lines;
+    }
+
+    // A delimiter split between two reads is found once its last byte is pending
+    let mut start = 0;
+    while let Some(offset) = pending[start..]
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)
+    {
+        let end = start + offset + delimiter.len();
+        let line_end = if keep_delimiter { end } else { start + offset };
+        lines.push(pending[start..line_end].to_vec());
+        start = end;
+    }
+    pending.drain(..start);
+
+    lines
 }
 
 /// Binds a TCP socket to the specified address and starts listening for incoming connections.
@@ -39,27 +437,69 @@ impl Action for Bind {
 
     /// Performs the bind action by creating a socket, binding it to the specified address,
     async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if let Some(endpoint) = &self.host {
+            let to = resolve_endpoint(&ctx, endpoint).await.map_err(|e| {
+                ActionError::BindError(format!("Error resolving {} ({})", endpoint, e))
+            })?;
+            let resolved = Bind {
+                to,
+                host: None,
+                ..self.clone()
+            };
+            return resolved.perform(ctx).await;
+        }
+
+        self.check_framing()?;
         event!(tracing::Level::INFO, "Binding socket to {}", self.to);
-        let socket = tokio::net::TcpSocket::new_v4().map_err(|_| ActionError::BindError)?;
+        let config = Arc::new(self.clone());
 
-        socket
-            .set_reuseaddr(true)
-            .map_err(|_| ActionError::BindError)?;
-        socket.bind(self.to).map_err(|_| ActionError::BindError)?;
+        if self.protocol != Some(Protocol::Udp) {
+            let mut attempt = 0;
+            let listener = loop {
+                match self.listen_tcp() {
+                    Ok(listener) => break listener,
+                    Err(_) if attempt < self.bind_retries => {
+                        attempt += 1;
+                        event!(
+                            tracing::Level::WARN,
+                            "Failed to bind {}, retrying ({}/{})",
+                            self.to,
+                            attempt,
+                            self.bind_retries
+                        );
+                        tokio::time::sleep(Duration::from_millis(self.bind_retry_delay_ms)).await;
+                    }
+                    Err(e) => return Err(e),
+                }
+            };
 
-        let listener = socket.listen(1024).map_err(|_| ActionError::BindError)?;
-        let to_clone = self.to;
+            if let Ok(local_addr) = listener.local_addr() {
+                let mut context = ctx.lock().await;
+                let instant = context.clock.now();
+                context.bound.push(local_addr);
+                context.bind_instants.entry(local_addr).or_insert(instant);
+                context.accepted_connections.insert(local_addr, 0);
+            }
 
-        // Accept incomming tcp connections
-        let ctx_clone = Arc::clone(&ctx);
-        tokio::spawn(async move {
-            accept_tcp(listener, to_clone, ctx_clone).await;
-        });
+            // Accept incomming tcp connections
+            let ctx_clone = Arc::clone(&ctx);
+            let config = Arc::clone(&config);
+            tokio::spawn(async move {
+                accept_tcp(listener, config, ctx_clone).await;
+            });
+        }
 
-        // Accept incoming udp messages
-        tokio::spawn(async move {
-            accept_udp(to_clone, ctx).await;
-        });
+        if self.protocol != Some(Protocol::Tcp) {
+            let udp_addr = match self.protocol {
+                Some(_) => self.to,
+                None => SocketAddr::new(self.to.ip(), DEFAULT_UDP_PORT),
+            };
+
+            // Accept incoming udp messages
+            tokio::spawn(async move {
+                accept_udp(udp_addr, config, ctx).await;
+            });
+        }
 
         event!(
             tracing::Level::DEBUG,
@@ -71,68 +511,264 @@ impl Action for Bind {
     }
 }
 
-#[instrument(name = "udp_listener", level = "info", skip(ctx), fields(addr = %addr))]
-async fn accept_udp(addr: SocketAddr, ctx: Ctx) {
-    let ip = addr.ip();
-    let port = 49999;
+#[instrument(name = "udp_listener", level = "info", skip(config, ctx), fields(addr = %addr))]
+async fn accept_udp(addr: SocketAddr, config: Arc<Bind>, ctx: Ctx) {
+    event!(tracing::Level::DEBUG, "Binding UDP socket to {}", addr);
+    let udp_socket = match config.ssm_source {
+        Some(source) => bind_ssm(addr, source),
+        None => UdpSocket::bind(addr).await,
+    };
+    let udp_socket = match udp_socket {
+        Ok(udp_socket) => Arc::new(udp_socket),
+        Err(e) => {
+            event!(tracing::Level::ERROR, "Error binding UDP socket: {}", e);
+            return;
+        }
+    };
 
-    event!(
-        tracing::Level::DEBUG,
-        "Binding UDP socket to {}:{}",
-        ip,
-        port
-    );
-    let udp_socket = UdpSocket::bind(format!("{}:{}", ip, port))
-        .await
-        .expect("Failed to bind UDP socket");
+    let local_addr = match udp_socket.local_addr() {
+        Ok(local_addr) => local_addr,
+        Err(e) => {
+            event!(
+                tracing::Level::ERROR,
+                "Error reading UDP socket address: {}",
+                e
+            );
+            return;
+        }
+    };
+    {
+        let mut context = ctx.lock().await;
+        let instant = context.clock.now();
+        context.bound.push(local_addr);
+        context.bind_instants.entry(local_addr).or_insert(instant);
+        context.received_datagrams.insert(local_addr, 0);
+        context
+            .udp_listeners
+            .insert(local_addr, Arc::clone(&udp_socket));
+    }
+    let window = config.reorder_window.unwrap_or(1).max(1);
+    let mut pending: Vec<(SocketAddr, Vec<u8>, bool)> = Vec::new();
 
-    let mut buf = vec![0; 1024];
+    let mut buf = vec![0; config.buffer_size];
     loop {
-        let (len, addr) = udp_socket.recv_from(&mut buf).await.unwrap();
-        event!(
-            tracing::Level::INFO,
-            "Received {} UDP bytes from {}",
-            len,
-            addr
-        );
-
-        let received_message = ReceiveEvent {
-            instant: tokio::time::Instant::now(),
-            from: addr,
-            to: udp_socket.local_addr().unwrap(),
-            buffer: buf[..len].to_vec(),
+        let received = if pending.is_empty() {
+            Some(recv_counted(&udp_socket, &mut buf, &ctx).await)
+        } else {
+            // Release the held datagrams if no other one arrives in time
+            tokio::time::timeout(
+                REORDER_FLUSH_DELAY,
+                recv_counted(&udp_socket, &mut buf, &ctx),
+            )
+            .await
+            .ok()
         };
 
-        ctx.lock().await.receive_events.push(received_message);
-        ctx.lock().await.receive_notifier.notify_waiters();
+        if let Some(received) = received {
+            let (len, addr) = match received {
+                Ok(received) => received,
+                // An ICMP error caused by a previous datagram, the socket is still usable
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset
+                    ) =>
+                {
+                    event!(tracing::Level::ERROR, "Error receiving UDP datagram: {}", e);
+                    continue;
+                }
+                Err(e) => {
+                    event!(tracing::Level::ERROR, "Error receiving UDP datagram: {}", e);
+                    return;
+                }
+            };
+            if config.ssm_source.is_some_and(|source| addr.ip() != source) {
+                event!(
+                    tracing::Level::DEBUG,
+                    "Ignored UDP datagram from {}, not the multicast source",
+                    addr
+                );
+                mark_recorded(&ctx, len).await;
+                continue;
+            }
+            event!(
+                tracing::Level::INFO,
+                "Received {} UDP bytes from {}",
+                len,
+                addr
+            );
+
+            // A datagram filling the whole buffer may have been cut by the kernel
+            let truncated = len == buf.len();
+            if truncated {
+                event!(
+                    tracing::Level::WARN,
+                    "UDP datagram from {} may have been truncated to {} bytes",
+                    addr,
+                    len
+                );
+            }
+
+            pending.push((addr, buf[..len].to_vec(), truncated));
+            if pending.len() < window {
+                continue;
+            }
+        }
+
+        let mut context = ctx.lock().await;
+        *context.received_datagrams.entry(local_addr).or_default() += pending.len() as u64;
+        let recorded: usize = pending.iter().map(|(_, buffer, _)| buffer.len()).sum();
+        context.unrecorded_bytes = context.unrecorded_bytes.saturating_sub(recorded);
+        context.rng.shuffle(&mut pending);
+
+        for (from, buffer, truncated) in pending.drain(..) {
+            let received_message = ReceiveEvent {
+                instant: context.clock.now(),
+                seq: context.next_seq(),
+                from,
+                to: local_addr,
+                buffer,
+                truncated,
+                phase: context.phase.clone(),
+            };
+
+            if config.keeps(&received_message) {
+                context.push_receive_event(received_message);
+            }
+        }
+        context.receive_notifier.notify_waiters();
     }
 }
 
+/// Binds a UDP socket to the multicast group `addr` and joins it for `source` only.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos",
+    target_os = "windows"
+))]
+fn bind_ssm(addr: SocketAddr, source: Ipv4Addr) -> std::io::Result<UdpSocket> {
+    let IpAddr::V4(group) = addr.ip() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "source-specific multicast requires an IPv4 group",
+        ));
+    };
+
+    let socket = socket2::Socket::new(
+        socket2::Domain::IPV4,
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.join_ssm_v4(&source, &group, &Ipv4Addr::UNSPECIFIED)?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "macos",
+    target_os = "windows"
+)))]
+fn bind_ssm(_addr: SocketAddr, _source: Ipv4Addr) -> std::io::Result<UdpSocket> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "source-specific multicast is not supported on this platform",
+    ))
+}
+
 /// Listens for incoming connections on the given listener
 /// and processes each connection in a separate task.
-#[instrument(name = "tcp_listener", level = "info", skip(listener, ctx), fields(addr = %listener.local_addr().unwrap()))]
-async fn accept_tcp(listener: tokio::net::TcpListener, addr: std::net::SocketAddr, ctx: Ctx) {
-    let binded_addr = addr.to_string();
+#[instrument(name = "tcp_listener", level = "info", skip(listener, config, ctx), fields(addr = %listener.local_addr().unwrap()))]
+async fn accept_tcp(listener: tokio::net::TcpListener, config: Arc<Bind>, ctx: Ctx) {
+    let binded_addr = config.to.to_string();
+    let listener_addr = listener.local_addr().ok();
 
     let _ = tokio::spawn(
         async move {
             loop {
                 match listener.accept().await {
                     Ok((socket, addr)) => {
+                        if !config.accepts(addr.ip()) {
+                            event!(
+                                tracing::Level::INFO,
+                                "Rejected connection from {}, not in the allowlist",
+                                addr
+                            );
+
+                            let mut context = ctx.lock().await;
+                            let phase = context.phase.clone();
+                            let instant = context.clock.now();
+                            let seq = context.next_seq();
+                            context.reject_events.push(RejectEvent {
+                                instant,
+                                seq,
+                                from: addr,
+                                to: socket.local_addr().unwrap(),
+                                phase,
+                            });
+
+                            // Dropping the socket closes the connection
+                            drop(socket);
+                            continue;
+                        }
+
                         event!(tracing::Level::INFO, "Accepted connection from {}", addr);
 
                         // Store the event in the context and signal every task waiting for it
-                        ctx.lock().await.connect_events.push(ConnectEvent {
-                            instant: tokio::time::Instant::now(),
-                            from: addr,
-                            to: socket.local_addr().unwrap(),
-                        });
-                        ctx.lock().await.connect_notifier.notify_waiters();
+                        let reader = {
+                            let mut context = ctx.lock().await;
+                            let local_addr = socket.local_addr().unwrap();
+
+                            // The peer is still connected, this is the same logical connection
+                            let duplicate =
+                                config.dedup_connects && connect_active(&context, addr, local_addr);
+
+                            if duplicate {
+                                event!(
+                                    tracing::Level::DEBUG,
+                                    "{} is already connected, connect event suppressed",
+                                    addr
+                                );
+                            } else {
+                                let phase = context.phase.clone();
+                                let instant = context.clock.now();
+                                let seq = context.next_seq();
+                                context.connect_events.push(ConnectEvent {
+                                    instant,
+                                    seq,
+                                    from: addr,
+                                    to: local_addr,
+                                    phase,
+                                });
+                                context.connect_notifier.notify_waiters();
+                            }
+                            context.record_connection();
+                            if let Some(listener_addr) = listener_addr {
+                                *context
+                                    .accepted_connections
+                                    .entry(listener_addr)
+                                    .or_default() += 1;
+                            }
+
+                            // Keep the write half so that the node can push data to the peer
+                            let (reader, writer) = socket.into_split();
+                            context.server_streams.insert(addr, writer);
+                            context.stream_notifier.notify_waiters();
+                            reader
+                        };
 
                         let ctx_clone = ctx.clone();
+                        let config = Arc::clone(&config);
                         tokio::spawn(
                             async move {
-                                process_socket(socket, ctx_clone).await;
+                                process_socket(reader, addr, config, ctx_clone).await;
                             }
                             .instrument(tracing::info_span!("process_socket", addr = %addr)),
                         );
@@ -148,29 +784,114 @@ async fn accept_tcp(listener: tokio::net::TcpListener, addr: std::net::SocketAdd
     .await;
 }
 
+/// Returns true if a connection from `from` to `to` was recorded and no disconnect
+/// of it was recorded since.
+fn connect_active(context: &NodeContext, from: SocketAddr, to: SocketAddr) -> bool {
+    let last_connect = context
+        .connect_events
+        .iter()
+        .filter(|e| e.from == from && e.to == to)
+        .map(|e| e.seq)
+        .max();
+
+    last_connect.is_some_and(|seq| {
+        !context
+            .disconnect_events
+            .iter()
+            .any(|e| e.from == from && e.to == to && e.seq > seq)
+    })
+}
+
 /// Processes the incoming socket connection.
 /// Reads data from the socket and handles it accordingly.
-async fn process_socket(mut socket: tokio::net::TcpStream, ctx: Ctx) {
-    let mut buf = vec![0; 1024];
+async fn process_socket(socket: OwnedReadHalf, peer: SocketAddr, config: Arc<Bind>, ctx: Ctx) {
+    let mut buf = vec![0; config.buffer_size];
+    // Bytes read but not recorded yet, waiting for the rest of their record
+    let mut pending = Vec::new();
     loop {
-        match socket.read(&mut buf).await {
+        wait_reads_resumed(peer, &ctx).await;
+
+        let coalesce_ms = match config.framing {
+            Framing::Coalesce { idle_ms } if !pending.is_empty() => Some(idle_ms),
+            _ => None,
+        };
+
+        let read = if let Some(idle_ms) = coalesce_ms {
+            match tokio::time::timeout(
+                Duration::from_millis(idle_ms),
+                read_counted(&socket, &mut buf, &ctx),
+            )
+            .await
+            {
+                Ok(read) => read,
+                Err(_) => {
+                    let record = std::mem::take(&mut pending);
+                    let len = record.len();
+                    record_tcp_receive(&socket, peer, record, &config, &ctx).await;
+                    mark_recorded(&ctx, len).await;
+                    continue;
+                }
+            }
+        } else if let Some(timeout_ms) = config.idle_timeout_ms {
+            match tokio::time::timeout(
+                Duration::from_millis(timeout_ms),
+                read_counted(&socket, &mut buf, &ctx),
+            )
+            .await
+            {
+                Ok(read) => read,
+                Err(_) => {
+                    event!(
+                        tracing::Level::INFO,
+                        "No data for {}ms, closing connection",
+                        timeout_ms
+                    );
+                    record_disconnect(&socket, peer, DisconnectReason::IdleTimeout, &ctx).await;
+                    break;
+                }
+            }
+        } else {
+            read_counted(&socket, &mut buf, &ctx).await
+        };
+
+        match read {
             Ok(0) => {
                 event!(tracing::Level::INFO, "Buffer is empty, closing connection");
+                record_disconnect(&socket, peer, DisconnectReason::Fin, &ctx).await;
                 break;
             }
             Ok(n) => {
-                let addr = socket.peer_addr().unwrap();
-                event!(tracing::Level::INFO, "Read {} TCP bytes from {}", n, addr);
-
-                let received_message = ReceiveEvent {
-                    instant: tokio::time::Instant::now(),
-                    from: addr,
-                    to: socket.local_addr().unwrap(),
-                    buffer: buf[..n].to_vec(),
-                };
+                event!(tracing::Level::INFO, "Read {} TCP bytes from {}", n, peer);
 
-                ctx.lock().await.receive_events.push(received_message);
-                ctx.lock().await.receive_notifier.notify_waiters();
+                pending.extend_from_slice(&buf[..n]);
+                let read = pending.len();
+                match config.split_records(&mut pending) {
+                    Ok(records) => {
+                        for record in records {
+                            record_tcp_receive(&socket, peer, record, &config, &ctx).await;
+                        }
+                        // Headers and delimiters are consumed without being recorded
+                        mark_recorded(&ctx, read - pending.len()).await;
+                    }
+                    Err(e) => {
+                        event!(
+                            tracing::Level::WARN,
+                            "Invalid framing from {}, closing connection: {}",
+                            peer,
+                            e
+                        );
+                        mark_recorded(&ctx, pending.len()).await;
+                        pending.clear();
+                        record_disconnect(&socket, peer, DisconnectReason::FramingError, &ctx)
+                            .await;
+                        break;
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionReset => {
+                event!(tracing::Level::INFO, "Connection reset by peer");
+                record_disconnect(&socket, peer, DisconnectReason::Rst, &ctx).await;
+                break;
             }
             Err(e) => {
                 event!(tracing::Level::ERROR, "Error reading from socket: {}", e);
@@ -178,4 +899,820 @@ async fn process_socket(mut socket: tokio::net::TcpStream, ctx: Ctx) {
             }
         }
     }
+
+    if !pending.is_empty() {
+        let len = pending.len();
+        record_tcp_receive(&socket, peer, pending, &config, &ctx).await;
+        mark_recorded(&ctx, len).await;
+    }
+
+    // The peer address is no longer available once the connection is reset
+    let mut context = ctx.lock().await;
+    context.server_streams.remove(&peer);
+    context.stream_notifier.notify_waiters();
+}
+
+/// Reads from `socket` once data is available.
+///
+/// The bytes are taken from the socket and counted as unrecorded under the context lock,
+/// so that [`crate::protocol::ip::Sync`] always sees them either queued or unrecorded.
+async fn read_counted(socket: &OwnedReadHalf, buf: &mut [u8], ctx: &Ctx) -> std::io::Result<usize> {
+    loop {
+        socket.readable().await?;
+        let mut context = ctx.lock().await;
+        match socket.try_read(buf) {
+            Ok(n) => {
+                context.unrecorded_bytes += n;
+                return Ok(n);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Receives a datagram on `socket`, counting its bytes as unrecorded as [`read_counted`] does.
+async fn recv_counted(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+    ctx: &Ctx,
+) -> std::io::Result<(usize, SocketAddr)> {
+    loop {
+        socket.readable().await?;
+        let mut context = ctx.lock().await;
+        match socket.try_recv_from(buf) {
+            Ok((len, addr)) => {
+                context.unrecorded_bytes += len;
+                return Ok((len, addr));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Removes `len` bytes, now recorded or discarded, from the unrecorded bytes of the node.
+async fn mark_recorded(ctx: &Ctx, len: usize) {
+    let mut context = ctx.lock().await;
+    context.unrecorded_bytes = context.unrecorded_bytes.saturating_sub(len);
+}
+
+/// Waits until reads from `peer` are not paused.
+async fn wait_reads_resumed(peer: SocketAddr, ctx: &Ctx) {
+    loop {
+        let notifier = ctx.lock().await.reads_notifier.clone();
+        let notified = notifier.notified();
+        tokio::pin!(notified);
+        // Register before checking, a resume in between would be missed otherwise
+        notified.as_mut().enable();
+
+        let paused = ctx
+            .lock()
+            .await
+            .paused_reads
+            .keys()
+            .any(|paused| addr_match(*paused, peer));
+        if !paused {
+            return;
+        }
+
+        event!(tracing::Level::DEBUG, "Reads from {} paused", peer);
+        notified.await;
+    }
+}
+
+/// Records a message read on the accepted connection read by `socket`.
+async fn record_tcp_receive(
+    socket: &OwnedReadHalf,
+    from: SocketAddr,
+    buffer: Vec<u8>,
+    config: &Bind,
+    ctx: &Ctx,
+) {
+    let Ok(to) = socket.local_addr() else {
+        return;
+    };
+
+    let mut context = ctx.lock().await;
+    let received_message = ReceiveEvent {
+        instant: context.clock.now(),
+        seq: context.next_seq(),
+        from,
+        to,
+        buffer,
+        truncated: false,
+        phase: context.phase.clone(),
+    };
+
+    if config.keeps(&received_message) {
+        context.push_receive_event(received_message);
+        context.receive_notifier.notify_waiters();
+    }
+}
+
+/// Records the end of the accepted connection read by `socket`.
+async fn record_disconnect(
+    socket: &OwnedReadHalf,
+    from: SocketAddr,
+    reason: DisconnectReason,
+    ctx: &Ctx,
+) {
+    let Ok(to) = socket.local_addr() else {
+        return;
+    };
+
+    let mut context = ctx.lock().await;
+    let phase = context.phase.clone();
+    let instant = context.clock.now();
+    let seq = context.next_seq();
+    context.disconnect_events.push(DisconnectEvent {
+        instant,
+        seq,
+        from,
+        to,
+        reason,
+        phase,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, SocketAddr},
+        time::Duration,
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpSocket, UdpSocket},
+    };
+
+    use crate::{
+        Ctx, DisconnectReason, Node,
+        action::{Action, ActionError, Sleep},
+        protocol::Protocol,
+    };
+
+    use super::{Bind, Framing, split_frames, split_lines};
+
+    #[tokio::test]
+    async fn test_udp_datagram_larger_than_buffer_is_truncated() {
+        let node = Node::new("test-node");
+        let bind = Bind::new("127.0.1.1:3000".parse().unwrap()).with_buffer_size(16);
+        bind.perform(node.ctx()).await.unwrap();
+
+        let socket = UdpSocket::bind("127.0.1.1:0").await.unwrap();
+        let mut received = Vec::new();
+        for _ in 0..50 {
+            socket
+                .send_to(&[0xAB; 32], "127.0.1.1:49999")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            received = node.ctx().lock().await.receive_events.clone();
+            if !received.is_empty() {
+                break;
+            }
+        }
+
+        assert!(!received.is_empty());
+        assert!(received[0].truncated);
+        assert_eq!(received[0].buffer, vec![0xAB; 16]);
+    }
+
+    #[tokio::test]
+    async fn test_udp_datagram_fits_larger_buffer() {
+        let node = Node::new("test-node");
+        let bind = Bind::new("127.0.1.81:3000".parse().unwrap()).with_buffer_size(8192);
+        bind.perform(node.ctx()).await.unwrap();
+
+        let datagram: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+        let socket = UdpSocket::bind("127.0.1.81:0").await.unwrap();
+        let mut received = Vec::new();
+        for _ in 0..50 {
+            socket.send_to(&datagram, "127.0.1.81:49999").await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            received = node.ctx().lock().await.receive_events.clone();
+            if !received.is_empty() {
+                break;
+            }
+        }
+
+        assert!(!received.is_empty());
+        assert!(!received[0].truncated);
+        assert_eq!(received[0].buffer, datagram);
+    }
+
+    #[tokio::test]
+    async fn test_accept_allowlist() {
+        let server: SocketAddr = "127.0.1.12:3000".parse().unwrap();
+        let allowed: IpAddr = "127.0.1.13".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_accept_allowlist(vec![allowed])
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let allowed_socket = TcpSocket::new_v4().unwrap();
+        allowed_socket
+            .bind("127.0.1.13:0".parse().unwrap())
+            .unwrap();
+        let mut allowed_stream = allowed_socket.connect(server).await.unwrap();
+        allowed_stream.write_all(&[1, 2, 3]).await.unwrap();
+
+        let denied_socket = TcpSocket::new_v4().unwrap();
+        denied_socket.bind("127.0.1.14:0".parse().unwrap()).unwrap();
+        let mut denied_stream = denied_socket.connect(server).await.unwrap();
+
+        // The server closes the rejected connection right away
+        let mut buf = [0; 16];
+        let closed = tokio::time::timeout(Duration::from_secs(1), denied_stream.read(&mut buf))
+            .await
+            .unwrap();
+        assert!(matches!(closed, Ok(0) | Err(_)));
+
+        for _ in 0..50 {
+            if !ctx.lock().await.receive_events.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let context = ctx.lock().await;
+        assert_eq!(context.connect_events.len(), 1);
+        assert_eq!(context.connect_events[0].from.ip(), allowed);
+        assert_eq!(context.receive_events[0].buffer, vec![1, 2, 3]);
+        assert_eq!(context.reject_events.len(), 1);
+        assert_eq!(
+            context.reject_events[0].from.ip(),
+            "127.0.1.14".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_reorder_window() {
+        let node = Node::with_seed("test-node", 42);
+        let ctx = node.ctx();
+
+        Bind::new("127.0.1.15:3000".parse().unwrap())
+            .with_reorder_window(4)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let socket = UdpSocket::bind("127.0.1.16:0").await.unwrap();
+        for i in 0..4u8 {
+            socket.send_to(&[i], "127.0.1.15:49999").await.unwrap();
+        }
+
+        for _ in 0..50 {
+            if ctx.lock().await.receive_events.len() == 4 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let recorded: Vec<u8> = ctx
+            .lock()
+            .await
+            .receive_events
+            .iter()
+            .map(|e| e.buffer[0])
+            .collect();
+
+        let mut sorted = recorded.clone();
+        sorted.sort();
+        assert_eq!(sorted, vec![0, 1, 2, 3]);
+        assert_ne!(recorded, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_connects() {
+        // Opens a connection from `client`, reset when dropped
+        let connect = |client: SocketAddr, server: SocketAddr| {
+            let socket = TcpSocket::new_v4().unwrap();
+            socket.set_reuseaddr(true).unwrap();
+            socket.set_linger(Some(Duration::ZERO)).unwrap();
+            socket.bind(client).unwrap();
+            socket.connect(server)
+        };
+        let wait_connections = |ctx: Ctx, count: u64| async move {
+            while ctx.lock().await.total_connections < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        // A client reconnects from the same address before its reset is read
+        for (server, client, dedup, expected) in [
+            ("127.0.1.19:3000", "127.0.1.21:4000", true, 1),
+            ("127.0.1.20:3000", "127.0.1.21:4001", false, 2),
+        ] {
+            let (server, client) = (server.parse().unwrap(), client.parse().unwrap());
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+            ctx.lock()
+                .await
+                .paused_reads
+                .insert(client, tokio::time::Instant::now());
+
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .with_dedup_connects(dedup)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+
+            for count in 1..=2 {
+                let stream = connect(client, server).await.unwrap();
+                wait_connections(ctx.clone(), count).await;
+                drop(stream);
+            }
+
+            assert_eq!(ctx.lock().await.connect_events.len(), expected);
+        }
+
+        // A reconnect after a recorded disconnect, then distinct clients sharing an ip
+        let server: SocketAddr = "127.0.1.130:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.131:4000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_dedup_connects(true)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        for count in 1..=2 {
+            drop(connect(client, server).await.unwrap());
+            wait_connections(ctx.clone(), count).await;
+            while ctx.lock().await.disconnect_events.len() < count as usize {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        let mut clients = Vec::new();
+        for port in [4001, 4002] {
+            let client = SocketAddr::new(client.ip(), port);
+            clients.push(connect(client, server).await.unwrap());
+        }
+        wait_connections(ctx.clone(), 4).await;
+
+        assert_eq!(ctx.lock().await.connect_events.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_bind_retries() {
+        let server: SocketAddr = "127.0.1.24:3000".parse().unwrap();
+        let node = Node::new("test-node");
+
+        // The port is held by another listener for a short while
+        let held = std::net::TcpListener::bind(server).unwrap();
+        assert!(matches!(
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .perform(node.ctx())
+                .await,
+            Err(ActionError::BindError(_))
+        ));
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            drop(held);
+        });
+
+        assert!(
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .with_bind_retries(10, 50)
+                .perform(node.ctx())
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_closes_connection() {
+        let server: SocketAddr = "127.0.1.38:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_idle_timeout(100)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.1.39:0".parse().unwrap()).unwrap();
+        let mut client = socket.connect(server).await.unwrap();
+
+        // The server closes the connection once the timeout expires
+        let mut buf = [0; 16];
+        let read = tokio::time::timeout(Duration::from_secs(1), client.read(&mut buf))
+            .await
+            .unwrap();
+        assert_eq!(read.unwrap(), 0);
+
+        let context = ctx.lock().await;
+        assert_eq!(context.disconnect_events.len(), 1);
+        assert_eq!(
+            context.disconnect_events[0].reason,
+            DisconnectReason::IdleTimeout
+        );
+        assert_eq!(
+            context.disconnect_events[0].from,
+            client.local_addr().unwrap()
+        );
+        assert!(context.server_streams.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_receive_filter() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new("127.0.1.43:3000".parse().unwrap())
+            .with_receive_filter(Box::new(|event| event.buffer.len() % 2 == 1))
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let socket = UdpSocket::bind("127.0.1.44:0").await.unwrap();
+        for len in 1..=6 {
+            socket
+                .send_to(&vec![0; len], "127.0.1.43:49999")
+                .await
+                .unwrap();
+        }
+
+        let mut stream = tokio::net::TcpStream::connect("127.0.1.43:3000")
+            .await
+            .unwrap();
+        stream.write_all(b"odd").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(b"even").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut lengths: Vec<usize> = ctx
+            .lock()
+            .await
+            .receive_events
+            .iter()
+            .map(|e| e.buffer.len())
+            .collect();
+        lengths.sort();
+        assert_eq!(lengths, vec![1, 3, 3, 5]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_ssm_source_filtering() {
+        let group: SocketAddr = "232.1.1.1:3000".parse().unwrap();
+
+        // Address the kernel picks to send to the group, the source of the datagrams
+        let probe = std::net::UdpSocket::bind("0.0.0.0:0").unwrap();
+        probe.connect(group).unwrap();
+        let IpAddr::V4(source) = probe.local_addr().unwrap().ip() else {
+            unreachable!()
+        };
+
+        let joined = Node::new("joined");
+        Bind::new(group)
+            .with_protocol(Protocol::Udp)
+            .with_ssm_source(source)
+            .perform(joined.ctx())
+            .await
+            .unwrap();
+        let other = Node::new("other");
+        Bind::new(group)
+            .with_protocol(Protocol::Udp)
+            .with_ssm_source("192.0.2.123".parse().unwrap())
+            .perform(other.ctx())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let sender = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+        sender.set_multicast_loop_v4(true).unwrap();
+        sender.send_to(b"ssm", group).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let received = joined.ctx().lock().await.receive_events.clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].from.ip(), IpAddr::V4(source));
+        assert!(other.ctx().lock().await.receive_events.is_empty());
+    }
+
+    /// Waits for `n` events to be received, then returns the received buffers.
+    async fn wait_for_events(ctx: &Ctx, n: usize) -> Vec<Vec<u8>> {
+        for _ in 0..50 {
+            if ctx.lock().await.receive_events.len() >= n {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        ctx.lock()
+            .await
+            .receive_events
+            .iter()
+            .map(|e| e.buffer.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_fixed_record_size() {
+        let server: SocketAddr = "127.0.1.61:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_fixed_record_size(1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = tokio::net::TcpStream::connect(server).await.unwrap();
+        for chunk in [700, 1100, 700] {
+            client.write_all(&vec![1; chunk]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let lengths = |ctx: &crate::Ctx| {
+            let ctx = ctx.clone();
+            async move {
+                ctx.lock()
+                    .await
+                    .receive_events
+                    .iter()
+                    .map(|e| e.buffer.len())
+                    .collect::<Vec<usize>>()
+            }
+        };
+        assert_eq!(lengths(&ctx).await, vec![1000, 1000]);
+
+        drop(client);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(lengths(&ctx).await, vec![1000, 1000, 500]);
+    }
+
+    #[test]
+    fn test_split_frames() {
+        // Two frames in one segment, then a frame split across two segments
+        let mut pending = vec![0, 2, 1, 2, 0, 1, 3, 0, 3, 4];
+        assert_eq!(split_frames(&mut pending, 2), Ok(vec![vec![1, 2], vec![3]]));
+        assert_eq!(pending, vec![0, 3, 4]);
+
+        pending.extend_from_slice(&[5, 6]);
+        assert_eq!(split_frames(&mut pending, 2), Ok(vec![vec![4, 5, 6]]));
+        assert!(pending.is_empty());
+
+        // A header this large would overflow the end of the frame
+        let mut pending = vec![0xff; 9];
+        assert!(split_frames(&mut pending, 8).is_err());
+    }
+
+    #[test]
+    fn test_split_lines() {
+        // Two lines in one read, then a delimiter split across two reads
+        let mut pending = b"EHLO a\r\nMAIL\r\nDATA\r".to_vec();
+        assert_eq!(
+            split_lines(&mut pending, b"\r\n", false),
+            vec![b"EHLO a".to_vec(), b"MAIL".to_vec()]
+        );
+        assert_eq!(pending, b"DATA\r".to_vec());
+
+        pending.extend_from_slice(b"\nQU");
+        assert_eq!(
+            split_lines(&mut pending, b"\r\n", true),
+            vec![b"DATA\r\n".to_vec()]
+        );
+        assert_eq!(pending, b"QU".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_delimiter_framing() {
+        let server: SocketAddr = "127.0.1.83:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        let invalid =
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .with_framing(Framing::Delimiter {
+                    delimiter: Vec::new(),
+                    keep_delimiter: false,
+                });
+        assert!(matches!(
+            invalid.perform(ctx.clone()).await,
+            Err(ActionError::BindError(_))
+        ));
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::Delimiter {
+                delimiter: b"\r\n".to_vec(),
+                keep_delimiter: false,
+            })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = tokio::net::TcpStream::connect(server).await.unwrap();
+        client.set_nodelay(true).unwrap();
+        // The delimiter straddles two reads
+        client.write_all(b"PING\r").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        client.write_all(b"\n").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Two lines in one read
+        client.write_all(b"SET a 1\r\nGET a\r\n").await.unwrap();
+
+        let buffers = wait_for_events(&ctx, 3).await;
+
+        assert_eq!(
+            buffers,
+            vec![b"PING".to_vec(), b"SET a 1".to_vec(), b"GET a".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coalesce_framing() {
+        let server: SocketAddr = "127.0.1.91:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_buffer_size(4)
+            .with_framing(Framing::Coalesce { idle_ms: 100 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = tokio::net::TcpStream::connect(server).await.unwrap();
+        client.set_nodelay(true).unwrap();
+        client.write_all(b"HELLO ").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        client.write_all(b"WORLD").await.unwrap();
+
+        let buffers = wait_for_events(&ctx, 1).await;
+
+        // The connection is still open, the bytes are recorded once idle
+        assert_eq!(buffers, vec![b"HELLO WORLD".to_vec()]);
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_length_prefixed_framing() {
+        let server: SocketAddr = "127.0.1.82:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::LengthPrefixed { header_bytes: 4 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = tokio::net::TcpStream::connect(server).await.unwrap();
+        client.set_nodelay(true).unwrap();
+        // A message split across two segments
+        client.write_all(&[0, 0, 0, 5, b'H', b'E']).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        client.write_all(b"LLO").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Two messages in one segment
+        client
+            .write_all(&[0, 0, 0, 1, b'A', 0, 0, 0, 2, b'B', b'C'])
+            .await
+            .unwrap();
+
+        let buffers = wait_for_events(&ctx, 3).await;
+
+        assert_eq!(
+            buffers,
+            vec![b"HELLO".to_vec(), b"A".to_vec(), b"BC".to_vec()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame_closes_connection() {
+        let server: SocketAddr = "127.0.1.129:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        let invalid = Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::LengthPrefixed { header_bytes: 0 });
+        assert!(matches!(
+            invalid.perform(ctx.clone()).await,
+            Err(ActionError::BindError(_))
+        ));
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::LengthPrefixed { header_bytes: 8 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = tokio::net::TcpStream::connect(server).await.unwrap();
+        client.write_all(&[0xff; 8]).await.unwrap();
+
+        // The node closes the connection instead of waiting for the frame
+        let mut buf = [0; 1];
+        let read = tokio::time::timeout(Duration::from_secs(1), client.read(&mut buf)).await;
+        assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))), "{:?}", read);
+
+        let context = ctx.lock().await;
+        assert!(context.receive_events.is_empty());
+        let reasons: Vec<DisconnectReason> =
+            context.disconnect_events.iter().map(|e| e.reason).collect();
+        assert_eq!(reasons, vec![DisconnectReason::FramingError]);
+    }
+
+    #[tokio::test]
+    async fn test_bind_ipv6() {
+        let node = Node::new("test-node");
+        Bind::new("[::1]:0".parse().unwrap())
+            .with_protocol(Protocol::Tcp)
+            .perform(node.ctx())
+            .await
+            .unwrap();
+        Bind::new("[::1]:0".parse().unwrap())
+            .with_protocol(Protocol::Udp)
+            .perform(node.ctx())
+            .await
+            .unwrap();
+
+        let mut bound = Vec::new();
+        for _ in 0..50 {
+            bound = node.ctx().lock().await.bound.clone();
+            if bound.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(bound.len(), 2);
+        assert!(bound.iter().all(|addr| addr.is_ipv6() && addr.port() != 0));
+
+        // Binding the address of the TCP listener again fails with the reason
+        let result = Bind::new(bound[0])
+            .with_protocol(Protocol::Tcp)
+            .perform(node.ctx())
+            .await;
+        assert!(
+            matches!(result, Err(ActionError::BindError(e)) if e.contains(&bound[0].to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_bind_failure_keeps_node_running() {
+        let held: SocketAddr = "127.0.1.94:3000".parse().unwrap();
+        let server: SocketAddr = "127.0.1.94:3001".parse().unwrap();
+        let _socket = std::net::UdpSocket::bind(held).unwrap();
+
+        let mut node = Node::new("test-node");
+        node.add_action(Bind::new(held).with_protocol(Protocol::Udp));
+        node.add_action(Sleep::new(50));
+        node.add_action(Bind::new(server).with_protocol(Protocol::Tcp));
+        node.start().await;
+
+        // The UDP listener gave up, the following actions still ran
+        assert_eq!(node.ctx().lock().await.bound, vec![server]);
+    }
+
+    #[tokio::test]
+    async fn test_bind_from_json() {
+        let action: Box<dyn Action> =
+            serde_json::from_str(r#"{"Bind":{"to":"127.0.1.68:3000"}}"#).unwrap();
+        assert_eq!(action.name(), "BIND");
+
+        let node = Node::new("test-node");
+        action.perform(node.ctx()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // Without a protocol, UDP is also bound on the default port
+        let mut bound = node.ctx().lock().await.bound.clone();
+        bound.sort();
+        assert_eq!(
+            bound,
+            vec![
+                "127.0.1.68:3000".parse::<SocketAddr>().unwrap(),
+                SocketAddr::new("127.0.1.68".parse().unwrap(), super::DEFAULT_UDP_PORT)
+            ]
+        );
+
+        // Defaults are filled in for the omitted fields
+        let json = serde_json::to_string(&action).unwrap();
+        let bind: Bind = serde_json::from_str(r#"{"to":"127.0.1.68:3000"}"#).unwrap();
+        assert_eq!(bind, Bind::new("127.0.1.68:3000".parse().unwrap()));
+        assert!(json.contains("\"buffer_size\""));
+    }
 }
@@ -0,0 +1,284 @@
+use std::{
+    ffi::CString,
+    net::Ipv4Addr,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::unix::AsyncFd;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{CapturedFrame, Ctx},
+};
+
+/// Largest frame read from the capture socket, frames above are truncated.
+const CAPTURE_FRAME_SIZE: usize = 65536;
+
+/// Captures the raw frames seen on `interface` with an `AF_PACKET` socket, recording them
+/// as [`CapturedFrame`]s until the node is cancelled or finishes its actions.
+///
+/// `filter` is a comma separated list of IPv4 addresses, only the frames from or to one
+/// of them are kept. Every frame is kept without a filter.
+///
+/// Opening the socket requires root or the `CAP_NET_RAW` capability.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Capture {
+    interface: String,
+    #[serde(default)]
+    filter: Option<String>,
+}
+
+impl Capture {
+    pub fn new(interface: &str) -> Self {
+        Capture {
+            interface: interface.to_string(),
+            filter: None,
+        }
+    }
+
+    /// Keeps only the frames from or to the given comma separated IPv4 addresses.
+    pub fn with_filter(mut self, filter: &str) -> Self {
+        self.filter = Some(filter.to_string());
+        self
+    }
+
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    fn addresses(&self) -> Result<Vec<Ipv4Addr>, ActionError> {
+        let Some(filter) = &self.filter else {
+            return Ok(Vec::new());
+        };
+
+        filter
+            .split(',')
+            .map(|addr| {
+                addr.trim().parse().map_err(|_| {
+                    event!(tracing::Level::ERROR, "Invalid capture filter {}", filter);
+                    ActionError::BindError(format!("Invalid capture filter {}", filter))
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for Capture {
+    fn name(&self) -> String {
+        "CAPTURE".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let addresses = self.addresses()?;
+        let socket = open_packet_socket(&self.interface)
+            .and_then(AsyncFd::new)
+            .map_err(|e| {
+                event!(
+                    tracing::Level::ERROR,
+                    "Error opening a capture socket on {} ({})",
+                    self.interface,
+                    e
+                );
+                ActionError::BindError(format!(
+                    "Error opening a capture socket on {} ({})",
+                    self.interface, e
+                ))
+            })?;
+
+        event!(
+            tracing::Level::INFO,
+            "Capturing frames on {}",
+            self.interface
+        );
+        let mut cancelled = ctx.lock().await.cancelled.subscribe();
+        let interface = self.interface.clone();
+        tokio::spawn(async move {
+            let cancelled = async move {
+                let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+            };
+            tokio::pin!(cancelled);
+
+            let mut buffer = vec![0; CAPTURE_FRAME_SIZE];
+            loop {
+                let len = tokio::select! {
+                    _ = &mut cancelled => break,
+                    len = recv(&socket, &mut buffer) => len,
+                };
+                let frame = match len {
+                    Ok(len) => &buffer[..len],
+                    Err(e) => {
+                        event!(
+                            tracing::Level::ERROR,
+                            "Capture on {} failed ({})",
+                            interface,
+                            e
+                        );
+                        break;
+                    }
+                };
+
+                if !addresses.is_empty() && !matches_addresses(frame, &addresses) {
+                    continue;
+                }
+
+                let mut context = ctx.lock().await;
+                let instant = context.clock.now();
+                context.captured_frames.push(CapturedFrame {
+                    instant,
+                    frame: frame.to_vec(),
+                });
+            }
+
+            event!(tracing::Level::INFO, "Capture on {} stopped", interface);
+        });
+
+        Ok(())
+    }
+}
+
+/// Opens a non-blocking `AF_PACKET` socket receiving every frame of `interface`.
+fn open_packet_socket(interface: &str) -> std::io::Result<OwnedFd> {
+    let name = CString::new(interface)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `name` is a valid NUL terminated string
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if index == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let protocol = (libc::ETH_P_ALL as u16).to_be();
+    // SAFETY: plain socket creation, the descriptor is owned right after
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_PACKET,
+            libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            protocol as libc::c_int,
+        )
+    };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a new descriptor owned by nothing else
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: sockaddr_ll is a plain C struct, all zeroes is a valid value
+    let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as libc::c_ushort;
+    addr.sll_protocol = protocol;
+    addr.sll_ifindex = index as libc::c_int;
+    // SAFETY: `addr` is a valid sockaddr_ll of the given length
+    let res = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    if res < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(fd)
+}
+
+async fn recv(socket: &AsyncFd<OwnedFd>, buffer: &mut [u8]) -> std::io::Result<usize> {
+    loop {
+        let mut guard = socket.readable().await?;
+        let res = guard.try_io(|socket| {
+            // SAFETY: `buffer` is valid for writes of its length
+            let len = unsafe {
+                libc::recv(
+                    socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if len < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(len as usize)
+            }
+        });
+
+        if let Ok(res) = res {
+            return res;
+        }
+    }
+}
+
+/// Returns true if `frame` is an IPv4 packet over Ethernet from or to one of `addresses`.
+fn matches_addresses(frame: &[u8], addresses: &[Ipv4Addr]) -> bool {
+    const ETHERTYPE_IPV4: [u8; 2] = [0x08, 0x00];
+    if frame.len() < 34 || frame[12..14] != ETHERTYPE_IPV4 {
+        return false;
+    }
+
+    let addr = |offset: usize| {
+        Ipv4Addr::new(
+            frame[offset],
+            frame[offset + 1],
+            frame[offset + 2],
+            frame[offset + 3],
+        )
+    };
+    let (source, destination) = (addr(26), addr(30));
+    addresses.contains(&source) || addresses.contains(&destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use crate::{Node, action::Action};
+
+    use super::{Capture, matches_addresses};
+
+    #[test]
+    fn test_matches_addresses() {
+        let mut frame = vec![0; 34];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        frame[26..30].copy_from_slice(&[127, 0, 1, 69]);
+        frame[30..34].copy_from_slice(&[127, 0, 1, 70]);
+
+        assert!(matches_addresses(&frame, &[Ipv4Addr::new(127, 0, 1, 70)]));
+        assert!(!matches_addresses(&frame, &[Ipv4Addr::new(127, 0, 1, 71)]));
+        // Not IPv4
+        frame[12..14].copy_from_slice(&[0x86, 0xdd]);
+        assert!(!matches_addresses(&frame, &[Ipv4Addr::new(127, 0, 1, 70)]));
+    }
+
+    #[tokio::test]
+    #[ignore = "requires CAP_NET_RAW"]
+    async fn test_capture_loopback() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Capture::new("lo")
+            .with_filter("127.0.1.69")
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let socket = std::net::UdpSocket::bind("127.0.1.69:0").unwrap();
+        socket.send_to(b"captured", "127.0.1.70:3000").unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        node.cancel().await;
+
+        let context = ctx.lock().await;
+        assert!(
+            context
+                .captured_frames
+                .iter()
+                .any(|frame| frame.frame.ends_with(b"captured"))
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{CounterSnapshot, Ctx},
+};
+
+/// Snapshots the traffic counters of the node under `label`, replacing
+/// a previous checkpoint with the same label.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CounterCheckpoint {
+    label: String,
+}
+
+impl CounterCheckpoint {
+    pub fn new(label: &str) -> Self {
+        CounterCheckpoint {
+            label: label.to_string(),
+        }
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for CounterCheckpoint {
+    fn name(&self) -> String {
+        "COUNTER_CHECKPOINT".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let mut context = ctx.lock().await;
+        let counters = context.counters();
+        event!(
+            tracing::Level::INFO,
+            "Checkpoint {}: {} bytes sent, {} bytes received",
+            self.label,
+            counters.bytes_sent,
+            counters.bytes_received
+        );
+        context.checkpoints.insert(self.label.clone(), counters);
+
+        Ok(())
+    }
+}
+
+/// Asserts at least `min_bytes_received` bytes were received between
+/// the checkpoints `from_label` and `to_label`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertDelta {
+    from_label: String,
+    to_label: String,
+    min_bytes_received: u64,
+}
+
+impl AssertDelta {
+    pub fn new(from_label: &str, to_label: &str, min_bytes_received: u64) -> Self {
+        AssertDelta {
+            from_label: from_label.to_string(),
+            to_label: to_label.to_string(),
+            min_bytes_received,
+        }
+    }
+
+    pub fn from_label(&self) -> &str {
+        &self.from_label
+    }
+
+    pub fn to_label(&self) -> &str {
+        &self.to_label
+    }
+
+    pub fn min_bytes_received(&self) -> u64 {
+        self.min_bytes_received
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertDelta {
+    fn name(&self) -> String {
+        "ASSERT_DELTA".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let (from, to) = {
+            let context = ctx.lock().await;
+            let checkpoint = |label: &str| -> Result<CounterSnapshot, ActionError> {
+                context.checkpoints.get(label).copied().ok_or_else(|| {
+                    ActionError::AssertError(format!("No checkpoint labelled {}", label))
+                })
+            };
+            (checkpoint(&self.from_label)?, checkpoint(&self.to_label)?)
+        };
+
+        let received = to.bytes_received.saturating_sub(from.bytes_received);
+        event!(
+            tracing::Level::INFO,
+            "Received {} bytes between checkpoints {} and {}",
+            received,
+            self.from_label,
+            self.to_label
+        );
+
+        if received < self.min_bytes_received {
+            return Err(ActionError::AssertError(format!(
+                "Received {} bytes between checkpoints {} and {}, expected at least {}",
+                received, self.from_label, self.to_label, self.min_bytes_received
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect, Send, SendMode},
+        },
+    };
+
+    use super::{AssertDelta, CounterCheckpoint};
+
+    #[tokio::test]
+    async fn test_assert_received_bytes_delta() {
+        let server: SocketAddr = "127.0.1.89:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        let client: SocketAddr = "127.0.1.90:0".parse().unwrap();
+        Connect::new(client, server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        CounterCheckpoint::new("before")
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Send::new(SendMode::Unicast, client, server, vec![1; 100])
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        for _ in 0..50 {
+            if ctx.lock().await.counters().bytes_received == 100 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        CounterCheckpoint::new("after")
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        assert!(
+            AssertDelta::new("before", "after", 100)
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            AssertDelta::new("before", "after", 101)
+                .perform(ctx.clone())
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+        assert!(matches!(
+            AssertDelta::new("before", "missing", 0).perform(ctx).await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+}
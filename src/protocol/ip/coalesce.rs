@@ -0,0 +1,133 @@
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{Ctx, ReceiveEvent},
+    protocol::ip::addr_match,
+};
+
+/// Merges the fragments of logical messages received from `from`.
+///
+/// Consecutive receive events of the same connection that arrive at most `gap_ms`
+/// after the previous fragment are replaced by a single event, stamped with the
+/// instant of the first fragment and holding the concatenated buffers.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CoalesceReceives {
+    from: SocketAddr,
+    gap_ms: u64,
+}
+
+impl CoalesceReceives {
+    pub fn new(from: SocketAddr, gap_ms: u64) -> Self {
+        CoalesceReceives { from, gap_ms }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn gap_ms(&self) -> u64 {
+        self.gap_ms
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for CoalesceReceives {
+    fn name(&self) -> String {
+        "COALESCE_RECEIVES".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let mut context = ctx.lock().await;
+        let events = std::mem::take(&mut context.receive_events);
+        let before = events.len();
+        context.receive_events = coalesce(events, self.from, Duration::from_millis(self.gap_ms));
+
+        event!(
+            tracing::Level::INFO,
+            "Coalesced {} receive events into {}",
+            before,
+            context.receive_events.len()
+        );
+        Ok(())
+    }
+}
+
+fn coalesce(events: Vec<ReceiveEvent>, from: SocketAddr, gap: Duration) -> Vec<ReceiveEvent> {
+    let mut coalesced: Vec<ReceiveEvent> = Vec::with_capacity(events.len());
+    // Index of the message being merged and instant of its last fragment, by connection
+    let mut open: HashMap<(SocketAddr, SocketAddr), (usize, Instant)> = HashMap::new();
+
+    for event in events {
+        if !addr_match(from, event.from) {
+            coalesced.push(event);
+            continue;
+        }
+
+        let key = (event.from, event.to);
+        match open.get_mut(&key) {
+            Some((idx, last)) if event.instant.duration_since(*last) <= gap => {
+                *last = event.instant;
+                let merged = &mut coalesced[*idx];
+                merged.buffer.extend_from_slice(&event.buffer);
+                merged.truncated |= event.truncated;
+            }
+            _ => {
+                open.insert(key, (coalesced.len(), event.instant));
+                coalesced.push(event);
+            }
+        }
+    }
+
+    coalesced
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::time::Instant;
+
+    use crate::{Node, ReceiveEvent, action::Action};
+
+    use super::CoalesceReceives;
+
+    #[tokio::test]
+    async fn test_coalesce_receives() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let other: SocketAddr = "127.0.0.2:3000".parse().unwrap();
+        let start = Instant::now();
+        let event = |from, offset_ms, buffer: &[u8]| ReceiveEvent {
+            instant: start + Duration::from_millis(offset_ms),
+            seq: 0,
+            from,
+            to: "127.0.0.3:4000".parse().unwrap(),
+            buffer: buffer.to_vec(),
+            truncated: false,
+            phase: None,
+        };
+
+        let node = Node::new("test-node");
+        node.ctx().lock().await.receive_events = vec![
+            event(from, 0, b"HEL"),
+            event(other, 2, b"x"),
+            event(from, 5, b"LO"),
+            event(from, 100, b"BYE"),
+        ];
+
+        CoalesceReceives::new("127.0.0.1:0".parse().unwrap(), 10)
+            .perform(node.ctx())
+            .await
+            .unwrap();
+
+        let events = node.ctx().lock().await.receive_events.clone();
+        let buffers: Vec<&[u8]> = events.iter().map(|e| e.buffer.as_slice()).collect();
+        assert_eq!(buffers, [&b"HELLO"[..], b"x", b"BYE"]);
+        assert_eq!(events[0].instant, start);
+    }
+}
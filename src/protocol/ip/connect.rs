@@ -1,10 +1,20 @@
 use crate::{
     action::{Action, ActionError},
-    node::{ConnectEvent, Ctx},
+    node::{ConnectEvent, Ctx, ReceiveEvent, SendEvent},
+    protocol::ip::{Send, SendMode},
+    resolver::resolve_endpoint,
 };
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tokio::net::TcpSocket;
+use std::{
+    net::SocketAddr,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, ReadBuf},
+    net::{TcpSocket, TcpStream},
+    task::AbortHandle,
+};
 use tracing::event;
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -12,8 +22,17 @@ pub struct Connect {
     from: std::net::SocketAddr,
     to: std::net::SocketAddr,
     timeout_ms: u64,
+    #[serde(default)]
+    force_new: bool,
+    #[serde(default)]
+    to_host: Option<String>,
+    #[serde(default)]
+    read_responses: bool,
 }
 
+/// Size of the buffer used to read the responses on an outbound connection.
+const RESPONSE_BUFFER_SIZE: usize = 1024;
+
 impl Connect {
     /// Creates a new Connect action.
     pub fn new(from: std::net::SocketAddr, to: std::net::SocketAddr, timeout_ms: u64) -> Self {
@@ -21,9 +40,30 @@ impl Connect {
             from,
             to,
             timeout_ms,
+            force_new: false,
+            to_host: None,
+            read_responses: false,
         }
     }
 
+    /// Dials a new connection even if one to `to` is still open, closing the old one.
+    pub fn with_force_new(mut self, force_new: bool) -> Self {
+        self.force_new = force_new;
+        self
+    }
+
+    /// Connects to the `host:port` endpoint resolved by the node resolver instead of `to`.
+    pub fn with_to_host(mut self, endpoint: &str) -> Self {
+        self.to_host = Some(endpoint.to_string());
+        self
+    }
+
+    /// Records the data sent back by the peer on the connection as receive events.
+    pub fn with_read_responses(mut self, read_responses: bool) -> Self {
+        self.read_responses = read_responses;
+        self
+    }
+
     pub fn from(&self) -> &std::net::SocketAddr {
         &self.from
     }
@@ -35,6 +75,18 @@ impl Connect {
     pub fn timeout_ms(&self) -> u64 {
         self.timeout_ms
     }
+
+    pub fn force_new(&self) -> bool {
+        self.force_new
+    }
+
+    pub fn to_host(&self) -> Option<&str> {
+        self.to_host.as_deref()
+    }
+
+    pub fn read_responses(&self) -> bool {
+        self.read_responses
+    }
 }
 
 #[async_trait::async_trait]
@@ -46,6 +98,43 @@ impl Action for Connect {
 
     /// Attempts to connect from `from` to `to` using a TCP socket.
     async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if let Some(endpoint) = &self.to_host {
+            let to = resolve_endpoint(&ctx, endpoint).await.map_err(|e| {
+                ActionError::ConnectError(format!("Error resolving {} ({})", endpoint, e))
+            })?;
+            let resolved = Connect {
+                to,
+                to_host: None,
+                ..self.clone()
+            };
+            return resolved.perform(ctx).await;
+        }
+
+        // Take the stream to replace out of the context, so that it is not locked while closing
+        let replaced = {
+            let mut context = ctx.lock().await;
+            match context.tcp_streams.get(&self.to) {
+                Some(stream) if !self.force_new && is_open(stream) => {
+                    event!(
+                        tracing::Level::INFO,
+                        "Reusing the connection to {}",
+                        self.to
+                    );
+                    return Ok(());
+                }
+                Some(_) => context.close_tcp_stream(&self.to),
+                None => None,
+            }
+        };
+        if let Some(mut stream) = replaced {
+            event!(
+                tracing::Level::INFO,
+                "Closing the connection to {}",
+                self.to
+            );
+            let _ = stream.shutdown().await;
+        }
+
         event!(
             tracing::Level::INFO,
             "Connecting from {} to {}",
@@ -53,45 +142,600 @@ impl Action for Connect {
             self.to
         );
 
-        let socket = TcpSocket::new_v4().map_err(|error| {
-            ActionError::ConnectError(format!("Error creating socket for {} ({})", self.to, error))
-        })?;
+        let mut stream = dial(self.from, self.to, self.timeout_ms).await?;
+        // Record the source port picked by the system rather than the requested one
+        let local_addr = stream.local_addr().unwrap_or(self.from);
+        let mut reader = None;
+        if self.read_responses {
+            let (writer, handle) = spawn_response_reader(stream, self.to, ctx.clone())?;
+            stream = writer;
+            reader = Some(handle);
+        }
 
-        socket.bind(self.from).map_err(|error| {
-            ActionError::ConnectError(format!("Error binding socket to {} ({})", self.from, error))
-        })?;
+        event!(tracing::Level::INFO, "Connected to {}", self.to);
+        let mut context = ctx.lock().await;
+        context.tcp_streams.insert(self.to, stream);
+        if let Some(previous) =
+            reader.and_then(|reader| context.response_readers.insert(self.to, reader))
+        {
+            previous.abort();
+        }
 
-        let stream = tokio::time::timeout(
-            Duration::from_millis(self.timeout_ms),
-            socket.connect(self.to),
-        )
+        let connect_event = ConnectEvent {
+            instant: context.clock.now(),
+            seq: context.next_seq(),
+            from: local_addr,
+            to: self.to,
+            phase: context.phase.clone(),
+        };
+
+        // Store the connect event in the context and signal every task waiting for it
+        context.connect_events.push(connect_event);
+        context.record_connection();
+        context.connect_notifier.notify_waiters();
+        context.stream_notifier.notify_waiters();
+
+        Ok(())
+    }
+}
+
+/// Opens a TCP connection from `from` to `to`, failing after `timeout_ms`.
+pub(crate) async fn dial(
+    from: SocketAddr,
+    to: SocketAddr,
+    timeout_ms: u64,
+) -> Result<TcpStream, ActionError> {
+    let socket = match to {
+        SocketAddr::V4(_) => TcpSocket::new_v4(),
+        SocketAddr::V6(_) => TcpSocket::new_v6(),
+    }
+    .map_err(|error| {
+        ActionError::ConnectError(format!("Error creating socket for {} ({})", to, error))
+    })?;
+
+    // Fixed source ports of previous runs may still be in TIME_WAIT
+    socket.set_reuseaddr(true).map_err(|error| {
+        ActionError::ConnectError(format!("Error configuring socket for {} ({})", to, error))
+    })?;
+    socket.bind(from).map_err(|error| {
+        ActionError::ConnectError(format!("Error binding socket to {} ({})", from, error))
+    })?;
+
+    tokio::time::timeout(Duration::from_millis(timeout_ms), socket.connect(to))
         .await
         .map_err(|_| {
-            ActionError::ConnectError(format!(
-                "Timeout connecting to {} ({}ms)",
-                self.to, self.timeout_ms
-            ))
+            ActionError::ConnectError(format!("Timeout connecting to {} ({}ms)", to, timeout_ms))
         })?
         .map_err(|error| {
             ActionError::ConnectError(format!(
                 "Error while trying to connect to {} ({})",
+                to, error
+            ))
+        })
+}
+
+/// Reads what `peer` sends on `stream` in a background task, from a duplicate of the
+/// socket, and returns the stream so that it can still be written by the send actions.
+///
+/// The duplicate keeps the connection open until the task ends, so it is aborted with the
+/// returned handle when the stream is closed by the node.
+fn spawn_response_reader(
+    stream: TcpStream,
+    peer: SocketAddr,
+    ctx: Ctx,
+) -> Result<(TcpStream, AbortHandle), ActionError> {
+    let duplicate = |stream: TcpStream| -> std::io::Result<(TcpStream, TcpStream)> {
+        let stream = stream.into_std()?;
+        let reader = TcpStream::from_std(stream.try_clone()?)?;
+        Ok((TcpStream::from_std(stream)?, reader))
+    };
+    let (stream, reader) = duplicate(stream).map_err(|error| {
+        ActionError::ConnectError(format!(
+            "Error duplicating the connection to {} ({})",
+            peer, error
+        ))
+    })?;
+
+    let handle = tokio::spawn(read_responses(reader, peer, ctx)).abort_handle();
+    Ok((stream, handle))
+}
+
+/// Records the data read from `peer` as receive events until the connection is closed
+/// or the node is cancelled.
+async fn read_responses(mut reader: TcpStream, peer: SocketAddr, ctx: Ctx) {
+    let Ok(local_addr) = reader.local_addr() else {
+        return;
+    };
+
+    let mut cancelled = ctx.lock().await.cancelled.subscribe();
+    let cancelled = async move {
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+    };
+    tokio::pin!(cancelled);
+
+    let mut buf = vec![0; RESPONSE_BUFFER_SIZE];
+    loop {
+        let read = tokio::select! {
+            _ = &mut cancelled => {
+                event!(tracing::Level::INFO, "Node cancelled, no longer reading from {}", peer);
+                break;
+            }
+            read = reader.read(&mut buf) => read,
+        };
+
+        match read {
+            Ok(0) => {
+                event!(
+                    tracing::Level::INFO,
+                    "Connection to {} closed by peer",
+                    peer
+                );
+                break;
+            }
+            Ok(n) => {
+                event!(tracing::Level::INFO, "Read {} TCP bytes from {}", n, peer);
+
+                let mut context = ctx.lock().await;
+                let received_message = ReceiveEvent {
+                    instant: context.clock.now(),
+                    seq: context.next_seq(),
+                    from: peer,
+                    to: local_addr,
+                    buffer: buf[..n].to_vec(),
+                    truncated: false,
+                    phase: context.phase.clone(),
+                };
+                context.push_receive_event(received_message);
+                context.receive_notifier.notify_waiters();
+            }
+            Err(e) => {
+                event!(tracing::Level::ERROR, "Error reading from {}: {}", peer, e);
+                break;
+            }
+        }
+    }
+}
+
+/// Returns true if the peer has neither closed nor reset the stream,
+/// without consuming any pending data.
+fn is_open(stream: &TcpStream) -> bool {
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut byte = [0; 1];
+    let mut buf = ReadBuf::new(&mut byte);
+
+    match stream.poll_peek(&mut cx, &mut buf) {
+        Poll::Ready(Ok(0)) | Poll::Ready(Err(_)) => false,
+        Poll::Ready(Ok(_)) | Poll::Pending => true,
+    }
+}
+
+/// Connects from `from` to `to` and sends `buffer` on the new connection within a single action,
+/// so that no other action can use the connection in between.
+///
+/// Only the `Unicast` (TCP), `UdpConnected` and `TcpOneShot` modes open a connection, the
+/// `TcpOneShot` one being closed once the data is sent.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ConnectSend {
+    from: SocketAddr,
+    to: SocketAddr,
+    buffer: Vec<u8>,
+    timeout_ms: u64,
+    mode: SendMode,
+}
+
+impl ConnectSend {
+    pub fn new(
+        mode: SendMode,
+        from: SocketAddr,
+        to: SocketAddr,
+        buffer: Vec<u8>,
+        timeout_ms: u64,
+    ) -> Self {
+        ConnectSend {
+            from,
+            to,
+            buffer,
+            timeout_ms,
+            mode,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms
+    }
+
+    pub fn mode(&self) -> &SendMode {
+        &self.mode
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for ConnectSend {
+    fn name(&self) -> String {
+        "CONNECT_SEND".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        match self.mode {
+            SendMode::Unicast => {}
+            SendMode::UdpConnected | SendMode::TcpOneShot { .. } => {
+                // The socket is connected by the send itself
+                return Send::new(self.mode.clone(), self.from, self.to, self.buffer.clone())
+                    .perform(ctx)
+                    .await;
+            }
+            _ => {
+                return Err(ActionError::SendError(format!(
+                    "{:?} mode does not open a connection",
+                    self.mode
+                )));
+            }
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Connecting from {} to {} and sending {} bytes",
+            self.from,
+            self.to,
+            self.buffer.len()
+        );
+        let mut stream = dial(self.from, self.to, self.timeout_ms).await?;
+        let local_addr = stream.local_addr().unwrap_or(self.from);
+
+        // Keep the context locked until the data is sent
+        let mut context = ctx.lock().await;
+        let phase = context.phase.clone();
+        let instant = context.clock.now();
+        let seq = context.next_seq();
+        context.connect_events.push(ConnectEvent {
+            instant,
+            seq,
+            from: local_addr,
+            to: self.to,
+            phase: phase.clone(),
+        });
+        context.record_connection();
+        context.connect_notifier.notify_waiters();
+
+        let sent = stream.write_all(&self.buffer).await;
+        context.tcp_streams.insert(self.to, stream);
+        context.stream_notifier.notify_waiters();
+        sent.map_err(|error| {
+            ActionError::SendError(format!(
+                "Connected to {} but failed to send ({})",
                 self.to, error
             ))
         })?;
 
-        event!(tracing::Level::INFO, "Connected to {}", self.to);
-        ctx.lock().await.tcp_streams.insert(self.to, stream);
-
-        let connect_event = ConnectEvent {
-            instant: tokio::time::Instant::now(),
+        let instant = context.clock.now();
+        let seq = context.next_seq();
+        context.push_send_event(SendEvent {
+            instant,
+            seq,
             from: self.from,
             to: self.to,
-        };
-
-        // Store the connect event in the context and signal every task waiting for it
-        ctx.lock().await.connect_events.push(connect_event);
-        ctx.lock().await.connect_notifier.notify_waiters();
+            buffer: self.buffer.clone(),
+            len: self.buffer.len(),
+            phase,
+        });
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{
+        Node, StaticResolver,
+        action::{Action, ActionError},
+        protocol::Protocol,
+        protocol::ip::{
+            Bind, BufferMatch, Disconnect, MessagesPredicate, ReceivePredicate, Send, SendMode,
+            Wait, WaitEvent,
+        },
+    };
+
+    use super::{Connect, ConnectSend};
+
+    #[tokio::test]
+    async fn test_connect_reads_responses() {
+        let server: SocketAddr = "127.0.1.107:3000".parse().unwrap();
+        let listener = tokio::net::TcpListener::bind(server).await.unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 64];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket.write_all(&buf[..n]).await.unwrap();
+        });
+
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Connect::new("127.0.1.108:0".parse().unwrap(), server, 1000)
+            .with_read_responses(true)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Send::new(
+            SendMode::Unicast,
+            "127.0.1.108:0".parse().unwrap(),
+            server,
+            b"echo".to_vec(),
+        )
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+
+        Wait::new(WaitEvent::Messages(
+            ReceivePredicate::new(vec![MessagesPredicate {
+                from: server,
+                to: "127.0.1.108:0".parse().unwrap(),
+                buffer: b"echo".to_vec(),
+                matching: BufferMatch::Exact,
+            }])
+            .with_timeout(1000),
+        ))
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+        assert_eq!(ctx.lock().await.send_events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_response_reader_stops_with_the_stream() {
+        for (server, client, cancel) in [
+            ("127.0.1.132:3000", "127.0.1.133:0", false),
+            ("127.0.1.134:3000", "127.0.1.135:0", true),
+        ] {
+            let (server, client): (SocketAddr, SocketAddr) =
+                (server.parse().unwrap(), client.parse().unwrap());
+            let listener = tokio::net::TcpListener::bind(server).await.unwrap();
+
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+            Connect::new(client, server, 1000)
+                .with_read_responses(true)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            if cancel {
+                node.cancel().await;
+            } else {
+                Disconnect::new(server).perform(ctx.clone()).await.unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+
+            // Nothing reads the connection anymore, what the peer sends is not recorded
+            let _ = socket.write_all(b"late").await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let context = ctx.lock().await;
+            assert!(context.receive_events.is_empty(), "cancel: {}", cancel);
+            assert!(cancel || context.response_readers.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_reuses_open_stream() {
+        for (server, force_new, expected) in
+            [("127.0.1.40:3000", false, 1), ("127.0.1.41:3000", true, 2)]
+        {
+            let server: SocketAddr = server.parse().unwrap();
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+
+            let connect = Connect::new("127.0.1.42:0".parse().unwrap(), server, 1000)
+                .with_force_new(force_new);
+            connect.perform(ctx.clone()).await.unwrap();
+            connect.perform(ctx.clone()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let context = ctx.lock().await;
+            // Each connection is recorded by both of its ends
+            let recorded = context
+                .connect_events
+                .iter()
+                .filter(|e| e.to == server)
+                .count();
+            assert_eq!(recorded, 2 * expected);
+            assert_eq!(context.tcp_streams.len(), 1);
+            // The accepted side of a closed connection is released
+            assert_eq!(context.server_streams.len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_send() {
+        let server: SocketAddr = "127.0.1.62:3000".parse().unwrap();
+        let server_node = Node::new("server");
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+
+        let client = Node::new("client");
+        ConnectSend::new(
+            SendMode::Unicast,
+            "127.0.1.63:0".parse().unwrap(),
+            server,
+            b"hello".to_vec(),
+            1000,
+        )
+        .perform(client.ctx())
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let received = server_node.ctx().lock().await.receive_events.clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].buffer, b"hello".to_vec());
+
+        let context = client.ctx();
+        let context = context.lock().await;
+        assert_eq!(context.connect_events.len(), 1);
+        assert_eq!(context.send_events.len(), 1);
+        assert!(context.tcp_streams.contains_key(&server));
+        assert!(context.connect_events[0].instant <= context.send_events[0].instant);
+    }
+
+    #[tokio::test]
+    async fn test_connect_send_tcp_one_shot() {
+        let server: SocketAddr = "127.0.1.142:3000".parse().unwrap();
+        let server_node = Node::new("server");
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+
+        let client = Node::new("client");
+        ConnectSend::new(
+            SendMode::TcpOneShot {
+                read_response: false,
+            },
+            "127.0.1.143:0".parse().unwrap(),
+            server,
+            b"hello".to_vec(),
+            1000,
+        )
+        .perform(client.ctx())
+        .await
+        .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let received = server_node.ctx().lock().await.receive_events.clone();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].buffer, b"hello".to_vec());
+
+        let context = client.ctx();
+        let context = context.lock().await;
+        assert_eq!(context.connect_events.len(), 1);
+        assert_eq!(context.send_events.len(), 1);
+        assert!(!context.tcp_streams.contains_key(&server));
+    }
+
+    #[test]
+    fn test_connect_serde_round_trip() {
+        let connect = Connect::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "127.0.0.1:3000".parse().unwrap(),
+            500,
+        )
+        .with_force_new(true);
+
+        let boxed: Box<dyn Action> = Box::new(connect.clone());
+        let json = serde_json::to_string(&boxed).unwrap();
+        let action: Box<dyn Action> = serde_json::from_str(&json).unwrap();
+        assert_eq!(action.name(), "CONNECT");
+        assert_eq!(serde_json::to_string(&action).unwrap(), json);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let decoded: Connect = serde_json::from_value(value["Connect"].clone()).unwrap();
+        assert_eq!(decoded, connect);
+    }
+
+    #[tokio::test]
+    async fn test_connect_ipv6() {
+        let server_node = Node::new("server");
+        Bind::new("[::1]:0".parse().unwrap())
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+        let server = server_node.ctx().lock().await.bound[0];
+        assert!(server.is_ipv6());
+
+        let node = Node::new("client");
+        let ctx = node.ctx();
+        Connect::new("[::1]:0".parse().unwrap(), server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let context = ctx.lock().await;
+        assert_eq!(context.connect_events.len(), 1);
+        assert!(context.connect_events[0].from.is_ipv6());
+        assert_eq!(context.connect_events[0].to, server);
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_host_with_static_resolver() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        ctx.lock().await.resolver =
+            Arc::new(StaticResolver::new().with_host("server.test", "127.0.1.97".parse().unwrap()));
+
+        Bind::new("0.0.0.0:0".parse().unwrap())
+            .with_host("server.test:3000")
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Connect::new(
+            "127.0.1.98:0".parse().unwrap(),
+            "0.0.0.0:0".parse().unwrap(),
+            1000,
+        )
+        .with_to_host("server.test:3000")
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+
+        let server: SocketAddr = "127.0.1.97:3000".parse().unwrap();
+        let context = ctx.lock().await;
+        assert_eq!(context.bound, vec![server]);
+        assert!(context.tcp_streams.contains_key(&server));
+        drop(context);
+
+        let unknown = Connect::new("127.0.1.98:0".parse().unwrap(), server, 1000)
+            .with_to_host("other.test:3000")
+            .perform(ctx)
+            .await;
+        assert!(matches!(unknown, Err(ActionError::ConnectError(e)) if e.contains("other.test")));
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_bracketed_ipv6_host() {
+        let server_node = Node::new("server");
+        Bind::new("[::1]:0".parse().unwrap())
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+        let server = server_node.ctx().lock().await.bound[0];
+
+        let node = Node::new("client");
+        let ctx = node.ctx();
+        Connect::new("[::1]:0".parse().unwrap(), "[::]:0".parse().unwrap(), 1000)
+            .with_to_host(&format!("[::1]:{}", server.port()))
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        assert!(ctx.lock().await.tcp_streams.contains_key(&server));
+    }
+}
@@ -0,0 +1,110 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::Ctx,
+};
+
+/// Closes the TCP stream previously connected to `target`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Disconnect {
+    target: SocketAddr,
+}
+
+impl Disconnect {
+    pub fn new(target: SocketAddr) -> Self {
+        Disconnect { target }
+    }
+
+    pub fn target(&self) -> &SocketAddr {
+        &self.target
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for Disconnect {
+    fn name(&self) -> String {
+        "DISCONNECT".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        // Release the context before closing, the shutdown may wait to flush the stream
+        let stream = ctx.lock().await.close_tcp_stream(&self.target);
+        let Some(mut stream) = stream else {
+            event!(
+                tracing::Level::ERROR,
+                "No stream connected to {}",
+                self.target
+            );
+            return Err(ActionError::DisconnectError);
+        };
+
+        event!(tracing::Level::INFO, "Disconnecting from {}", self.target);
+        stream.shutdown().await.map_err(|e| {
+            event!(
+                tracing::Level::ERROR,
+                "Error closing the stream to {} ({})",
+                self.target,
+                e
+            );
+            ActionError::DisconnectError
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        DisconnectReason, Node,
+        action::{Action, ActionError},
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect},
+        },
+    };
+
+    use super::Disconnect;
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let server: SocketAddr = "127.0.1.71:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Connect::new("127.0.1.72:0".parse().unwrap(), server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        Disconnect::new(server).perform(ctx.clone()).await.unwrap();
+        assert!(!ctx.lock().await.tcp_streams.contains_key(&server));
+
+        // The server side sees the connection closed
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let reasons: Vec<DisconnectReason> = ctx
+            .lock()
+            .await
+            .disconnect_events
+            .iter()
+            .map(|e| e.reason)
+            .collect();
+        assert_eq!(reasons, vec![DisconnectReason::Fin]);
+
+        assert_eq!(
+            Disconnect::new(server).perform(ctx).await,
+            Err(ActionError::DisconnectError)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::Ctx,
+};
+
+/// Interval between two checks of the socket send queue.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Waits until every byte written to the stream connected to `peer` has left the socket.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct WaitDrained {
+    peer: SocketAddr,
+    timeout_ms: u64,
+}
+
+impl WaitDrained {
+    pub fn new(peer: SocketAddr, timeout_ms: u64) -> Self {
+        WaitDrained { peer, timeout_ms }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for WaitDrained {
+    fn name(&self) -> String {
+        "WAIT_DRAINED".into()
+    }
+
+    /// Flushes the stream and polls its send queue until it is empty.
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let drain = async {
+            loop {
+                let unsent = {
+                    let mut context = ctx.lock().await;
+                    let stream = context.tcp_streams.get_mut(&self.peer).ok_or_else(|| {
+                        event!(
+                            tracing::Level::ERROR,
+                            "No stream connected to {}",
+                            self.peer
+                        );
+                        ActionError::WaitError
+                    })?;
+
+                    stream.flush().await.map_err(|e| {
+                        event!(
+                            tracing::Level::ERROR,
+                            "Error flushing stream {}: {}",
+                            self.peer,
+                            e
+                        );
+                        ActionError::WaitError
+                    })?;
+
+                    unsent_bytes(stream).await.map_err(|e| {
+                        event!(
+                            tracing::Level::ERROR,
+                            "Error reading send queue of {}: {}",
+                            self.peer,
+                            e
+                        );
+                        ActionError::WaitError
+                    })?
+                };
+
+                if unsent == 0 {
+                    event!(tracing::Level::DEBUG, "Stream to {} drained", self.peer);
+                    return Ok(());
+                }
+
+                event!(
+                    tracing::Level::DEBUG,
+                    "{} bytes still queued to {}",
+                    unsent,
+                    self.peer
+                );
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+        };
+
+        tokio::time::timeout(Duration::from_millis(self.timeout_ms), drain)
+            .await
+            .map_err(|_| {
+                event!(
+                    tracing::Level::WARN,
+                    "Timeout waiting for stream to {} to drain ({}ms)",
+                    self.peer,
+                    self.timeout_ms
+                );
+                ActionError::WaitError
+            })?
+    }
+}
+
+/// Returns the number of bytes not yet sent by the socket (SIOCOUTQ).
+#[cfg(target_os = "linux")]
+async fn unsent_bytes(stream: &TcpStream) -> std::io::Result<usize> {
+    use std::os::fd::AsRawFd;
+
+    let mut unsent: libc::c_int = 0;
+    // SAFETY: the descriptor is owned by `stream` and `unsent` outlives the call
+    let res = unsafe { libc::ioctl(stream.as_raw_fd(), libc::TIOCOUTQ, &mut unsent) };
+    if res < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(unsent as usize)
+}
+
+/// The send queue can't be inspected on this platform, give the kernel a moment instead.
+#[cfg(not(target_os = "linux"))]
+async fn unsent_bytes(_stream: &TcpStream) -> std::io::Result<usize> {
+    tokio::time::sleep(DRAIN_POLL_INTERVAL * 10).await;
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::ip::{Bind, Connect, Send, SendMode},
+    };
+
+    use super::WaitDrained;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_wait_drained() {
+        let server: SocketAddr = "127.0.1.10:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server).perform(ctx.clone()).await.unwrap();
+        Connect::new("127.0.1.11:0".parse().unwrap(), server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Send::new(
+            SendMode::Unicast,
+            "127.0.1.11:0".parse().unwrap(),
+            server,
+            vec![0xAB; 64 * 1024],
+        )
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+
+        assert!(WaitDrained::new(server, 2000).perform(ctx).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_wait_drained_without_stream() {
+        let node = Node::new("test-node");
+        let peer: SocketAddr = "127.0.1.11:3000".parse().unwrap();
+
+        assert_eq!(
+            WaitDrained::new(peer, 100).perform(node.ctx()).await,
+            Err(ActionError::WaitError)
+        );
+    }
+}
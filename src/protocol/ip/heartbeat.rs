@@ -0,0 +1,164 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::MissedTickBehavior;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::Ctx,
+    protocol::ip::{Send, SendMode},
+};
+
+/// Sends `payload` from `from` to `to` every `interval_ms` in the background,
+/// until the node is cancelled or finishes its actions.
+///
+/// The action returns as soon as the background task is spawned. Each payload is
+/// sent as a [`Send`] action would, UDP datagrams by default.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    from: SocketAddr,
+    to: SocketAddr,
+    payload: Vec<u8>,
+    interval_ms: u64,
+    #[serde(default = "default_mode")]
+    mode: SendMode,
+}
+
+fn default_mode() -> SendMode {
+    SendMode::UdpUnicast
+}
+
+impl Heartbeat {
+    pub fn new(from: SocketAddr, to: SocketAddr, payload: Vec<u8>, interval_ms: u64) -> Self {
+        Heartbeat {
+            from,
+            to,
+            payload,
+            interval_ms,
+            mode: default_mode(),
+        }
+    }
+
+    /// Sends the heartbeats in the given mode, such as on a connected TCP stream.
+    pub fn with_mode(mut self, mode: SendMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    pub fn interval_ms(&self) -> u64 {
+        self.interval_ms
+    }
+
+    pub fn mode(&self) -> &SendMode {
+        &self.mode
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for Heartbeat {
+    fn name(&self) -> String {
+        "HEARTBEAT".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if self.interval_ms == 0 {
+            return Err(ActionError::SendError(
+                "Heartbeat interval must not be zero".into(),
+            ));
+        }
+
+        let mut cancelled = ctx.lock().await.cancelled.subscribe();
+        let send = Send::new(self.mode.clone(), self.from, self.to, self.payload.clone());
+        let mut interval = tokio::time::interval(Duration::from_millis(self.interval_ms));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        event!(
+            tracing::Level::INFO,
+            "Sending heartbeats from {} to {} every {}ms",
+            self.from,
+            self.to,
+            self.interval_ms
+        );
+        tokio::spawn(async move {
+            let cancelled = async move {
+                let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+            };
+            tokio::pin!(cancelled);
+
+            loop {
+                tokio::select! {
+                    _ = &mut cancelled => break,
+                    _ = interval.tick() => {
+                        if let Err(e) = send.perform(ctx.clone()).await {
+                            event!(tracing::Level::WARN, "Heartbeat to {} failed: {:?}", send.to(), e);
+                        }
+                    }
+                }
+            }
+
+            event!(tracing::Level::INFO, "Heartbeats to {} stopped", send.to());
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        Node,
+        action::Action,
+        protocol::{Protocol, ip::Bind},
+    };
+
+    use super::Heartbeat;
+
+    #[tokio::test]
+    async fn test_heartbeat_until_cancelled() {
+        let server: SocketAddr = "127.0.1.66:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Udp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Heartbeat::new(
+            "127.0.1.67:0".parse().unwrap(),
+            server,
+            b"beat".to_vec(),
+            50,
+        )
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+
+        // Ticks at 0, 50, 100 and 150ms
+        tokio::time::sleep(Duration::from_millis(175)).await;
+        node.cancel().await;
+        let sent = ctx.lock().await.send_events.len();
+        assert!((3..=5).contains(&sent), "{} heartbeats sent", sent);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        let context = ctx.lock().await;
+        assert_eq!(context.send_events.len(), sent);
+        assert!(context.receive_events.iter().all(|e| e.buffer == b"beat"));
+    }
+}
@@ -1,14 +1,74 @@
+mod assert;
 mod bind;
+#[cfg(all(feature = "raw", target_os = "linux"))]
+mod capture;
+mod checkpoint;
+mod coalesce;
 mod connect;
+mod disconnect;
+mod drain;
+mod heartbeat;
+mod pause;
+mod pool;
+mod script;
 mod send;
+mod sync;
+#[cfg(target_os = "linux")]
+mod tcp_info;
 mod wait;
 
+pub use assert::AssertAlternating;
+pub use assert::AssertBacklogBelow;
+pub use assert::AssertBound;
+pub use assert::AssertChunkCadence;
+pub use assert::AssertCloseReason;
+pub use assert::AssertConnectAfterBindWithin;
+pub use assert::AssertConnectOrder;
+pub use assert::AssertConnectRate;
+pub use assert::AssertEntropyAbove;
+pub use assert::AssertFrameSequence;
+pub use assert::AssertJitter;
+pub use assert::AssertNeverConnected;
+pub use assert::AssertNotContains;
+pub use assert::AssertReceiveRate;
+pub use assert::AssertSequence;
+pub use assert::AssertTotalConnections;
+pub use assert::AssertTranscript;
 pub use bind::Bind;
+pub use bind::Framing;
+pub use bind::ReceiveFilter;
+#[cfg(all(feature = "raw", target_os = "linux"))]
+pub use capture::Capture;
+pub use checkpoint::AssertDelta;
+pub use checkpoint::CounterCheckpoint;
+pub use coalesce::CoalesceReceives;
 pub use connect::Connect;
+pub use connect::ConnectSend;
+pub use disconnect::Disconnect;
+pub use drain::WaitDrained;
+pub use heartbeat::Heartbeat;
+pub use pause::PauseReads;
+pub use pause::ResumeReads;
+pub use pool::ConnectPool;
+pub use script::ScriptStep;
+pub use script::SendScript;
 pub use send::Send;
 pub use send::SendMode;
+pub use send::SendPattern;
+pub use sync::Sync;
+#[cfg(target_os = "linux")]
+pub use tcp_info::AssertRetransmitsBelow;
+#[cfg(target_os = "linux")]
+pub use tcp_info::TcpInfo;
+pub use wait::BufferMatch;
+pub use wait::BytePattern;
 pub use wait::ConnectPredicate;
+pub use wait::CountPredicate;
 pub use wait::MessagesPredicate;
+pub use wait::OrderType;
 pub use wait::ReceivePredicate;
+pub use wait::SendPredicate;
+pub use wait::StreamContainsPredicate;
 pub use wait::Wait;
 pub use wait::WaitEvent;
+pub(crate) use wait::addr_match;
@@ -0,0 +1,183 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{Ctx, NodeContext},
+};
+
+/// Stops reading the connections accepted from `peer`, as a slow consumer would,
+/// so that the data sent by the peer piles up in the socket buffers.
+///
+/// Reads resume after `duration_ms`, or on a [`ResumeReads`] without a duration.
+/// A port of 0 in `peer` pauses every connection from its address.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PauseReads {
+    peer: SocketAddr,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+}
+
+impl PauseReads {
+    pub fn new(peer: SocketAddr, duration_ms: Option<u64>) -> Self {
+        PauseReads { peer, duration_ms }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.duration_ms
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for PauseReads {
+    fn name(&self) -> String {
+        "PAUSE_READS".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let paused_at = {
+            let mut context = ctx.lock().await;
+            let paused_at = context.clock.now();
+            context.paused_reads.insert(self.peer, paused_at);
+            paused_at
+        };
+        event!(tracing::Level::INFO, "Pausing reads from {}", self.peer);
+
+        if let Some(duration_ms) = self.duration_ms {
+            let peer = self.peer;
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+
+                // Leave a later pause of the same peer alone
+                let mut context = ctx.lock().await;
+                if context.paused_reads.get(&peer) == Some(&paused_at) {
+                    resume(&mut context, &peer);
+                }
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Resumes reading the connections from `peer` paused by [`PauseReads`].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ResumeReads {
+    peer: SocketAddr,
+}
+
+impl ResumeReads {
+    pub fn new(peer: SocketAddr) -> Self {
+        ResumeReads { peer }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for ResumeReads {
+    fn name(&self) -> String {
+        "RESUME_READS".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        resume(&mut *ctx.lock().await, &self.peer);
+        Ok(())
+    }
+}
+
+fn resume(context: &mut NodeContext, peer: &SocketAddr) {
+    if context.paused_reads.remove(peer).is_some() {
+        event!(tracing::Level::INFO, "Resuming reads from {}", peer);
+        context.reads_notifier.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect, Send, SendMode},
+        },
+    };
+
+    use super::{PauseReads, ResumeReads};
+
+    #[tokio::test]
+    async fn test_pause_reads() {
+        let server: SocketAddr = "127.0.1.80:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.79:0".parse().unwrap();
+        let server_node = Node::new("server");
+        let client_node = Node::new("client");
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+        PauseReads::new(client, None)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+        Connect::new(client, server, 1000)
+            .perform(client_node.ctx())
+            .await
+            .unwrap();
+
+        // Send until the buffers of both sides are full
+        let send = Send::new(SendMode::Unicast, client, server, vec![0xAB; 1024 * 1024])
+            .with_nonblocking(true);
+        let mut sent = 0;
+        loop {
+            match send.perform(client_node.ctx()).await {
+                Ok(()) => sent += send.buffer().len(),
+                Err(ActionError::WouldBlock(written)) => {
+                    sent += written;
+                    break;
+                }
+                Err(e) => panic!("Unexpected error {:?}", e),
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(server_node.ctx().lock().await.receive_events.is_empty());
+
+        ResumeReads::new(client)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+        let received = async {
+            loop {
+                let received: usize = server_node
+                    .ctx()
+                    .lock()
+                    .await
+                    .receive_events
+                    .iter()
+                    .map(|e| e.buffer.len())
+                    .sum();
+                if received == sent {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), received)
+            .await
+            .expect("Data not drained after resuming reads");
+    }
+}
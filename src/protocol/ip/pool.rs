@@ -0,0 +1,255 @@
+use std::net::{IpAddr, SocketAddr};
+
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    Rng,
+    action::{Action, ActionError},
+    node::{ConnectEvent, Ctx},
+    protocol::ip::connect::dial,
+};
+
+/// Opens `size` TCP connections to `to`, the pool streams are kept by local address.
+///
+/// Connection `i` is bound to port `from.port() + i`, or to an ephemeral port
+/// if the port of `from` is 0. The connections are opened in that order unless
+/// a shuffle seed is given. With source ips, connection `i` is instead bound
+/// to an ephemeral port of the ip `i` modulo their number.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ConnectPool {
+    from: SocketAddr,
+    to: SocketAddr,
+    size: usize,
+    timeout_ms: u64,
+    #[serde(default)]
+    shuffle_seed: Option<u64>,
+    #[serde(default)]
+    source_ips: Vec<IpAddr>,
+}
+
+impl ConnectPool {
+    pub fn new(from: SocketAddr, to: SocketAddr, size: usize, timeout_ms: u64) -> Self {
+        ConnectPool {
+            from,
+            to,
+            size,
+            timeout_ms,
+            shuffle_seed: None,
+            source_ips: Vec::new(),
+        }
+    }
+
+    /// Opens the connections in an order shuffled with the given seed.
+    pub fn with_shuffle_seed(mut self, seed: u64) -> Self {
+        self.shuffle_seed = Some(seed);
+        self
+    }
+
+    /// Rotates the connections across the given source ips, for instance
+    /// aliases of the host, instead of the ports of `from`.
+    pub fn with_source_ips(mut self, source_ips: Vec<IpAddr>) -> Self {
+        self.source_ips = source_ips;
+        self
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms
+    }
+
+    pub fn shuffle_seed(&self) -> Option<u64> {
+        self.shuffle_seed
+    }
+
+    pub fn source_ips(&self) -> &[IpAddr] {
+        &self.source_ips
+    }
+
+    /// Returns the source addresses of the connections, in connect order.
+    fn sources(&self) -> Result<Vec<SocketAddr>, ActionError> {
+        let mut sources = (0..self.size)
+            .map(|idx| {
+                if !self.source_ips.is_empty() {
+                    let ip = self.source_ips[idx % self.source_ips.len()];
+                    return Ok(SocketAddr::new(ip, 0));
+                }
+
+                let port = match self.from.port() {
+                    0 => Some(0),
+                    port => u16::try_from(idx)
+                        .ok()
+                        .and_then(|idx| port.checked_add(idx)),
+                };
+                port.map(|port| SocketAddr::new(self.from.ip(), port))
+                    .ok_or_else(|| {
+                        ActionError::ConnectError(format!(
+                            "Not enough ports after {} for {} connections",
+                            self.from, self.size
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<SocketAddr>, ActionError>>()?;
+
+        if let Some(seed) = self.shuffle_seed {
+            Rng::new(seed).shuffle(&mut sources);
+        }
+
+        Ok(sources)
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for ConnectPool {
+    fn name(&self) -> String {
+        "CONNECT_POOL".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        event!(
+            tracing::Level::INFO,
+            "Opening {} connections from {} to {}",
+            self.size,
+            self.from,
+            self.to
+        );
+
+        for source in self.sources()? {
+            let stream = dial(source, self.to, self.timeout_ms).await?;
+            let local_addr = stream.local_addr().unwrap_or(source);
+
+            let mut context = ctx.lock().await;
+            context.pool_streams.insert(local_addr, stream);
+
+            // Record the actual source, so that the connect order can be checked
+            let phase = context.phase.clone();
+            let instant = context.clock.now();
+            let seq = context.next_seq();
+            context.connect_events.push(ConnectEvent {
+                instant,
+                seq,
+                from: local_addr,
+                to: self.to,
+                phase,
+            });
+            context.record_connection();
+            context.connect_notifier.notify_waiters();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, SocketAddr};
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::{Protocol, ip::Bind},
+    };
+
+    use super::ConnectPool;
+
+    #[tokio::test]
+    async fn test_connect_pool_shuffle_is_deterministic() {
+        let mut orders = Vec::new();
+        for (server, client) in [
+            ("127.0.1.56:3000", "127.0.1.57:40000"),
+            ("127.0.1.58:3000", "127.0.1.59:40000"),
+        ] {
+            let server: SocketAddr = server.parse().unwrap();
+            let client: SocketAddr = client.parse().unwrap();
+            let server_node = Node::new("server");
+            Bind::new(server)
+                .with_protocol(Protocol::Tcp)
+                .perform(server_node.ctx())
+                .await
+                .unwrap();
+
+            let node = Node::new("client");
+            let ctx = node.ctx();
+            ConnectPool::new(client, server, 8, 1000)
+                .with_shuffle_seed(7)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+
+            let context = ctx.lock().await;
+            assert_eq!(context.pool_streams.len(), 8);
+            let order: Vec<u16> = context
+                .connect_events
+                .iter()
+                .map(|e| e.from.port() - client.port())
+                .collect();
+            orders.push(order);
+        }
+
+        assert_eq!(orders[0], orders[1]);
+        let mut sorted = orders[0].clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..8).collect::<Vec<u16>>());
+        assert_ne!(orders[0], sorted);
+    }
+
+    #[tokio::test]
+    async fn test_connect_pool_rotates_source_ips() {
+        let server: SocketAddr = "127.0.1.84:3000".parse().unwrap();
+        let server_node = Node::new("server");
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+
+        let source_ips: Vec<IpAddr> = ["127.0.1.85", "127.0.1.86", "127.0.1.87"]
+            .iter()
+            .map(|ip| ip.parse().unwrap())
+            .collect();
+        let node = Node::new("client");
+        let ctx = node.ctx();
+        ConnectPool::new("127.0.1.85:0".parse().unwrap(), server, 5, 1000)
+            .with_source_ips(source_ips.clone())
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let ips: Vec<IpAddr> = ctx
+            .lock()
+            .await
+            .connect_events
+            .iter()
+            .map(|e| e.from.ip())
+            .collect();
+        assert_eq!(ips, [0, 1, 2, 0, 1].map(|idx| source_ips[idx]).to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_connect_pool_unbindable_source_ip() {
+        let node = Node::new("client");
+        let result = ConnectPool::new(
+            "127.0.1.88:0".parse().unwrap(),
+            "127.0.1.88:3000".parse().unwrap(),
+            1,
+            1000,
+        )
+        .with_source_ips(vec!["192.0.2.1".parse().unwrap()])
+        .perform(node.ctx())
+        .await;
+
+        assert!(matches!(result, Err(ActionError::ConnectError(e)) if e.contains("192.0.2.1")));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{net::SocketAddr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::Ctx,
+    protocol::ip::{Send, SendMode},
+};
+
+fn default_timescale() -> f64 {
+    1.0
+}
+
+/// A message of a [`SendScript`], sent `delay_ms` after the previous one.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ScriptStep {
+    pub delay_ms: u64,
+    pub buffer: Vec<u8>,
+}
+
+impl ScriptStep {
+    pub fn new(delay_ms: u64, buffer: Vec<u8>) -> Self {
+        ScriptStep { delay_ms, buffer }
+    }
+}
+
+/// Replays a recorded sequence of messages from `from` to `to`, keeping the delays between them.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SendScript {
+    mode: SendMode,
+    from: SocketAddr,
+    to: SocketAddr,
+    steps: Vec<ScriptStep>,
+    #[serde(default = "default_timescale")]
+    timescale: f64,
+}
+
+impl SendScript {
+    pub fn new(mode: SendMode, from: SocketAddr, to: SocketAddr, steps: Vec<ScriptStep>) -> Self {
+        SendScript {
+            mode,
+            from,
+            to,
+            steps,
+            timescale: default_timescale(),
+        }
+    }
+
+    /// Multiplies every delay of the script, 0.5 replays twice as fast and 2.0 at half speed.
+    pub fn with_timescale(mut self, timescale: f64) -> Self {
+        self.timescale = timescale;
+        self
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn steps(&self) -> &[ScriptStep] {
+        &self.steps
+    }
+
+    pub fn timescale(&self) -> f64 {
+        self.timescale
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for SendScript {
+    fn name(&self) -> String {
+        "SEND_SCRIPT".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if !self.timescale.is_finite() || self.timescale <= 0.0 {
+            return Err(ActionError::SendError(format!(
+                "Invalid timescale {}, it must be strictly positive",
+                self.timescale
+            )));
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Replaying {} messages from {} to {} (timescale {})",
+            self.steps.len(),
+            self.from,
+            self.to,
+            self.timescale
+        );
+
+        for step in &self.steps {
+            let delay = Duration::try_from_secs_f64(step.delay_ms as f64 / 1000.0 * self.timescale)
+                .map_err(|e| {
+                    ActionError::SendError(format!(
+                        "Invalid delay of {} ms at timescale {} ({})",
+                        step.delay_ms, self.timescale, e
+                    ))
+                })?;
+            tokio::time::sleep(delay).await;
+
+            Send::new(self.mode.clone(), self.from, self.to, step.buffer.clone())
+                .perform(ctx.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{net::UdpSocket, time::Instant};
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::ip::SendMode,
+    };
+
+    use super::{ScriptStep, SendScript};
+
+    #[tokio::test]
+    async fn test_send_script_timescale() {
+        let sink = UdpSocket::bind("127.0.1.29:3000").await.unwrap();
+        let script = SendScript::new(
+            SendMode::UdpUnicast,
+            "127.0.1.30:0".parse().unwrap(),
+            sink.local_addr().unwrap(),
+            vec![
+                ScriptStep::new(0, vec![1]),
+                ScriptStep::new(100, vec![2]),
+                ScriptStep::new(100, vec![3]),
+            ],
+        );
+
+        let mut elapsed = Vec::new();
+        for timescale in [1.0, 0.5] {
+            let node = Node::new("test-node");
+            let start = Instant::now();
+            script
+                .clone()
+                .with_timescale(timescale)
+                .perform(node.ctx())
+                .await
+                .unwrap();
+            elapsed.push(start.elapsed());
+            assert_eq!(node.ctx().lock().await.send_events.len(), 3);
+        }
+
+        assert!(elapsed[0] >= Duration::from_millis(200));
+        assert!(elapsed[1] >= Duration::from_millis(100));
+        assert!(elapsed[1] < Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_send_script_invalid_timescale() {
+        let node = Node::new("test-node");
+        let script = SendScript::new(
+            SendMode::UdpUnicast,
+            "127.0.1.30:0".parse().unwrap(),
+            "127.0.1.29:3000".parse().unwrap(),
+            vec![ScriptStep::new(0, vec![1])],
+        );
+
+        for timescale in [0.0, -1.0] {
+            assert!(matches!(
+                script
+                    .clone()
+                    .with_timescale(timescale)
+                    .perform(node.ctx())
+                    .await,
+                Err(ActionError::SendError(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_script_delay_overflow() {
+        let node = Node::new("test-node");
+        let script = SendScript::new(
+            SendMode::UdpUnicast,
+            "127.0.1.30:0".parse().unwrap(),
+            "127.0.1.29:3000".parse().unwrap(),
+            vec![ScriptStep::new(1000, vec![1])],
+        )
+        .with_timescale(1e30);
+
+        assert!(matches!(
+            script.perform(node.ctx()).await,
+            Err(ActionError::SendError(_))
+        ));
+        assert!(node.ctx().lock().await.send_events.is_empty());
+    }
+}
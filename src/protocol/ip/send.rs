@@ -1,18 +1,49 @@
-use std::net::SocketAddr;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 
 use serde::{Deserialize, Serialize};
-use tokio::{io::AsyncWriteExt, net::UdpSocket};
-use tracing::event;
+use tokio::{
+    io::{AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpStream, UdpSocket, tcp::OwnedWriteHalf},
+};
+use tracing::{Instrument, event};
 
 use crate::{
     action::{Action, ActionError},
-    node::{Ctx, SendEvent},
+    node::{ConnectEvent, Ctx, NodeContext, ReceiveEvent, SendEvent},
+    protocol::ip::connect::dial,
+    resolver::resolve_endpoint,
 };
 
+/// Time allowed to open the connection of a one-shot TCP send.
+const ONE_SHOT_CONNECT_TIMEOUT_MS: u64 = 1000;
+/// Time allowed to the peer of a one-shot TCP send to send its response and close the connection.
+const ONE_SHOT_RESPONSE_TIMEOUT_MS: u64 = 1000;
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum SendMode {
+    /// Writes to the TCP stream previously connected to `to`.
     Unicast,
+    /// Sends a UDP datagram with the broadcast flag set.
     Broadcast,
+    /// Sends a single UDP datagram to `to`.
+    UdpUnicast,
+    /// Sends a UDP datagram on a socket connected to `to`, kept open so that
+    /// the replies of `to` are recorded as receive events.
+    UdpConnected,
+    /// Sends a UDP datagram to the IPv4 multicast group `to`, looped back to the local host.
+    Multicast,
+    /// Opens a new TCP connection from `from` to `to`, writes the buffer and closes it,
+    /// as connection-per-request protocols do. With `read_response`, the data sent back
+    /// until the peer closes the connection is recorded as receive events, failing the
+    /// send if the peer does not close it in time.
+    TcpOneShot {
+        #[serde(default)]
+        read_response: bool,
+    },
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -21,6 +52,10 @@ pub struct Send {
     from: SocketAddr,
     to: SocketAddr,
     buffer: Vec<u8>,
+    #[serde(default)]
+    nonblocking: bool,
+    #[serde(default)]
+    to_host: Option<String>,
 }
 
 impl Send {
@@ -31,9 +66,18 @@ impl Send {
             from,
             to,
             buffer,
+            nonblocking: false,
+            to_host: None,
         }
     }
 
+    /// Makes unicast sends fail with `ActionError::WouldBlock` instead of
+    /// waiting when the socket buffer is full. Other modes reject it with `ActionError::SendError`.
+    pub fn with_nonblocking(mut self, nonblocking: bool) -> Self {
+        self.nonblocking = nonblocking;
+        self
+    }
+
     pub fn from(&self) -> &SocketAddr {
         &self.from
     }
@@ -45,6 +89,20 @@ impl Send {
     pub fn buffer(&self) -> &[u8] {
         &self.buffer
     }
+
+    /// Sends to the `host:port` endpoint resolved by the node resolver instead of `to`.
+    pub fn with_to_host(mut self, endpoint: &str) -> Self {
+        self.to_host = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn nonblocking(&self) -> bool {
+        self.nonblocking
+    }
+
+    pub fn to_host(&self) -> Option<&str> {
+        self.to_host.as_deref()
+    }
 }
 
 #[async_trait::async_trait]
@@ -56,6 +114,30 @@ impl Action for Send {
 
     /// Sends data from `from` to `to` using the specified `mode`.
     async fn perform(&self, ctx: Ctx) -> Result<(), crate::action::ActionError> {
+        if let Some(endpoint) = &self.to_host {
+            let to = resolve_endpoint(&ctx, endpoint).await.map_err(|e| {
+                ActionError::SendError(format!("Error resolving {} ({})", endpoint, e))
+            })?;
+            let resolved = Send {
+                to,
+                to_host: None,
+                ..self.clone()
+            };
+            return resolved.perform(ctx).await;
+        }
+
+        if self.nonblocking && self.mode != SendMode::Unicast {
+            event!(
+                tracing::Level::ERROR,
+                "Nonblocking sends are not supported in {:?} mode",
+                self.mode
+            );
+            return Err(ActionError::SendError(format!(
+                "Nonblocking sends are not supported in {:?} mode",
+                self.mode
+            )));
+        }
+
         event!(
             tracing::Level::INFO,
             "Sending {} bytes from {} to {} in {:?} mode",
@@ -67,32 +149,168 @@ impl Action for Send {
 
         match self.mode {
             SendMode::Unicast => {
-                perform_unicast(ctx, &self.to, &self.from, &self.buffer).await?;
+                perform_unicast(ctx, &self.to, &self.from, &self.buffer, self.nonblocking).await?;
             }
             SendMode::Broadcast => {
                 perform_broadcast(ctx, &self.to, &self.from, &self.buffer).await?;
             }
+            SendMode::UdpUnicast => {
+                perform_udp_unicast(ctx, &self.to, &self.from, &self.buffer).await?;
+            }
+            SendMode::UdpConnected => {
+                perform_udp_connected(ctx, &self.to, &self.from, &self.buffer).await?;
+            }
+            SendMode::Multicast => {
+                perform_multicast(ctx, &self.to, &self.from, &self.buffer).await?;
+            }
+            SendMode::TcpOneShot { read_response } => {
+                perform_tcp_one_shot(ctx, &self.to, &self.from, &self.buffer, read_response)
+                    .await?;
+            }
         }
 
         Ok(())
     }
 }
 
+/// Writes `total` bytes of `byte` on the stream connected to `to`, `chunk_size` bytes at a time,
+/// without allocating the whole payload.
+///
+/// A single send event is recorded once everything is written, with the number of bytes sent
+/// but an empty buffer.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SendPattern {
+    from: SocketAddr,
+    to: SocketAddr,
+    byte: u8,
+    total: usize,
+    chunk_size: usize,
+}
+
+impl SendPattern {
+    pub fn new(
+        from: SocketAddr,
+        to: SocketAddr,
+        byte: u8,
+        total: usize,
+        chunk_size: usize,
+    ) -> Self {
+        SendPattern {
+            from,
+            to,
+            byte,
+            total,
+            chunk_size,
+        }
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn to(&self) -> &SocketAddr {
+        &self.to
+    }
+
+    pub fn byte(&self) -> u8 {
+        self.byte
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for SendPattern {
+    fn name(&self) -> String {
+        "SEND_PATTERN".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if self.chunk_size == 0 {
+            return Err(ActionError::SendError("Chunk size must not be zero".into()));
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "Sending {} bytes of {:#04x} from {} to {} in chunks of {}",
+            self.total,
+            self.byte,
+            self.from,
+            self.to,
+            self.chunk_size
+        );
+
+        // Write without holding the context, a receiver sharing it must record what it reads
+        let mut stream = TakenStream::take(&mut *ctx.lock().await, &self.to)?;
+        let chunk = vec![self.byte; self.chunk_size.min(self.total)];
+        let mut sent = 0;
+        let mut written = Ok(());
+        while sent < self.total {
+            let len = chunk.len().min(self.total - sent);
+            if let Err(e) = stream.writer().write_all(&chunk[..len]).await {
+                written = Err(ActionError::SendError(e.to_string()));
+                break;
+            }
+            sent += len;
+        }
+
+        let mut context = ctx.lock().await;
+        stream.restore(&mut context, self.to);
+        written?;
+
+        let phase = context.phase.clone();
+        let instant = context.clock.now();
+        let seq = context.next_seq();
+        context.push_send_event(SendEvent {
+            instant,
+            seq,
+            from: self.from,
+            to: self.to,
+            buffer: Vec::new(),
+            len: sent,
+            phase,
+        });
+
+        Ok(())
+    }
+}
+
 async fn perform_broadcast(
     ctx: Ctx,
     to: &SocketAddr,
     from: &SocketAddr,
     buffer: &[u8],
 ) -> Result<(), ActionError> {
+    // IPv6 has no broadcast, the closest equivalent is a multicast group
+    if to.is_ipv6() && !to.ip().is_multicast() {
+        return Err(ActionError::SendError(format!(
+            "Broadcast is not supported for IPv6 address {}, use a multicast group",
+            to
+        )));
+    }
+
     let socket: UdpSocket = UdpSocket::bind(from)
         .await
         .map_err(|_| crate::action::ActionError::SendError("Failed to bind udp socket".into()))?;
 
-    socket
-        .set_broadcast(true)
-        .map_err(|_| crate::action::ActionError::SendError("Failed to set broadcast".into()))?;
+    if to.is_ipv6() {
+        socket.set_multicast_loop_v6(true).map_err(|_| {
+            crate::action::ActionError::SendError("Failed to set multicast loop".into())
+        })?;
+    } else {
+        socket
+            .set_broadcast(true)
+            .map_err(|_| crate::action::ActionError::SendError("Failed to set broadcast".into()))?;
+    }
 
-    socket.send_to(buffer, to).await.map_err(|e| {
+    let sent = socket.send_to(buffer, to).await.map_err(|e| {
         event!(
             tracing::Level::ERROR,
             "Error sending broadcast data to {}: {}",
@@ -101,48 +319,928 @@ async fn perform_broadcast(
         );
         crate::action::ActionError::SendError(e.to_string())
     })?;
+    check_sent(sent, buffer, to)?;
 
-    ctx.lock().await.send_events.push(SendEvent {
-        instant: tokio::time::Instant::now(),
+    let mut context = ctx.lock().await;
+    let phase = context.phase.clone();
+    let instant = context.clock.now();
+    let seq = context.next_seq();
+    context.push_send_event(SendEvent {
+        instant,
+        seq,
         from: *from,
         to: *to,
         buffer: Vec::from(buffer),
+        len: buffer.len(),
+        phase,
     });
 
     Ok(())
 }
 
-async fn perform_unicast(
+async fn perform_udp_unicast(
     ctx: Ctx,
     to: &SocketAddr,
     from: &SocketAddr,
     buffer: &[u8],
 ) -> Result<(), ActionError> {
-    let ctx = &mut ctx.lock().await;
-    let streams = &mut ctx.tcp_streams;
+    let socket: UdpSocket = UdpSocket::bind(from).await.map_err(|e| {
+        ActionError::SendError(format!("Failed to bind udp socket to {} ({})", from, e))
+    })?;
 
-    let stream = streams.get_mut(to).ok_or_else(|| {
-        event!(tracing::Level::ERROR, "No stream connected to {}", to);
-        ActionError::SendError(format!("No stream connected to {}", to))
+    let sent = socket.send_to(buffer, to).await.map_err(|e| {
+        event!(
+            tracing::Level::ERROR,
+            "Error sending udp data to {}: {}",
+            to,
+            e
+        );
+        crate::action::ActionError::SendError(e.to_string())
     })?;
+    check_sent(sent, buffer, to)?;
 
-    stream.write_all(buffer).await.map_err(|e| {
+    let mut context = ctx.lock().await;
+    let phase = context.phase.clone();
+    let instant = context.clock.now();
+    let seq = context.next_seq();
+    context.push_send_event(SendEvent {
+        instant,
+        seq,
+        from: *from,
+        to: *to,
+        buffer: Vec::from(buffer),
+        len: buffer.len(),
+        phase,
+    });
+
+    Ok(())
+}
+
+async fn perform_multicast(
+    ctx: Ctx,
+    to: &SocketAddr,
+    from: &SocketAddr,
+    buffer: &[u8],
+) -> Result<(), ActionError> {
+    let group = match to.ip() {
+        IpAddr::V4(group) if group.is_multicast() => group,
+        ip => {
+            return Err(ActionError::SendError(format!(
+                "{} is not an IPv4 multicast group (224.0.0.0/4)",
+                ip
+            )));
+        }
+    };
+
+    let socket = UdpSocket::bind(from)
+        .await
+        .map_err(|e| ActionError::SendError(format!("Failed to bind udp socket ({})", e)))?;
+    let interface = match from.ip() {
+        IpAddr::V4(ip) => ip,
+        IpAddr::V6(_) => Ipv4Addr::UNSPECIFIED,
+    };
+
+    // Join the group so that the datagrams looped back are also delivered to this host
+    socket
+        .join_multicast_v4(group, interface)
+        .and_then(|_| socket.set_multicast_loop_v4(true))
+        .map_err(|e| {
+            ActionError::SendError(format!("Failed to join multicast group {} ({})", group, e))
+        })?;
+
+    let sent = socket.send_to(buffer, to).await.map_err(|e| {
         event!(
             tracing::Level::ERROR,
-            "Error writing to stream {}: {}",
+            "Error sending multicast data to {}: {}",
             to,
             e
         );
         ActionError::SendError(e.to_string())
     })?;
+    check_sent(sent, buffer, to)?;
+
+    let mut context = ctx.lock().await;
+    let phase = context.phase.clone();
+    let instant = context.clock.now();
+    let seq = context.next_seq();
+    context.push_send_event(SendEvent {
+        instant,
+        seq,
+        from: *from,
+        to: *to,
+        buffer: Vec::from(buffer),
+        len: buffer.len(),
+        phase,
+    });
+
+    Ok(())
+}
+
+async fn perform_udp_connected(
+    ctx: Ctx,
+    to: &SocketAddr,
+    from: &SocketAddr,
+    buffer: &[u8],
+) -> Result<(), ActionError> {
+    let existing = ctx.lock().await.udp_sockets.get(to).cloned();
+    let socket = match existing {
+        Some(socket) => socket,
+        None => {
+            let socket = UdpSocket::bind(from).await.map_err(|_| {
+                crate::action::ActionError::SendError("Failed to bind udp socket".into())
+            })?;
+            socket.connect(to).await.map_err(|e| {
+                crate::action::ActionError::SendError(format!(
+                    "Failed to connect udp socket to {} ({})",
+                    to, e
+                ))
+            })?;
+
+            let socket = Arc::new(socket);
+            ctx.lock()
+                .await
+                .udp_sockets
+                .insert(*to, Arc::clone(&socket));
+
+            let (reader, ctx_clone) = (Arc::clone(&socket), Arc::clone(&ctx));
+            tokio::spawn(
+                receive_udp_replies(reader, *to, ctx_clone)
+                    .instrument(tracing::info_span!("udp_replies", peer = %to)),
+            );
+            socket
+        }
+    };
+
+    let sent = socket.send(buffer).await.map_err(|e| {
+        event!(
+            tracing::Level::ERROR,
+            "Error sending udp data to {}: {}",
+            to,
+            e
+        );
+        crate::action::ActionError::SendError(e.to_string())
+    })?;
+    check_sent(sent, buffer, to)?;
 
-    ctx.send_events.push(SendEvent {
-        instant: tokio::time::Instant::now(),
+    let mut context = ctx.lock().await;
+    let phase = context.phase.clone();
+    let instant = context.clock.now();
+    let seq = context.next_seq();
+    context.push_send_event(SendEvent {
+        instant,
+        seq,
         from: *from,
         to: *to,
         buffer: Vec::from(buffer),
+        len: buffer.len(),
+        phase,
     });
+
+    Ok(())
+}
+
+async fn perform_tcp_one_shot(
+    ctx: Ctx,
+    to: &SocketAddr,
+    from: &SocketAddr,
+    buffer: &[u8],
+    read_response: bool,
+) -> Result<(), ActionError> {
+    let mut stream = dial(*from, *to, ONE_SHOT_CONNECT_TIMEOUT_MS).await?;
+    let local_addr = stream.local_addr().unwrap_or(*from);
+    {
+        let mut context = ctx.lock().await;
+        let phase = context.phase.clone();
+        let instant = context.clock.now();
+        let seq = context.next_seq();
+        context.connect_events.push(ConnectEvent {
+            instant,
+            seq,
+            from: local_addr,
+            to: *to,
+            phase,
+        });
+        context.record_connection();
+        context.connect_notifier.notify_waiters();
+    }
+
+    let write_error = |e: std::io::Error| {
+        event!(
+            tracing::Level::ERROR,
+            "Error writing to one-shot stream {}: {}",
+            to,
+            e
+        );
+        ActionError::SendError(e.to_string())
+    };
+    stream.write_all(buffer).await.map_err(write_error)?;
+    // The end of the request is signaled by closing the write side
+    stream.shutdown().await.map_err(write_error)?;
+
+    {
+        let mut context = ctx.lock().await;
+        let phase = context.phase.clone();
+        let instant = context.clock.now();
+        let seq = context.next_seq();
+        context.push_send_event(SendEvent {
+            instant,
+            seq,
+            from: local_addr,
+            to: *to,
+            buffer: Vec::from(buffer),
+            len: buffer.len(),
+            phase,
+        });
+    }
+
+    if !read_response {
+        return Ok(());
+    }
+
+    let read = async {
+        let mut buf = vec![0; 65535];
+        loop {
+            let len = stream.read(&mut buf).await.map_err(|e| {
+                ActionError::SendError(format!("Error reading the response of {} ({})", to, e))
+            })?;
+            if len == 0 {
+                return Ok(());
+            }
+
+            let mut context = ctx.lock().await;
+            let received_message = ReceiveEvent {
+                instant: context.clock.now(),
+                seq: context.next_seq(),
+                from: *to,
+                to: local_addr,
+                buffer: buf[..len].to_vec(),
+                truncated: false,
+                phase: context.phase.clone(),
+            };
+            context.push_receive_event(received_message);
+            context.receive_notifier.notify_waiters();
+        }
+    };
+
+    tokio::time::timeout(Duration::from_millis(ONE_SHOT_RESPONSE_TIMEOUT_MS), read)
+        .await
+        .map_err(|_| {
+            event!(
+                tracing::Level::ERROR,
+                "Response of {} not closed within {} ms",
+                to,
+                ONE_SHOT_RESPONSE_TIMEOUT_MS
+            );
+            ActionError::SendError(format!(
+                "Response of {} not closed within {} ms",
+                to, ONE_SHOT_RESPONSE_TIMEOUT_MS
+            ))
+        })?
+}
+
+/// Fails if a datagram was only partly sent, which the receiver would see truncated.
+fn check_sent(sent: usize, buffer: &[u8], to: &SocketAddr) -> Result<(), ActionError> {
+    if sent < buffer.len() {
+        event!(
+            tracing::Level::ERROR,
+            "Only {} of {} bytes sent to {}",
+            sent,
+            buffer.len(),
+            to
+        );
+        return Err(ActionError::SendError(format!(
+            "Datagram to {} truncated to {} of {} bytes",
+            to,
+            sent,
+            buffer.len()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Records the datagrams received from `peer` on a connected UDP socket.
+async fn receive_udp_replies(socket: Arc<UdpSocket>, peer: SocketAddr, ctx: Ctx) {
+    let mut buf = vec![0; 65535];
+    loop {
+        let len = match socket.recv(&mut buf).await {
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                event!(tracing::Level::WARN, "Udp peer {} is unreachable", peer);
+                continue;
+            }
+            Err(e) => {
+                event!(tracing::Level::ERROR, "Error reading udp replies: {}", e);
+                break;
+            }
+        };
+        event!(
+            tracing::Level::INFO,
+            "Received {} UDP bytes from {}",
+            len,
+            peer
+        );
+
+        let mut context = ctx.lock().await;
+        let received_message = ReceiveEvent {
+            instant: context.clock.now(),
+            seq: context.next_seq(),
+            from: peer,
+            to: socket.local_addr().unwrap_or(peer),
+            buffer: buf[..len].to_vec(),
+            truncated: false,
+            phase: context.phase.clone(),
+        };
+
+        context.push_receive_event(received_message);
+        context.receive_notifier.notify_waiters();
+    }
+}
+
+async fn perform_unicast(
+    ctx: Ctx,
+    to: &SocketAddr,
+    from: &SocketAddr,
+    buffer: &[u8],
+    nonblocking: bool,
+) -> Result<(), ActionError> {
+    let ctx = &mut ctx.lock().await;
+    let stream = stream_to(ctx, to)?;
+
+    let written = if nonblocking {
+        try_write_all(ctx, to, buffer)
+    } else {
+        stream.write_all(buffer).await.map(|_| buffer.len())
+    }
+    .map_err(|e| {
+        event!(
+            tracing::Level::ERROR,
+            "Error writing to stream {}: {}",
+            to,
+            e
+        );
+        ActionError::SendError(e.to_string())
+    })?;
+
+    if written > 0 {
+        let phase = ctx.phase.clone();
+        let instant = ctx.clock.now();
+        let seq = ctx.next_seq();
+        ctx.push_send_event(SendEvent {
+            instant,
+            seq,
+            from: *from,
+            to: *to,
+            buffer: Vec::from(&buffer[..written]),
+            len: written,
+            phase,
+        });
+    }
+
+    if written < buffer.len() {
+        event!(
+            tracing::Level::WARN,
+            "Stream to {} would block, {} of {} bytes sent",
+            to,
+            written,
+            buffer.len()
+        );
+        return Err(ActionError::WouldBlock(written));
+    }
     event!(tracing::Level::INFO, "Data sent to {}", to);
 
     Ok(())
 }
+
+/// Returns the stream to `to`, preferring the one connected by the node to the ones it accepted.
+fn stream_to<'a>(
+    ctx: &'a mut NodeContext,
+    to: &SocketAddr,
+) -> Result<&'a mut (dyn AsyncWrite + Unpin + std::marker::Send), ActionError> {
+    if let Some(stream) = ctx.tcp_streams.get_mut(to) {
+        Ok(stream)
+    } else if let Some(stream) = ctx.server_streams.get_mut(to) {
+        Ok(stream)
+    } else {
+        event!(tracing::Level::ERROR, "No stream connected to {}", to);
+        Err(ActionError::SendError(format!(
+            "No stream connected to {}",
+            to
+        )))
+    }
+}
+
+/// Stream taken out of the context of the node, to be written to without holding it.
+enum TakenStream {
+    Connected(TcpStream),
+    Accepted(OwnedWriteHalf),
+}
+
+impl TakenStream {
+    /// Takes the stream to `to`, preferring the one connected by the node to the ones it accepted.
+    ///
+    /// The stream is missing from the context until restored.
+    fn take(ctx: &mut NodeContext, to: &SocketAddr) -> Result<Self, ActionError> {
+        if let Some(stream) = ctx.tcp_streams.remove(to) {
+            Ok(TakenStream::Connected(stream))
+        } else if let Some(stream) = ctx.server_streams.remove(to) {
+            Ok(TakenStream::Accepted(stream))
+        } else {
+            event!(tracing::Level::ERROR, "No stream connected to {}", to);
+            Err(ActionError::SendError(format!(
+                "No stream connected to {}",
+                to
+            )))
+        }
+    }
+
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Unpin + std::marker::Send) {
+        match self {
+            TakenStream::Connected(stream) => stream,
+            TakenStream::Accepted(stream) => stream,
+        }
+    }
+
+    /// Puts the stream back into the context, unless another stream to `to` was stored meanwhile.
+    fn restore(self, ctx: &mut NodeContext, to: SocketAddr) {
+        match self {
+            TakenStream::Connected(stream) => {
+                ctx.tcp_streams.entry(to).or_insert(stream);
+            }
+            TakenStream::Accepted(stream) => {
+                ctx.server_streams.entry(to).or_insert(stream);
+            }
+        }
+    }
+}
+
+/// Writes as much of `buffer` as the stream to `to` accepts without waiting
+/// and returns the number of bytes written.
+fn try_write_all(ctx: &NodeContext, to: &SocketAddr, buffer: &[u8]) -> std::io::Result<usize> {
+    let mut written = 0;
+
+    while written < buffer.len() {
+        let result = if let Some(stream) = ctx.tcp_streams.get(to) {
+            stream.try_write(&buffer[written..])
+        } else if let Some(stream) = ctx.server_streams.get(to) {
+            stream.try_write(&buffer[written..])
+        } else {
+            return Err(std::io::ErrorKind::NotConnected.into());
+        };
+
+        match result {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(n) => written += n,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, net::SocketAddr, time::Duration};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpSocket, UdpSocket},
+    };
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+    };
+
+    use crate::protocol::{
+        Protocol,
+        ip::{Bind, Connect},
+    };
+
+    use super::{Send, SendMode, SendPattern, check_sent};
+
+    #[tokio::test]
+    async fn test_udp_unicast() {
+        let listener = UdpSocket::bind("127.0.1.77:3000").await.unwrap();
+        let to = listener.local_addr().unwrap();
+        let node = Node::new("test-node");
+
+        Send::new(
+            SendMode::UdpUnicast,
+            "127.0.1.78:0".parse().unwrap(),
+            to,
+            b"request".to_vec(),
+        )
+        .perform(node.ctx())
+        .await
+        .unwrap();
+
+        let mut buf = [0; 16];
+        let (len, from) = listener.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"request");
+        assert_eq!(from.ip(), "127.0.1.78".parse::<std::net::IpAddr>().unwrap());
+
+        // Binding the address of the listener fails with the OS error
+        match Send::new(SendMode::UdpUnicast, to, to, b"request".to_vec())
+            .perform(node.ctx())
+            .await
+        {
+            Err(ActionError::SendError(reason)) => {
+                assert!(reason.contains("in use"), "{}", reason)
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        assert_eq!(node.ctx().lock().await.send_events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_udp_unicast_ipv6() {
+        let server = Node::new("server");
+        Bind::new("[::1]:0".parse().unwrap())
+            .with_protocol(Protocol::Udp)
+            .perform(server.ctx())
+            .await
+            .unwrap();
+
+        let mut to = None;
+        for _ in 0..50 {
+            to = server.ctx().lock().await.bound.first().copied();
+            if to.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let to = to.expect("The IPv6 listener was not bound");
+
+        let node = Node::new("test-node");
+        let send = Send::new(
+            SendMode::UdpUnicast,
+            "[::1]:0".parse().unwrap(),
+            to,
+            vec![1, 2, 3],
+        );
+        send.perform(node.ctx()).await.unwrap();
+        assert_eq!(node.ctx().lock().await.send_events.len(), 1);
+
+        for _ in 0..50 {
+            if !server.ctx().lock().await.receive_events.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let context = server.ctx();
+        let context = context.lock().await;
+        assert_eq!(context.receive_events.len(), 1);
+        assert_eq!(context.receive_events[0].buffer, vec![1, 2, 3]);
+        assert!(context.receive_events[0].from.is_ipv6());
+        assert_eq!(context.receive_events[0].to, to);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_ipv6_unicast_address_is_rejected() {
+        let to: SocketAddr = "[::1]:49999".parse().unwrap();
+        let node = Node::new("test-node");
+        let send = Send::new(
+            SendMode::Broadcast,
+            "[::1]:0".parse().unwrap(),
+            to,
+            vec![1, 2, 3],
+        );
+
+        assert!(matches!(
+            send.perform(node.ctx()).await,
+            Err(ActionError::SendError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unicast_to_accepted_stream() {
+        let server: SocketAddr = "127.0.1.17:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server).perform(ctx.clone()).await.unwrap();
+
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.1.18:0".parse().unwrap()).unwrap();
+        let mut client = socket.connect(server).await.unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        for _ in 0..50 {
+            if ctx.lock().await.server_streams.contains_key(&client_addr) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        Send::new(SendMode::Unicast, server, client_addr, vec![1, 2, 3])
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut buf = [0; 16];
+        let len = client.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], &[1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_udp_connected_request_response() {
+        let peer = UdpSocket::bind("127.0.1.25:3000").await.unwrap();
+        let to = peer.local_addr().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        let request = Send::new(
+            SendMode::UdpConnected,
+            "127.0.1.26:0".parse().unwrap(),
+            to,
+            b"request".to_vec(),
+        );
+
+        let mut sources = Vec::new();
+        for _ in 0..2 {
+            request.perform(ctx.clone()).await.unwrap();
+
+            let mut buf = [0; 16];
+            let (len, source) = peer.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], b"request");
+            sources.push(source);
+
+            peer.send_to(b"response", source).await.unwrap();
+        }
+
+        for _ in 0..50 {
+            if ctx.lock().await.receive_events.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The second request reuses the socket of the first one
+        assert_eq!(sources[0], sources[1]);
+        let context = ctx.lock().await;
+        assert_eq!(context.udp_sockets.len(), 1);
+        assert_eq!(context.receive_events.len(), 2);
+        assert_eq!(context.receive_events[0].from, to);
+        assert_eq!(context.receive_events[0].buffer, b"response".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_send_would_block() {
+        let listener = TcpSocket::new_v4().unwrap();
+        listener.set_recv_buffer_size(4096).unwrap();
+        listener.bind("127.0.1.27:3000".parse().unwrap()).unwrap();
+        let listener = listener.listen(8).unwrap();
+        let to = listener.local_addr().unwrap();
+
+        // The peer never reads, so the buffers fill up
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_send_buffer_size(4096).unwrap();
+        socket.bind("127.0.1.28:0".parse().unwrap()).unwrap();
+        let stream = socket.connect(to).await.unwrap();
+        let (_peer, _) = listener.accept().await.unwrap();
+
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        ctx.lock().await.tcp_streams.insert(to, stream);
+
+        let buffer = vec![0xAB; 4 * 1024 * 1024];
+        let send = Send::new(
+            SendMode::Unicast,
+            "127.0.1.28:0".parse().unwrap(),
+            to,
+            buffer.clone(),
+        )
+        .with_nonblocking(true);
+
+        match send.perform(ctx.clone()).await {
+            Err(ActionError::WouldBlock(written)) => {
+                assert!(written > 0 && written < buffer.len());
+                assert_eq!(ctx.lock().await.send_events[0].buffer.len(), written);
+            }
+            other => panic!("Expected WouldBlock, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_rejected_outside_unicast() {
+        let node = Node::new("test-node");
+        let send = Send::new(
+            SendMode::UdpUnicast,
+            "127.0.1.136:0".parse().unwrap(),
+            "127.0.1.136:3000".parse().unwrap(),
+            b"hello".to_vec(),
+        )
+        .with_nonblocking(true);
+
+        let result = send.perform(node.ctx()).await;
+
+        assert!(matches!(result, Err(ActionError::SendError(_))));
+        assert!(node.ctx().lock().await.send_events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_pattern() {
+        let server: SocketAddr = "127.0.1.47:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.48:0".parse().unwrap();
+        // A single chunk larger than the socket buffers, read by the same node
+        let total = 16 * 1024 * 1024;
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Connect::new(client, server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        tokio::time::timeout(
+            Duration::from_secs(10),
+            SendPattern::new(client, server, 0xab, total, total).perform(ctx.clone()),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(ctx.lock().await.tcp_streams.contains_key(&server));
+
+        let received = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let received: usize = ctx
+                    .lock()
+                    .await
+                    .receive_events
+                    .iter()
+                    .map(|e| e.buffer.len())
+                    .sum();
+                if received >= total {
+                    return received;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let context = ctx.lock().await;
+        assert_eq!(received, total);
+        assert!(
+            context
+                .receive_events
+                .iter()
+                .all(|e| e.buffer.iter().all(|b| *b == 0xab))
+        );
+        assert_eq!(context.send_events.len(), 1);
+        assert_eq!(context.send_events[0].len, total);
+        assert!(context.send_events[0].buffer.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_multicast_rejects_non_group_address() {
+        let node = Node::new("test-node");
+
+        for to in ["127.0.1.75:3000", "[ff02::1]:3000"] {
+            let send = Send::new(
+                SendMode::Multicast,
+                "127.0.1.76:0".parse().unwrap(),
+                to.parse().unwrap(),
+                b"discover".to_vec(),
+            );
+            match send.perform(node.ctx()).await {
+                Err(ActionError::SendError(reason)) => {
+                    assert!(reason.contains("not an IPv4 multicast group"), "{}", reason)
+                }
+                other => panic!("Unexpected result {:?}", other),
+            }
+        }
+        assert!(node.ctx().lock().await.send_events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_one_shot_opens_a_connection_per_send() {
+        let server: SocketAddr = "127.0.1.92:3000".parse().unwrap();
+        let server_node = Node::new("server");
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(server_node.ctx())
+            .await
+            .unwrap();
+
+        let node = Node::new("client");
+        for request in [b"GET /1", b"GET /2", b"GET /3"] {
+            Send::new(
+                SendMode::TcpOneShot {
+                    read_response: false,
+                },
+                "127.0.1.93:0".parse().unwrap(),
+                server,
+                request.to_vec(),
+            )
+            .perform(node.ctx())
+            .await
+            .unwrap();
+        }
+
+        for _ in 0..50 {
+            if server_node.ctx().lock().await.connect_events.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let sources: HashSet<SocketAddr> = server_node
+            .ctx()
+            .lock()
+            .await
+            .connect_events
+            .iter()
+            .map(|e| e.from)
+            .collect();
+        assert_eq!(sources.len(), 3);
+        assert_eq!(node.ctx().lock().await.send_events.len(), 3);
+        // The client does not keep the connections
+        assert!(node.ctx().lock().await.tcp_streams.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_one_shot_records_the_response() {
+        let server: SocketAddr = "127.0.1.137:3000".parse().unwrap();
+        let listener = TcpListener::bind(server).await.unwrap();
+        tokio::spawn(async move {
+            for close in [true, false] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).await.unwrap();
+                stream.write_all(b"200 ").await.unwrap();
+                stream.write_all(&request).await.unwrap();
+                if close {
+                    drop(stream);
+                } else {
+                    // Never closes the connection
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        drop(stream);
+                    });
+                }
+            }
+        });
+
+        let node = Node::new("client");
+        let send = |request: &[u8]| {
+            Send::new(
+                SendMode::TcpOneShot {
+                    read_response: true,
+                },
+                "127.0.1.138:0".parse().unwrap(),
+                server,
+                request.to_vec(),
+            )
+        };
+
+        send(b"GET /1").perform(node.ctx()).await.unwrap();
+        let response: Vec<u8> = node
+            .ctx()
+            .lock()
+            .await
+            .receive_events
+            .iter()
+            .flat_map(|e| e.buffer.clone())
+            .collect();
+        assert_eq!(response, b"200 GET /1");
+        assert_eq!(node.ctx().lock().await.receive_events[0].from, server);
+
+        // A peer that never closes the connection does not hang the action
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), send(b"GET /2").perform(node.ctx()))
+                .await
+                .expect("The response read is not bounded");
+        assert!(matches!(result, Err(ActionError::SendError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_datagram_is_reported() {
+        let node = Node::new("test-node");
+        let result = Send::new(
+            SendMode::UdpUnicast,
+            "127.0.1.101:0".parse().unwrap(),
+            "127.0.1.102:3000".parse().unwrap(),
+            vec![0; 70000],
+        )
+        .perform(node.ctx())
+        .await;
+
+        assert!(matches!(result, Err(ActionError::SendError(_))));
+        assert!(node.ctx().lock().await.send_events.is_empty());
+
+        let to: SocketAddr = "127.0.1.102:3000".parse().unwrap();
+        assert!(matches!(
+            check_sent(3, &[0; 5], &to),
+            Err(ActionError::SendError(e)) if e.contains("3 of 5 bytes")
+        ));
+        assert!(check_sent(5, &[0; 5], &to).is_ok());
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{Ctx, NodeContext, ReceiveEvent},
+};
+
+/// Interval between two checks of the sockets receive queues.
+const SYNC_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Waits until every byte received by the sockets of the node has been recorded as a receive event.
+///
+/// The bytes read by the `Bind` listeners are covered until recorded, including those held
+/// for the rest of their record, a coalescing delay or a reorder window. A partial record
+/// therefore makes the sync time out. Data pending on the streams connected by the node,
+/// which are not read in the background, is read and recorded. The replies read by the
+/// response readers of `Connect` are only covered while queued on the socket.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Sync {
+    timeout_ms: u64,
+}
+
+impl Sync {
+    pub fn new(timeout_ms: u64) -> Self {
+        Sync { timeout_ms }
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout_ms
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for Sync {
+    fn name(&self) -> String {
+        "SYNC".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let sync = async {
+            loop {
+                let pending = {
+                    let mut context = ctx.lock().await;
+                    read_connected_streams(&mut context);
+                    // The listeners count what they read under the lock, the bytes are
+                    // either still queued or unrecorded
+                    pending_bytes(&context) + context.unrecorded_bytes
+                };
+
+                if pending == 0 {
+                    break;
+                }
+
+                event!(
+                    tracing::Level::DEBUG,
+                    "{} bytes not yet recorded, waiting for the listeners",
+                    pending
+                );
+                tokio::time::sleep(SYNC_POLL_INTERVAL).await;
+            }
+        };
+
+        tokio::time::timeout(Duration::from_millis(self.timeout_ms), sync)
+            .await
+            .map_err(|_| {
+                event!(
+                    tracing::Level::WARN,
+                    "Timeout waiting for pending reads ({}ms)",
+                    self.timeout_ms
+                );
+                ActionError::WaitError
+            })
+    }
+}
+
+/// Records the data available on the streams connected by the node.
+fn read_connected_streams(context: &mut NodeContext) {
+    let mut received = Vec::new();
+
+    for (peer, stream) in context.tcp_streams.iter() {
+        let mut buf = vec![0; 4096];
+        loop {
+            match stream.try_read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    event!(tracing::Level::INFO, "Read {} TCP bytes from {}", n, peer);
+                    let to = stream.local_addr().unwrap_or(*peer);
+                    received.push((*peer, to, buf[..n].to_vec()));
+                }
+                Err(_) => break,
+            }
+        }
+    }
+
+    if received.is_empty() {
+        return;
+    }
+
+    for (from, to, buffer) in received {
+        let received_message = ReceiveEvent {
+            instant: context.clock.now(),
+            seq: context.next_seq(),
+            from,
+            to,
+            buffer,
+            truncated: false,
+            phase: context.phase.clone(),
+        };
+        context.push_receive_event(received_message);
+    }
+    context.receive_notifier.notify_waiters();
+}
+
+/// Returns the number of bytes waiting in the receive queues of the sockets of the node.
+#[cfg(target_os = "linux")]
+fn pending_bytes(context: &NodeContext) -> usize {
+    use std::os::fd::AsRawFd;
+
+    fn unread_bytes(fd: std::os::fd::RawFd) -> usize {
+        let mut unread: libc::c_int = 0;
+        // SAFETY: the descriptor is kept open by the context and `unread` outlives the call
+        let res = unsafe { libc::ioctl(fd, libc::FIONREAD, &mut unread) };
+        if res < 0 { 0 } else { unread as usize }
+    }
+
+    let connected = context
+        .tcp_streams
+        .values()
+        .map(|stream| unread_bytes(stream.as_raw_fd()));
+    let accepted = context
+        .server_streams
+        .values()
+        .map(|writer| unread_bytes(writer.as_ref().as_raw_fd()));
+    let sockets = context
+        .udp_listeners
+        .values()
+        .map(|socket| unread_bytes(socket.as_raw_fd()));
+
+    connected.chain(accepted).chain(sockets).sum()
+}
+
+/// The receive queues can't be inspected on this platform.
+#[cfg(not(target_os = "linux"))]
+fn pending_bytes(_context: &NodeContext) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use tokio::{
+        io::AsyncWriteExt,
+        net::{TcpListener, TcpSocket, TcpStream},
+    };
+
+    use crate::{
+        Node,
+        action::{Action, ActionError},
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect, Framing},
+        },
+    };
+
+    use super::Sync;
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_sync_records_pending_reads() {
+        let server: SocketAddr = "127.0.1.22:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server).perform(ctx.clone()).await.unwrap();
+
+        // A peer writing to a stream accepted by the node
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.bind("127.0.1.23:0".parse().unwrap()).unwrap();
+        let mut client = socket.connect(server).await.unwrap();
+        client.write_all(&[1, 2, 3]).await.unwrap();
+
+        // A peer writing to a stream connected by the node
+        let listener = TcpListener::bind("127.0.1.23:3000").await.unwrap();
+        Connect::new(
+            "127.0.1.22:0".parse().unwrap(),
+            listener.local_addr().unwrap(),
+            1000,
+        )
+        .perform(ctx.clone())
+        .await
+        .unwrap();
+        let (mut peer, _) = listener.accept().await.unwrap();
+        peer.write_all(&[4, 5, 6]).await.unwrap();
+
+        Sync::new(1000).perform(ctx.clone()).await.unwrap();
+
+        let buffers: Vec<Vec<u8>> = ctx
+            .lock()
+            .await
+            .receive_events
+            .iter()
+            .map(|e| e.buffer.clone())
+            .collect();
+        assert!(buffers.contains(&vec![1, 2, 3]));
+        assert!(buffers.contains(&vec![4, 5, 6]));
+    }
+
+    /// Waits until the node accepted a connection, the sync only covers known sockets.
+    async fn wait_accepted(node: &Node) {
+        for _ in 0..50 {
+            if !node.ctx().lock().await.server_streams.is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("The connection was not accepted");
+    }
+
+    #[tokio::test]
+    async fn test_sync_waits_for_held_records() {
+        let server: SocketAddr = "127.0.1.139:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::Coalesce { idle_ms: 200 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut client = TcpStream::connect(server).await.unwrap();
+        wait_accepted(&node).await;
+        client.write_all(b"HELLO").await.unwrap();
+
+        // The bytes are held by the listener until the connection is idle
+        Sync::new(1000).perform(ctx.clone()).await.unwrap();
+        let context = ctx.lock().await;
+        assert_eq!(context.receive_events.len(), 1);
+        assert_eq!(context.receive_events[0].buffer, b"HELLO".to_vec());
+        assert_eq!(context.unrecorded_bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_times_out_on_partial_record() {
+        let server: SocketAddr = "127.0.1.140:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .with_framing(Framing::LengthPrefixed { header_bytes: 2 })
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        // The rest of the frame never comes
+        let mut client = TcpStream::connect(server).await.unwrap();
+        wait_accepted(&node).await;
+        client.write_all(&[0, 5, b'H', b'E']).await.unwrap();
+
+        assert_eq!(
+            Sync::new(200).perform(ctx.clone()).await,
+            Err(ActionError::WaitError)
+        );
+        assert!(ctx.lock().await.receive_events.is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+use std::{net::SocketAddr, os::fd::AsRawFd};
+
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tracing::event;
+
+use crate::{
+    action::{Action, ActionError},
+    node::{Ctx, TcpInfoSnapshot},
+};
+
+/// Records the kernel statistics (TCP_INFO) of the stream connected to `peer`.
+///
+/// Both the streams connected and accepted by the node are looked up,
+/// the snapshot replaces the previous one of the same peer.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TcpInfo {
+    peer: SocketAddr,
+}
+
+impl TcpInfo {
+    pub fn new(peer: SocketAddr) -> Self {
+        TcpInfo { peer }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for TcpInfo {
+    fn name(&self) -> String {
+        "TCP_INFO".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let mut context = ctx.lock().await;
+        let stream: &TcpStream = if let Some(stream) = context.tcp_streams.get(&self.peer) {
+            stream
+        } else if let Some(stream) = context.server_streams.get(&self.peer) {
+            stream.as_ref()
+        } else {
+            event!(
+                tracing::Level::ERROR,
+                "No stream connected to {}",
+                self.peer
+            );
+            return Err(ActionError::AssertError(format!(
+                "No stream connected to {}",
+                self.peer
+            )));
+        };
+
+        let info = tcp_info(stream).map_err(|e| {
+            ActionError::AssertError(format!("Error reading TCP_INFO of {} ({})", self.peer, e))
+        })?;
+
+        let snapshot = TcpInfoSnapshot {
+            instant: context.clock.now(),
+            rtt_us: info.tcpi_rtt,
+            rttvar_us: info.tcpi_rttvar,
+            retransmits: info.tcpi_retransmits,
+            total_retrans: info.tcpi_total_retrans,
+            snd_cwnd: info.tcpi_snd_cwnd,
+        };
+        event!(
+            tracing::Level::INFO,
+            "TCP_INFO of {}: {:?}",
+            self.peer,
+            snapshot
+        );
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &context.metrics {
+            metrics.sample("rtt_us", snapshot.rtt_us as f64);
+        }
+        context.tcp_infos.insert(self.peer, snapshot);
+
+        Ok(())
+    }
+}
+
+/// Asserts the last [`TcpInfo`] snapshot of `peer` counts fewer than `max` retransmitted segments.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct AssertRetransmitsBelow {
+    peer: SocketAddr,
+    max: u32,
+}
+
+impl AssertRetransmitsBelow {
+    pub fn new(peer: SocketAddr, max: u32) -> Self {
+        AssertRetransmitsBelow { peer, max }
+    }
+
+    pub fn peer(&self) -> &SocketAddr {
+        &self.peer
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for AssertRetransmitsBelow {
+    fn name(&self) -> String {
+        "ASSERT_RETRANSMITS_BELOW".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let context = ctx.lock().await;
+        let Some(snapshot) = context.tcp_infos.get(&self.peer) else {
+            return Err(ActionError::AssertError(format!(
+                "No TCP_INFO snapshot of {}",
+                self.peer
+            )));
+        };
+
+        if snapshot.total_retrans >= self.max {
+            return Err(ActionError::AssertError(format!(
+                "{} segments retransmitted to {}, expected fewer than {}",
+                snapshot.total_retrans, self.peer, self.max
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn tcp_info(stream: &TcpStream) -> std::io::Result<libc::tcp_info> {
+    // SAFETY: tcp_info is a plain C struct, all zeroes is a valid value
+    let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+
+    // SAFETY: the descriptor is owned by `stream`, `info` and `len` outlive the call
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            &mut info as *mut libc::tcp_info as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        Node,
+        action::Action,
+        protocol::{
+            Protocol,
+            ip::{Bind, Connect, Send, SendMode},
+        },
+    };
+
+    use super::{AssertRetransmitsBelow, TcpInfo};
+
+    #[tokio::test]
+    async fn test_tcp_info() {
+        let server: SocketAddr = "127.0.1.64:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        let client: SocketAddr = "127.0.1.65:0".parse().unwrap();
+        Connect::new(client, server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        Send::new(SendMode::Unicast, client, server, vec![1; 1024])
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        TcpInfo::new(server).perform(ctx.clone()).await.unwrap();
+        {
+            let context = ctx.lock().await;
+            let snapshot = &context.tcp_infos[&server];
+            // Loopback round trips are well under a second
+            assert!(snapshot.rtt_us < 1_000_000, "{:?}", snapshot);
+            assert!(snapshot.snd_cwnd > 0, "{:?}", snapshot);
+        }
+
+        assert!(
+            AssertRetransmitsBelow::new(server, 1)
+                .perform(ctx.clone())
+                .await
+                .is_ok()
+        );
+        assert!(
+            AssertRetransmitsBelow::new("127.0.1.66:3000".parse().unwrap(), 1)
+                .perform(ctx)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,12 +1,13 @@
-use std::net::SocketAddr;
+use std::{future::Future, net::SocketAddr, pin::Pin, sync::Arc, task::Poll, time::Duration};
 
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
-use tokio::time::Instant;
+use tokio::{sync::Notify, time::Instant};
 use tracing::event;
 
 use crate::{
     action::{Action, ActionError},
-    node::{ConnectEvent, Ctx, ReceiveEvent},
+    node::{ConnectEvent, Ctx, NodeContext, ReceiveEvent, SendEvent},
 };
 
 #[async_trait::async_trait]
@@ -18,11 +19,54 @@ pub trait Predicate {
 pub struct ConnectPredicate {
     pub from: SocketAddr,
     pub to: SocketAddr,
+    #[serde(default)]
+    pub phase: Option<String>,
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Subnet of the peer, replacing the ip of `from` when set.
+    #[serde(default)]
+    pub from_cidr: Option<IpNet>,
 }
 
 impl ConnectPredicate {
     pub fn new(from: SocketAddr, to: SocketAddr) -> Self {
-        ConnectPredicate { from, to }
+        ConnectPredicate {
+            from,
+            to,
+            phase: None,
+            debounce_ms: None,
+            timeout_ms: None,
+            from_cidr: None,
+        }
+    }
+
+    /// Only considers the events recorded during the given phase.
+    pub fn with_phase(mut self, phase: &str) -> Self {
+        self.phase = Some(phase.to_string());
+        self
+    }
+
+    /// Accepts a connection from any ip of `cidr`, such as a NAT or container
+    /// network, instead of the ip of `from`. The port of `from` still applies.
+    pub fn with_from_cidr(mut self, cidr: IpNet) -> Self {
+        self.from_cidr = Some(cidr);
+        self
+    }
+
+    /// Waits `debounce_ms` after a notification before checking again,
+    /// so that a burst of connections is checked at once.
+    pub fn with_debounce(mut self, debounce_ms: u64) -> Self {
+        self.debounce_ms = Some(debounce_ms);
+        self
+    }
+
+    /// Fails with `ActionError::WaitError` if no matching connection was
+    /// recorded after `timeout_ms`, instead of waiting forever.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
     }
 
     pub fn from(&self) -> &SocketAddr {
@@ -38,37 +82,99 @@ impl ConnectPredicate {
 impl Predicate for ConnectPredicate {
     /// Checks if a connection event from `from` to `to` exists in the context.
     async fn check(&self, ctx: Ctx) -> Result<(), ActionError> {
-        let instant = Instant::now();
-        loop {
-            let notifier = {
-                let context = ctx.lock().await;
-                let connect_events = &context.connect_events;
-
-                match connect_match(self.from, self.to, instant, connect_events) {
-                    true => {
-                        event!(
-                            tracing::Level::DEBUG,
-                            "Connection from {} to {} found",
-                            self.from,
-                            self.to
-                        );
-                        return Ok(());
-                    }
-                    false => {
-                        event!(
-                            tracing::Level::DEBUG,
-                            "Connection from {} to {} not found",
-                            self.from,
-                            self.to
-                        );
-                    }
-                }
+        let instant = ctx.lock().await.clock.now();
+        let debounce = self.debounce_ms.map(Duration::from_millis);
+
+        let scan = scan_until(
+            &ctx,
+            |context| context.connect_notifier.clone(),
+            debounce,
+            |context| {
+                let found = self.evaluate(&context.connect_events, instant);
+                event!(
+                    tracing::Level::DEBUG,
+                    "Connection from {} to {} {}",
+                    self.from,
+                    self.to,
+                    if found { "found" } else { "not found" }
+                );
+                found
+            },
+        );
+
+        let Some(timeout_ms) = self.timeout_ms else {
+            scan.await;
+            return Ok(());
+        };
+        if tokio::time::timeout(Duration::from_millis(timeout_ms), scan)
+            .await
+            .is_err()
+        {
+            event!(
+                tracing::Level::WARN,
+                "No connection from {} to {} after {}ms",
+                self.from,
+                self.to,
+                timeout_ms
+            );
+            return Err(ActionError::WaitError);
+        }
 
-                context.connect_notifier.clone()
-            };
+        Ok(())
+    }
+}
 
-            // wait to be notified before checking again
-            notifier.notified().await;
+impl ConnectPredicate {
+    /// Returns true if a matching connection was recorded after `since`.
+    fn evaluate(&self, connect_events: &[ConnectEvent], since: Instant) -> bool {
+        let connect_events: Vec<ConnectEvent> = connect_events
+            .iter()
+            .filter(|e| phase_match(self.phase.as_deref(), e.phase.as_deref()))
+            .cloned()
+            .collect();
+
+        match self.from_cidr {
+            Some(cidr) => connect_events.iter().any(|e| {
+                e.instant > since
+                    && cidr.contains(&e.from.ip())
+                    && (self.from.port() == 0 || self.from.port() == e.from.port())
+                    && addr_match(self.to, e.to)
+            }),
+            None => connect_match(self.from, self.to, since, &connect_events),
+        }
+    }
+}
+
+/// Calls `scan` under the context lock until it returns true, scanning again each time
+/// the notifier returned by `notifier` is signaled, at most once per `debounce` if set.
+///
+/// Returns the number of scans.
+async fn scan_until(
+    ctx: &Ctx,
+    notifier: impl Fn(&NodeContext) -> Arc<Notify>,
+    debounce: Option<Duration>,
+    mut scan: impl FnMut(&NodeContext) -> bool,
+) -> usize {
+    let mut scans = 0;
+    loop {
+        let context = ctx.lock().await;
+        let notifier = notifier(&context);
+        // Register before scanning so that a notification sent meanwhile is not missed
+        let notified = notifier.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        scans += 1;
+        if scan(&context) {
+            return scans;
+        }
+        drop(context);
+
+        // wait to be notified before checking again
+        notified.await;
+        if let Some(debounce) = debounce {
+            // Notifications sent meanwhile are covered by the next scan
+            tokio::time::sleep(debounce).await;
         }
     }
 }
@@ -91,7 +197,7 @@ fn connect_match(
                     expected_from.ip(),
                     expected_to
                 );
-                e.from.ip() == expected_from.ip() && e.to == expected_to
+                e.from.ip() == expected_from.ip() && addr_match(expected_to, e.to)
             } else {
                 event!(
                     tracing::Level::DEBUG,
@@ -99,36 +205,146 @@ fn connect_match(
                     expected_from,
                     expected_to
                 );
-                e.from == expected_from && e.to == expected_to
+                e.from == expected_from && addr_match(expected_to, e.to)
             }
         })
 }
 
+/// A regular expression over bytes, serialized as its source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BytePattern(regex::bytes::Regex);
+
+impl BytePattern {
+    pub fn new(pattern: &str) -> Result<Self, regex::Error> {
+        regex::bytes::Regex::new(pattern).map(BytePattern)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, buffer: &[u8]) -> bool {
+        self.0.is_match(buffer)
+    }
+}
+
+impl PartialEq for BytePattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl TryFrom<String> for BytePattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        BytePattern::new(&pattern)
+    }
+}
+
+impl From<BytePattern> for String {
+    fn from(pattern: BytePattern) -> Self {
+        pattern.as_str().to_string()
+    }
+}
+
+/// How the received bytes are compared by a `MessagesPredicate`.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub enum BufferMatch {
+    /// The received bytes equal `buffer`.
+    #[default]
+    Exact,
+    /// The pattern matches somewhere in the received bytes, `buffer` is ignored.
+    Regex(BytePattern),
+    /// The received bytes start with the given header, `buffer` is ignored.
+    Prefix(Vec<u8>),
+    /// The received bytes end with the given trailer, `buffer` is ignored.
+    Suffix(Vec<u8>),
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct MessagesPredicate {
     pub from: SocketAddr,
     pub to: SocketAddr,
     pub buffer: Vec<u8>,
+    #[serde(default)]
+    pub matching: BufferMatch,
 }
 
 impl MessagesPredicate {
+    /// Returns true if the event holds the expected buffer. A port of 0 in
+    /// `from` or `to` matches any port.
     pub fn matches(&self, event: &ReceiveEvent) -> bool {
-        if self.from.port() == 0 {
-            event.from.ip() == self.from.ip() && event.to == self.to && event.buffer == self.buffer
-        } else {
-            event.from == self.from && event.to == self.to && event.buffer == self.buffer
-        }
+        addr_match(self.from, event.from)
+            && addr_match(self.to, event.to)
+            && match &self.matching {
+                BufferMatch::Exact => event.buffer == self.buffer,
+                BufferMatch::Regex(pattern) => pattern.is_match(&event.buffer),
+                BufferMatch::Prefix(prefix) => event.buffer.starts_with(prefix),
+                BufferMatch::Suffix(suffix) => event.buffer.ends_with(suffix),
+            }
     }
 }
 
+/// Whether the expected messages of a `ReceivePredicate` must be received in order.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum OrderType {
+    /// The messages are received in the given order, other messages may come in between.
+    #[default]
+    Ordered,
+    /// Each message matches a distinct received message, in any order.
+    Unordered,
+}
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ReceivePredicate {
     pub messages: Vec<MessagesPredicate>,
+    #[serde(default)]
+    pub phase: Option<String>,
+    #[serde(default)]
+    pub debounce_ms: Option<u64>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub order: OrderType,
 }
 
 impl ReceivePredicate {
     pub fn new(messages: Vec<MessagesPredicate>) -> Self {
-        ReceivePredicate { messages }
+        ReceivePredicate {
+            messages,
+            phase: None,
+            debounce_ms: None,
+            timeout_ms: None,
+            order: OrderType::Ordered,
+        }
+    }
+
+    /// Only considers the events recorded during the given phase.
+    pub fn with_phase(mut self, phase: &str) -> Self {
+        self.phase = Some(phase.to_string());
+        self
+    }
+
+    /// Sets whether the messages must be received in the given order.
+    pub fn with_order(mut self, order: OrderType) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Waits `debounce_ms` after a notification before checking again,
+    /// so that a burst of received messages is checked at once.
+    pub fn with_debounce(mut self, debounce_ms: u64) -> Self {
+        self.debounce_ms = Some(debounce_ms);
+        self
+    }
+
+    /// Fails with `ActionError::WaitError` if the messages were not all
+    /// received after `timeout_ms`, instead of waiting forever.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
     }
 
     pub fn messages(&self) -> &[MessagesPredicate] {
@@ -140,66 +356,668 @@ impl ReceivePredicate {
 impl Predicate for ReceivePredicate {
     /// Checks if the specified messages have been received in the context.
     async fn check(&self, ctx: Ctx) -> Result<(), ActionError> {
-        let instant = Instant::now();
-        loop {
+        let instant = ctx.lock().await.clock.now();
+        let debounce = self.debounce_ms.map(Duration::from_millis);
+        let mut matcher = ReceiveMatcher::new(self.order);
+
+        let scan = scan_until(
+            &ctx,
+            |context| context.receive_notifier.clone(),
+            debounce,
+            |context| {
+                let found = matcher.advance(&context.receive_events, &self.messages, |e| {
+                    received_since(e, instant, self.phase.as_deref())
+                });
+                event!(
+                    tracing::Level::DEBUG,
+                    "Receive predicate {} for messages {:?}",
+                    if found { "found" } else { "not found" },
+                    self.messages
+                );
+                found
+            },
+        );
+
+        let Some(timeout_ms) = self.timeout_ms else {
+            scan.await;
+            return Ok(());
+        };
+        if tokio::time::timeout(Duration::from_millis(timeout_ms), scan)
+            .await
+            .is_err()
+        {
             event!(
-                tracing::Level::DEBUG,
-                "Checking receive predicate for messages {:?}",
-                self.messages
+                tracing::Level::ERROR,
+                "Messages {:?} still missing after {}ms",
+                matcher.missing(&self.messages),
+                timeout_ms
             );
+            return Err(ActionError::WaitError);
+        }
 
-            let notifier = {
-                let context = ctx.lock().await;
-                let events: Vec<&ReceiveEvent> = context
-                    .receive_events
-                    .iter()
-                    .filter(|e| e.instant > instant)
-                    .collect();
+        Ok(())
+    }
+}
 
-                let expected_messages = &self.messages;
+impl ReceivePredicate {
+    /// Returns true if the expected messages were all received after `since`.
+    fn evaluate(&self, receive_events: &[ReceiveEvent], since: Instant) -> bool {
+        ReceiveMatcher::new(self.order).advance(receive_events, &self.messages, |e| {
+            received_since(e, since, self.phase.as_deref())
+        })
+    }
+}
 
-                match receive_exact_match(&events, expected_messages) {
-                    true => {
-                        event!(
-                            tracing::Level::DEBUG,
-                            "Receive predicate found for messages {:?}",
-                            self.messages
-                        );
-                        return Ok(());
+/// Waits until the number of received messages matching `matcher` is at least `min`,
+/// in any order.
+///
+/// With a `max`, the predicate observes the whole timeout, which is then required, and
+/// fails as soon as more than `max` matching messages are received. Setting `min` and
+/// `max` to the same value expects exactly that many messages within the timeout.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CountPredicate {
+    pub matcher: MessagesPredicate,
+    pub min: usize,
+    #[serde(default)]
+    pub max: Option<usize>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl CountPredicate {
+    pub fn new(matcher: MessagesPredicate, min: usize, max: Option<usize>) -> Self {
+        CountPredicate {
+            matcher,
+            min,
+            max,
+            timeout_ms: None,
+        }
+    }
+
+    /// Fails with `ActionError::WaitError` if fewer than `min` matching messages
+    /// were received after `timeout_ms`, instead of waiting forever.
+    ///
+    /// With a `max`, this is the window the matching messages are counted over.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Returns the number of matching messages received after `since`.
+    fn count(&self, receive_events: &[ReceiveEvent], since: Instant) -> usize {
+        receive_events
+            .iter()
+            .filter(|e| e.instant > since && self.matcher.matches(e))
+            .count()
+    }
+
+    fn in_range(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+#[async_trait::async_trait]
+impl Predicate for CountPredicate {
+    /// Checks if the number of matching messages received reached `min` without exceeding `max`.
+    async fn check(&self, ctx: Ctx) -> Result<(), ActionError> {
+        if self.max.is_some() && self.timeout_ms.is_none() {
+            event!(
+                tracing::Level::ERROR,
+                "Counting at most {:?} messages requires a timeout",
+                self.max
+            );
+            return Err(ActionError::WaitError);
+        }
+
+        let instant = ctx.lock().await.clock.now();
+        let mut count = 0;
+
+        let scan = scan_until(
+            &ctx,
+            |context| context.receive_notifier.clone(),
+            None,
+            |context| {
+                count = self.count(&context.receive_events, instant);
+                event!(
+                    tracing::Level::DEBUG,
+                    "{} messages matching {:?} received",
+                    count,
+                    self.matcher.buffer
+                );
+                // The count only grows, once above the maximum it cannot get back in range
+                match self.max {
+                    Some(max) => count > max,
+                    None => count >= self.min,
+                }
+            },
+        );
+
+        let completed = match self.timeout_ms {
+            Some(timeout_ms) => {
+                let scanned = tokio::time::timeout(Duration::from_millis(timeout_ms), scan)
+                    .await
+                    .is_ok();
+                // With a maximum, only the end of the window completes the count
+                scanned != self.max.is_some()
+            }
+            None => {
+                scan.await;
+                true
+            }
+        };
+
+        if !completed || !self.in_range(count) {
+            event!(
+                tracing::Level::ERROR,
+                "{} messages matching {:?} received, expected between {} and {:?}",
+                count,
+                self.matcher.buffer,
+                self.min,
+                self.max
+            );
+            return Err(ActionError::WaitError);
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if an event of the `actual` phase is in the `expected` phase, if any.
+fn phase_match(expected: Option<&str>, actual: Option<&str>) -> bool {
+    expected.is_none() || expected == actual
+}
+
+/// Returns the events received after `instant`, during the given phase if any.
+#[cfg(test)]
+fn receive_events_since<'a>(
+    events: &'a [ReceiveEvent],
+    instant: Instant,
+    phase: Option<&str>,
+) -> Vec<&'a ReceiveEvent> {
+    events
+        .iter()
+        .filter(|e| received_since(e, instant, phase))
+        .collect()
+}
+
+fn received_since(event: &ReceiveEvent, instant: Instant, phase: Option<&str>) -> bool {
+    event.instant > instant && phase_match(phase, event.phase.as_deref())
+}
+
+/// Returns true if the expected messages were all received, in order.
+#[cfg(test)]
+fn receive_exact_match(events: &[&ReceiveEvent], expected_messages: &[MessagesPredicate]) -> bool {
+    let mut matcher = ReceiveMatcher::default();
+    for event in events {
+        matcher.feed(event, expected_messages);
+    }
+    matcher.matched == expected_messages.len()
+}
+
+/// Matches the expected messages against a growing event log, keeping its
+/// progress between checks so that only new events are scanned.
+///
+/// The new events of each scan are matched in instant order, the events sharing an
+/// instant in the order of their sequence numbers, so that they match deterministically.
+#[derive(Debug, Default)]
+struct ReceiveMatcher {
+    order: OrderType,
+    /// Index of the next event of the log to scan.
+    cursor: usize,
+    /// Number of expected messages matched so far.
+    matched: usize,
+    /// Number of events scanned.
+    #[cfg(test)]
+    scanned: usize,
+    /// Unordered only, log indices of the events matching at least one expected message.
+    candidates: Vec<usize>,
+    /// Unordered only, candidate matched by each expected message.
+    assigned: Vec<Option<usize>>,
+}
+
+impl ReceiveMatcher {
+    fn new(order: OrderType) -> Self {
+        ReceiveMatcher {
+            order,
+            ..ReceiveMatcher::default()
+        }
+    }
+
+    /// Returns the expected messages not matched yet.
+    fn missing<'a>(
+        &self,
+        expected_messages: &'a [MessagesPredicate],
+    ) -> Vec<&'a MessagesPredicate> {
+        match self.order {
+            OrderType::Ordered => expected_messages[self.matched..].iter().collect(),
+            OrderType::Unordered => expected_messages
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| self.assigned.get(*idx).is_none_or(Option::is_none))
+                .map(|(_, pred)| pred)
+                .collect(),
+        }
+    }
+
+    fn feed(&mut self, event: &ReceiveEvent, expected_messages: &[MessagesPredicate]) {
+        #[cfg(test)]
+        {
+            self.scanned += 1;
+        }
+        if let Some(pred) = expected_messages.get(self.matched)
+            && pred.matches(event)
+        {
+            self.matched += 1;
+        }
+    }
+
+    /// Scans the events recorded since the previous call that pass `filter`,
+    /// and returns true once all the expected messages matched.
+    fn advance(
+        &mut self,
+        events: &[ReceiveEvent],
+        expected_messages: &[MessagesPredicate],
+        filter: impl Fn(&ReceiveEvent) -> bool,
+    ) -> bool {
+        if events.len() < self.cursor {
+            // The log was trimmed, the progress no longer applies
+            event!(tracing::Level::DEBUG, "Receive events trimmed, rescanning");
+            self.cursor = 0;
+            self.matched = 0;
+            self.candidates.clear();
+            self.assigned.clear();
+        }
+
+        match self.order {
+            OrderType::Ordered => {
+                let mut new_events: Vec<&ReceiveEvent> =
+                    events[self.cursor..].iter().filter(|e| filter(e)).collect();
+                new_events.sort_by_key(|e| (e.instant, e.seq));
+                for event in new_events {
+                    self.feed(event, expected_messages);
+                }
+            }
+            OrderType::Unordered => {
+                self.assigned.resize(expected_messages.len(), None);
+                for (idx, event) in events.iter().enumerate().skip(self.cursor) {
+                    if !filter(event) {
+                        continue;
+                    }
+                    #[cfg(test)]
+                    {
+                        self.scanned += 1;
+                    }
+                    if expected_messages.iter().any(|pred| pred.matches(event)) {
+                        self.candidates.push(idx);
                     }
-                    false => {
-                        event!(
-                            tracing::Level::DEBUG,
-                            "Receive predicate not found for messages {:?}",
-                            self.messages
+                }
+
+                for pred in 0..expected_messages.len() {
+                    if self.assigned[pred].is_none() {
+                        let mut visited = vec![false; self.candidates.len()];
+                        assign(
+                            pred,
+                            expected_messages,
+                            events,
+                            &self.candidates,
+                            &mut self.assigned,
+                            &mut visited,
                         );
-                        context.receive_notifier.clone()
                     }
                 }
-            };
+                self.matched = self.assigned.iter().flatten().count();
+            }
+        }
+        self.cursor = events.len();
+
+        self.matched == expected_messages.len()
+    }
+}
 
-            // wait to be notified before checking again
-            notifier.notified().await;
+/// Assigns a distinct candidate event to the expected message `pred`, moving the
+/// other messages to other candidates if needed (an augmenting path of Kuhn's
+/// bipartite matching). Returns false if no assignment exists.
+fn assign(
+    pred: usize,
+    expected_messages: &[MessagesPredicate],
+    events: &[ReceiveEvent],
+    candidates: &[usize],
+    assigned: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for (candidate, idx) in candidates.iter().enumerate() {
+        if visited[candidate] || !expected_messages[pred].matches(&events[*idx]) {
+            continue;
+        }
+        visited[candidate] = true;
+
+        let owner = assigned.iter().position(|a| *a == Some(candidate));
+        if owner.is_none_or(|owner| {
+            assign(
+                owner,
+                expected_messages,
+                events,
+                candidates,
+                assigned,
+                visited,
+            )
+        }) {
+            assigned[pred] = Some(candidate);
+            return true;
         }
     }
+
+    false
 }
 
-fn receive_exact_match(events: &[&ReceiveEvent], expected_messages: &[MessagesPredicate]) -> bool {
-    let mut idx = 0;
-    expected_messages.iter().all(|pred| {
-        if let Some(pos) = events[idx..].iter().position(|evt| pred.matches(evt)) {
-            idx += pos + 1; // advance past the match
-            true
-        } else {
-            false
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StreamContainsPredicate {
+    pub from: SocketAddr,
+    pub needle: Vec<u8>,
+    #[serde(default)]
+    pub phase: Option<String>,
+}
+
+impl StreamContainsPredicate {
+    pub fn new(from: SocketAddr, needle: Vec<u8>) -> Self {
+        StreamContainsPredicate {
+            from,
+            needle,
+            phase: None,
         }
-    })
+    }
+
+    /// Only considers the bytes received during the given phase.
+    pub fn with_phase(mut self, phase: &str) -> Self {
+        self.phase = Some(phase.to_string());
+        self
+    }
+
+    pub fn from(&self) -> &SocketAddr {
+        &self.from
+    }
+
+    pub fn needle(&self) -> &[u8] {
+        &self.needle
+    }
+}
+
+#[async_trait::async_trait]
+impl Predicate for StreamContainsPredicate {
+    /// Checks if the bytes received from `from` contain `needle`, even across several events.
+    async fn check(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let instant = ctx.lock().await.clock.now();
+        scan_until(
+            &ctx,
+            |context| context.receive_notifier.clone(),
+            None,
+            |context| {
+                let found = self.evaluate(&context.receive_events, instant);
+                event!(
+                    tracing::Level::DEBUG,
+                    "Sequence {:?} {} in stream from {}",
+                    self.needle,
+                    if found { "found" } else { "not found" },
+                    self.from
+                );
+                found
+            },
+        )
+        .await;
+
+        Ok(())
+    }
+}
+
+impl StreamContainsPredicate {
+    /// Returns true if the bytes received from `from` after `since`, during the phase
+    /// if any, contain `needle`.
+    fn evaluate(&self, receive_events: &[ReceiveEvent], since: Instant) -> bool {
+        let events: Vec<&ReceiveEvent> = receive_events
+            .iter()
+            .filter(|e| received_since(e, since, self.phase.as_deref()))
+            .collect();
+
+        stream_contains(self.from, &self.needle, &events)
+    }
+}
+
+/// Returns true if `actual` matches `expected`, a port of 0 in `expected` matching any port.
+pub(crate) fn addr_match(expected: SocketAddr, actual: SocketAddr) -> bool {
+    if expected.port() == 0 {
+        actual.ip() == expected.ip()
+    } else {
+        actual == expected
+    }
+}
+
+fn stream_contains(from: SocketAddr, needle: &[u8], events: &[&ReceiveEvent]) -> bool {
+    if needle.is_empty() {
+        return true;
+    }
+
+    // Concatenate the received buffers so that a needle split between two events is found
+    let stream: Vec<u8> = events
+        .iter()
+        .filter(|e| addr_match(from, e.from))
+        .flat_map(|e| e.buffer.iter().copied())
+        .collect();
+
+    stream.windows(needle.len()).any(|window| window == needle)
 }
 
+/// Matches a message sent by the node, recorded after the wait started.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SendPredicate {
+    pub from: SocketAddr,
+    pub to: SocketAddr,
+    pub buffer: Vec<u8>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl SendPredicate {
+    pub fn new(from: SocketAddr, to: SocketAddr, buffer: Vec<u8>) -> Self {
+        SendPredicate {
+            from,
+            to,
+            buffer,
+            timeout_ms: None,
+        }
+    }
+
+    /// Fails with `ActionError::WaitError` if no matching message was
+    /// sent after `timeout_ms`, instead of waiting forever.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Returns true if the event holds the expected buffer. A port of 0 in
+    /// `from` or `to` matches any port.
+    pub fn matches(&self, event: &SendEvent) -> bool {
+        addr_match(self.from, event.from)
+            && addr_match(self.to, event.to)
+            && event.buffer == self.buffer
+    }
+}
+
+#[async_trait::async_trait]
+impl Predicate for SendPredicate {
+    /// Checks if a matching message was sent since the check started.
+    async fn check(&self, ctx: Ctx) -> Result<(), ActionError> {
+        let instant = ctx.lock().await.clock.now();
+        let scan = scan_until(
+            &ctx,
+            |context| context.send_notifier.clone(),
+            None,
+            |context| {
+                context
+                    .send_events
+                    .iter()
+                    .any(|e| e.instant > instant && self.matches(e))
+            },
+        );
+
+        let Some(timeout_ms) = self.timeout_ms else {
+            scan.await;
+            return Ok(());
+        };
+        if tokio::time::timeout(Duration::from_millis(timeout_ms), scan)
+            .await
+            .is_err()
+        {
+            event!(
+                tracing::Level::WARN,
+                "Nothing sent from {} to {} after {}ms",
+                self.from,
+                self.to,
+                timeout_ms
+            );
+            return Err(ActionError::WaitError);
+        }
+
+        Ok(())
+    }
+}
+
+/// Future of a wait, boxed so that the combined events can wait on their sub-events.
+type WaitFuture<'a> = Pin<Box<dyn Future<Output = Result<(), ActionError>> + Send + 'a>>;
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum WaitEvent {
     Connection(ConnectPredicate),
     Messages(ReceivePredicate),
+    StreamContains(StreamContainsPredicate),
+    /// Waits until the node has no open stream, connected or accepted, failing after `timeout_ms`.
+    StreamsDrained {
+        timeout_ms: u64,
+    },
+    Count(CountPredicate),
+    Sent(SendPredicate),
+    /// Waits for a connection accepted from `peer` to be closed at most `within_ms`
+    /// after it was established, failing if it stays open longer.
+    ConnectThenDisconnect {
+        peer: SocketAddr,
+        within_ms: u64,
+    },
+    /// Waits `duration_ms`, failing as soon as a message matching `message` is received.
+    NotReceived {
+        message: MessagesPredicate,
+        duration_ms: u64,
+    },
+    /// Waits for the first of the events to occur, failing once all of them failed.
+    Any(Vec<WaitEvent>),
+    /// Waits for all the events to occur in any order, failing as soon as one of them failed.
+    All(Vec<WaitEvent>),
+}
+
+impl WaitEvent {
+    /// Returns true if the event occurred after `since` in the given events,
+    /// without waiting for new ones, to check a predicate against captured data.
+    ///
+    /// `StreamsDrained`, `ConnectThenDisconnect` and `Sent` depend on the open
+    /// streams, the disconnections or the sent messages rather than on the given
+    /// events, so they are never satisfied by a snapshot. `NotReceived` is satisfied if
+    /// no matching message is in the snapshot.
+    pub fn evaluate_against(
+        &self,
+        receive_events: &[ReceiveEvent],
+        connect_events: &[ConnectEvent],
+        since: Instant,
+    ) -> bool {
+        match self {
+            WaitEvent::Connection(predicate) => predicate.evaluate(connect_events, since),
+            WaitEvent::Messages(predicate) => predicate.evaluate(receive_events, since),
+            WaitEvent::StreamContains(predicate) => predicate.evaluate(receive_events, since),
+            WaitEvent::Count(predicate) => {
+                predicate.in_range(predicate.count(receive_events, since))
+            }
+            WaitEvent::StreamsDrained { .. }
+            | WaitEvent::ConnectThenDisconnect { .. }
+            | WaitEvent::Sent(_) => false,
+            WaitEvent::NotReceived { message, .. } => !receive_events
+                .iter()
+                .any(|e| e.instant > since && message.matches(e)),
+            WaitEvent::Any(events) => events
+                .iter()
+                .any(|event| event.evaluate_against(receive_events, connect_events, since)),
+            WaitEvent::All(events) => events
+                .iter()
+                .all(|event| event.evaluate_against(receive_events, connect_events, since)),
+        }
+    }
+
+    /// Waits for the event to occur on the node.
+    fn wait(&self, ctx: Ctx) -> WaitFuture<'_> {
+        Box::pin(async move {
+            match self {
+                WaitEvent::Connection(predicate) => predicate.check(ctx).await,
+                WaitEvent::Messages(predicate) => predicate.check(ctx).await,
+                WaitEvent::Sent(predicate) => predicate.check(ctx).await,
+                WaitEvent::StreamContains(predicate) => predicate.check(ctx).await,
+                WaitEvent::Count(predicate) => predicate.check(ctx).await,
+                WaitEvent::StreamsDrained { timeout_ms } => {
+                    let drained = scan_until(
+                        &ctx,
+                        |context| context.stream_notifier.clone(),
+                        None,
+                        |context| {
+                            context.tcp_streams.is_empty() && context.server_streams.is_empty()
+                        },
+                    );
+
+                    tokio::time::timeout(Duration::from_millis(*timeout_ms), drained)
+                        .await
+                        .map(|_| ())
+                        .map_err(|_| {
+                            event!(
+                                tracing::Level::ERROR,
+                                "Streams not drained after {}ms",
+                                timeout_ms
+                            );
+                            ActionError::WaitError
+                        })
+                }
+                WaitEvent::ConnectThenDisconnect { peer, within_ms } => {
+                    connect_then_disconnect(&ctx, *peer, *within_ms).await
+                }
+                WaitEvent::Any(events) => wait_any(events, &ctx).await,
+                WaitEvent::All(events) => wait_all(events, &ctx).await,
+                WaitEvent::NotReceived {
+                    message,
+                    duration_ms,
+                } => {
+                    let instant = ctx.lock().await.clock.now();
+                    let received = scan_until(
+                        &ctx,
+                        |context| context.receive_notifier.clone(),
+                        None,
+                        |context| {
+                            context
+                                .receive_events
+                                .iter()
+                                .any(|e| e.instant > instant && message.matches(e))
+                        },
+                    );
+
+                    match tokio::time::timeout(Duration::from_millis(*duration_ms), received).await
+                    {
+                        Ok(_) => {
+                            event!(
+                                tracing::Level::ERROR,
+                                "Unexpected message {:?} from {} received",
+                                message.buffer,
+                                message.from
+                            );
+                            Err(ActionError::WaitError)
+                        }
+                        Err(_) => Ok(()),
+                    }
+                }
+            }
+        })
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -225,53 +1043,194 @@ impl Action for Wait {
     }
 
     async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
-        match &self.event {
-            WaitEvent::Connection(predicate) => predicate.check(ctx).await,
-            WaitEvent::Messages(predicate) => predicate.check(ctx).await,
+        self.event.wait(ctx).await
+    }
+}
+
+/// Polls the waits on `events` together until one succeeds, then drops the others.
+/// Fails with the last error once all of them failed, or right away without events.
+async fn wait_any(events: &[WaitEvent], ctx: &Ctx) -> Result<(), ActionError> {
+    let mut waits: Vec<_> = events.iter().map(|event| event.wait(ctx.clone())).collect();
+    let mut error = ActionError::WaitError;
+
+    std::future::poll_fn(|cx| {
+        let mut idx = 0;
+        while idx < waits.len() {
+            match waits[idx].as_mut().poll(cx) {
+                Poll::Ready(Ok(())) => return Poll::Ready(Ok(())),
+                Poll::Ready(Err(e)) => {
+                    error = e;
+                    drop(waits.swap_remove(idx));
+                }
+                Poll::Pending => idx += 1,
+            }
+        }
+
+        if waits.is_empty() {
+            event!(tracing::Level::WARN, "None of the awaited events occurred");
+            Poll::Ready(Err(error.clone()))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Polls the waits on `events` together until all of them succeeded. Fails with the
+/// first error, dropping the waits still pending.
+async fn wait_all(events: &[WaitEvent], ctx: &Ctx) -> Result<(), ActionError> {
+    let mut waits: Vec<_> = events.iter().map(|event| event.wait(ctx.clone())).collect();
+
+    std::future::poll_fn(|cx| {
+        let mut idx = 0;
+        while idx < waits.len() {
+            match waits[idx].as_mut().poll(cx) {
+                Poll::Ready(Ok(())) => drop(waits.swap_remove(idx)),
+                Poll::Ready(Err(e)) => {
+                    event!(
+                        tracing::Level::WARN,
+                        "{} of the awaited events did not occur",
+                        waits.len()
+                    );
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => idx += 1,
+            }
+        }
+
+        if waits.is_empty() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Waits for the first connection from `peer` accepted from now on, then for its
+/// disconnection, failing if it is still open `within_ms` after it was established.
+async fn connect_then_disconnect(
+    ctx: &Ctx,
+    peer: SocketAddr,
+    within_ms: u64,
+) -> Result<(), ActionError> {
+    let instant = ctx.lock().await.clock.now();
+    let mut connection = None;
+    scan_until(
+        ctx,
+        |context| context.connect_notifier.clone(),
+        None,
+        |context| {
+            connection = context
+                .connect_events
+                .iter()
+                .find(|e| e.instant > instant && addr_match(peer, e.from))
+                .map(|e| (e.from, e.instant));
+            connection.is_some()
+        },
+    )
+    .await;
+    let Some((from, connected_at)) = connection else {
+        return Err(ActionError::WaitError);
+    };
+    event!(
+        tracing::Level::DEBUG,
+        "{} connected, waiting for its disconnection",
+        from
+    );
+
+    let within = Duration::from_millis(within_ms);
+    let remaining = within.saturating_sub(ctx.lock().await.clock.now() - connected_at);
+    let mut disconnected_at = None;
+    // Disconnections are followed by the removal of the stream
+    let disconnection = scan_until(
+        ctx,
+        |context| context.stream_notifier.clone(),
+        None,
+        |context| {
+            disconnected_at = context
+                .disconnect_events
+                .iter()
+                .find(|e| e.from == from && e.instant >= connected_at)
+                .map(|e| e.instant);
+            disconnected_at.is_some()
+        },
+    );
+    let _ = tokio::time::timeout(remaining, disconnection).await;
+
+    match disconnected_at {
+        Some(disconnected_at) if disconnected_at - connected_at <= within => Ok(()),
+        _ => {
+            event!(
+                tracing::Level::ERROR,
+                "Connection from {} still open {}ms after it was established",
+                from,
+                within_ms
+            );
+            Err(ActionError::WaitError)
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{net::SocketAddr, time::Duration};
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
 
-    use tokio::time::Instant;
+    use tokio::{net::TcpSocket, time::Instant};
 
     use crate::{
-        ReceiveEvent,
+        Clock, MockClock, Node, ReceiveEvent,
+        action::{Action, ActionError, SetPhase},
         node::ConnectEvent,
-        protocol::ip::wait::{connect_match, receive_exact_match},
+        protocol::{
+            Protocol,
+            ip::{
+                Bind, Connect, Send, SendMode,
+                wait::{connect_match, receive_events_since, receive_exact_match, stream_contains},
+            },
+        },
     };
 
-    use super::MessagesPredicate;
+    use super::{
+        BufferMatch, BytePattern, ConnectPredicate, CountPredicate, MessagesPredicate, OrderType,
+        Predicate, ReceiveMatcher, ReceivePredicate, SendPredicate, StreamContainsPredicate, Wait,
+        WaitEvent, scan_until,
+    };
 
     #[test]
     fn test_receive_exact_match() {
         let event1 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            truncated: false,
+            phase: None,
         };
 
         let event2 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 1,
             from: "127.0.0.1:30000".parse().unwrap(),
             to: "127.0.0.1:30000".parse().unwrap(),
             buffer: vec![4, 5, 6],
+            truncated: false,
+            phase: None,
         };
 
         let predicate1 = MessagesPredicate {
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            matching: BufferMatch::Exact,
         };
 
         let predicate2 = MessagesPredicate {
             from: "127.0.0.1:30000".parse().unwrap(),
             to: "127.0.0.1:30000".parse().unwrap(),
             buffer: vec![4, 5, 6],
+            matching: BufferMatch::Exact,
         };
 
         let received_events: Vec<&ReceiveEvent> = vec![&event1, &event2];
@@ -284,21 +1243,26 @@ mod tests {
     fn test_receive_exact_match_different_length() {
         let event1 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            truncated: false,
+            phase: None,
         };
 
         let predicate1 = MessagesPredicate {
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            matching: BufferMatch::Exact,
         };
 
         let predicate2 = MessagesPredicate {
             from: "127.0.0.1:30000".parse().unwrap(),
             to: "127.0.0.1:30000".parse().unwrap(),
             buffer: vec![4, 5, 6],
+            matching: BufferMatch::Exact,
         };
 
         let received_events: Vec<&ReceiveEvent> = vec![&event1];
@@ -311,15 +1275,19 @@ mod tests {
     fn test_receive_exact_match_different_buffer() {
         let event1 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            truncated: false,
+            phase: None,
         };
 
         let predicate1 = MessagesPredicate {
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![],
+            matching: BufferMatch::Exact,
         };
 
         let received_events: Vec<&ReceiveEvent> = vec![&event1];
@@ -332,28 +1300,36 @@ mod tests {
     fn test_receive_exact_match_different_order() {
         let event1 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            truncated: false,
+            phase: None,
         };
 
         let event2 = ReceiveEvent {
             instant: Instant::now(),
+            seq: 0,
             from: "127.0.0.1:30000".parse().unwrap(),
             to: "127.0.0.1:30000".parse().unwrap(),
             buffer: vec![4, 5, 6],
+            truncated: false,
+            phase: None,
         };
 
         let predicate1 = MessagesPredicate {
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:3000".parse().unwrap(),
             buffer: vec![1, 2, 3],
+            matching: BufferMatch::Exact,
         };
 
         let predicate2 = MessagesPredicate {
             from: "127.0.0.1:30000".parse().unwrap(),
             to: "127.0.0.1:30000".parse().unwrap(),
             buffer: vec![4, 5, 6],
+            matching: BufferMatch::Exact,
         };
 
         let received_events: Vec<&ReceiveEvent> = vec![&event2, &event1];
@@ -372,8 +1348,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(connect_match(
@@ -394,8 +1372,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(!connect_match(
@@ -416,8 +1396,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(!connect_match(
@@ -438,8 +1420,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(connect_match(
@@ -460,8 +1444,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(!connect_match(
@@ -482,8 +1468,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(!connect_match(
@@ -504,8 +1492,10 @@ mod tests {
 
         let connect_event = ConnectEvent {
             instant: event_instant,
+            seq: 0,
             from: "127.0.0.1:3000".parse().unwrap(),
             to: "127.0.0.1:4000".parse().unwrap(),
+            phase: None,
         };
 
         assert!(!connect_match(
@@ -515,4 +1505,929 @@ mod tests {
             &[connect_event],
         ));
     }
+
+    #[test]
+    fn test_stream_contains_across_events() {
+        let event1 = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: "127.0.0.1:4000".parse().unwrap(),
+            buffer: vec![0x41, 0x0d, 0x0a],
+            truncated: false,
+            phase: None,
+        };
+
+        let event2 = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: "127.0.0.1:4000".parse().unwrap(),
+            buffer: vec![0x0d, 0x0a, 0x42],
+            truncated: false,
+            phase: None,
+        };
+
+        let from: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let needle = [0x0d, 0x0a, 0x0d, 0x0a];
+
+        assert!(!stream_contains(from, &needle, &[&event1]));
+        assert!(stream_contains(from, &needle, &[&event1, &event2]));
+    }
+
+    #[test]
+    fn test_stream_contains_other_peer() {
+        let event1 = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: "127.0.0.1:4000".parse().unwrap(),
+            buffer: vec![0x0d, 0x0a],
+            truncated: false,
+            phase: None,
+        };
+
+        let event2 = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from: "127.0.0.2:3000".parse().unwrap(),
+            to: "127.0.0.1:4000".parse().unwrap(),
+            buffer: vec![0x0d, 0x0a],
+            truncated: false,
+            phase: None,
+        };
+
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let needle = [0x0d, 0x0a, 0x0d, 0x0a];
+
+        assert!(!stream_contains(from, &needle, &[&event1, &event2]));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_stream_contains_final_fragment_between_scans() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        for _ in 0..500 {
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+
+            let predicate = StreamContainsPredicate::new(from, b"\r\n\r\n".to_vec());
+            let check = tokio::spawn({
+                let ctx = ctx.clone();
+                async move { predicate.check(ctx).await }
+            });
+
+            // The fragments race with the scans of the predicate
+            for fragment in [b"\r\n", b"\r\n"] {
+                let mut context = ctx.lock().await;
+                context.receive_events.push(ReceiveEvent {
+                    // Stamped after the predicate started, whenever it does
+                    instant: Instant::now() + Duration::from_secs(60),
+                    seq: 0,
+                    from,
+                    to: "127.0.0.1:4000".parse().unwrap(),
+                    buffer: fragment.to_vec(),
+                    truncated: false,
+                    phase: None,
+                });
+                context.receive_notifier.notify_waiters();
+            }
+
+            tokio::time::timeout(Duration::from_secs(1), check)
+                .await
+                .expect("The final fragment was missed")
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_receive_events_since_phase() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        let instant = Instant::now();
+
+        Bind::new("127.0.1.6:3000".parse().unwrap())
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let socket = tokio::net::UdpSocket::bind("127.0.1.7:0").await.unwrap();
+        for (phase, buffer) in [("setup", [1, 2, 3]), ("load", [4, 5, 6])] {
+            SetPhase::new(phase).perform(ctx.clone()).await.unwrap();
+            socket.send_to(&buffer, "127.0.1.6:49999").await.unwrap();
+
+            while !ctx
+                .lock()
+                .await
+                .receive_events
+                .iter()
+                .any(|e| e.phase.as_deref() == Some(phase))
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+
+        let predicate = |buffer: &[u8]| MessagesPredicate {
+            from: "127.0.1.7:0".parse().unwrap(),
+            to: "127.0.1.6:49999".parse().unwrap(),
+            buffer: buffer.to_vec(),
+            matching: BufferMatch::Exact,
+        };
+
+        let context = ctx.lock().await;
+        let load_events = receive_events_since(&context.receive_events, instant, Some("load"));
+        let setup_events = receive_events_since(&context.receive_events, instant, Some("setup"));
+
+        assert_eq!(load_events.len(), 1);
+        assert!(receive_exact_match(&load_events, &[predicate(&[4, 5, 6])]));
+        assert_eq!(setup_events.len(), 1);
+        assert!(setup_events[0].instant < load_events[0].instant);
+
+        // The message sent during setup does not satisfy a predicate scoped to the load
+        let setup_message = ReceivePredicate::new(vec![predicate(&[1, 2, 3])]);
+        assert!(
+            !setup_message
+                .clone()
+                .with_phase("load")
+                .evaluate(&context.receive_events, instant)
+        );
+        assert!(
+            setup_message
+                .with_phase("setup")
+                .evaluate(&context.receive_events, instant)
+        );
+
+        let setup_bytes = StreamContainsPredicate::new("127.0.1.7:0".parse().unwrap(), vec![2, 3]);
+        assert!(
+            !setup_bytes
+                .clone()
+                .with_phase("load")
+                .evaluate(&context.receive_events, instant)
+        );
+        assert!(setup_bytes.evaluate(&context.receive_events, instant));
+    }
+
+    #[tokio::test]
+    async fn test_connect_predicate_with_mock_clock() {
+        let server: SocketAddr = "127.0.1.34:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.35:0".parse().unwrap();
+        let clock = MockClock::new();
+        let start = clock.now();
+
+        let server_node = Node::new("server");
+        Bind::new(server).perform(server_node.ctx()).await.unwrap();
+
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        ctx.lock().await.clock = std::sync::Arc::new(clock.clone());
+
+        let connect = Connect::new(client, server, 1000).with_force_new(true);
+        connect.perform(ctx.clone()).await.unwrap();
+
+        // The connection is stamped at the instant the wait starts, so it is ignored
+        let wait = Wait::new(WaitEvent::Connection(ConnectPredicate::new(client, server)));
+        let wait = tokio::spawn({
+            let ctx = ctx.clone();
+            async move { wait.perform(ctx).await }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!wait.is_finished());
+
+        clock.advance(Duration::from_millis(10));
+        connect.perform(ctx.clone()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), wait)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        let context = ctx.lock().await;
+        let outgoing: Vec<Instant> = context
+            .connect_events
+            .iter()
+            .filter(|e| e.to == server && e.from.ip() == client.ip())
+            .map(|e| e.instant)
+            .collect();
+        assert_eq!(outgoing, [start, start + Duration::from_millis(10)]);
+    }
+
+    #[test]
+    fn test_receive_matcher_is_incremental() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let event = |buffer: &[u8]| ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            truncated: false,
+            phase: None,
+        };
+        let expected: Vec<MessagesPredicate> = [b"a", b"b", b"c"]
+            .iter()
+            .map(|buffer| MessagesPredicate {
+                from,
+                to,
+                buffer: buffer.to_vec(),
+                matching: BufferMatch::Exact,
+            })
+            .collect();
+
+        let mut matcher = ReceiveMatcher::default();
+        let mut events = vec![event(b"noise")];
+        for (idx, buffer) in [b"a", b"b", b"c"].iter().enumerate() {
+            events.push(event(*buffer));
+            let done = matcher.advance(&events, &expected, |_| true);
+
+            assert_eq!(done, idx == 2);
+            assert_eq!(matcher.matched, idx + 1);
+            // Each event is only scanned once
+            assert_eq!(matcher.scanned, events.len());
+        }
+
+        // Trimming the log restarts the match from scratch
+        events.truncate(1);
+        assert!(!matcher.advance(&events, &expected, |_| true));
+        assert_eq!(matcher.matched, 0);
+    }
+
+    #[tokio::test]
+    async fn test_debounce_coalesces_notifications() {
+        let mut scans = Vec::new();
+        for debounce in [None, Some(Duration::from_millis(50))] {
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+
+            let scanner = tokio::spawn({
+                let ctx = ctx.clone();
+                async move {
+                    scan_until(
+                        &ctx,
+                        |context| context.receive_notifier.clone(),
+                        debounce,
+                        |context| context.receive_events.len() >= 50,
+                    )
+                    .await
+                }
+            });
+            tokio::task::yield_now().await;
+
+            for _ in 0..50 {
+                let mut context = ctx.lock().await;
+                context.receive_events.push(ReceiveEvent {
+                    instant: Instant::now(),
+                    seq: 0,
+                    from: "127.0.0.1:3000".parse().unwrap(),
+                    to: "127.0.0.1:4000".parse().unwrap(),
+                    buffer: vec![1],
+                    truncated: false,
+                    phase: None,
+                });
+                context.receive_notifier.notify_waiters();
+                drop(context);
+                tokio::task::yield_now().await;
+            }
+
+            scans.push(scanner.await.unwrap());
+        }
+
+        assert!(scans[1] < scans[0], "{:?}", scans);
+        assert!(scans[1] <= 3, "{:?}", scans);
+    }
+
+    #[tokio::test]
+    async fn test_streams_drained() {
+        let server: SocketAddr = "127.0.1.60:3000".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(crate::protocol::Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let mut clients = Vec::new();
+        for _ in 0..2 {
+            clients.push(tokio::net::TcpStream::connect(server).await.unwrap());
+        }
+        while ctx.lock().await.server_streams.len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let drained = Wait::new(WaitEvent::StreamsDrained { timeout_ms: 100 });
+        assert!(drained.perform(ctx.clone()).await.is_err());
+
+        let wait = tokio::spawn({
+            let ctx = ctx.clone();
+            async move {
+                Wait::new(WaitEvent::StreamsDrained { timeout_ms: 1000 })
+                    .perform(ctx)
+                    .await
+            }
+        });
+        drop(clients);
+
+        assert!(wait.await.unwrap().is_ok());
+        assert!(ctx.lock().await.server_streams.is_empty());
+    }
+
+    #[test]
+    fn test_any_destination_port() {
+        let event = |to: &str| ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: to.parse().unwrap(),
+            buffer: vec![1],
+            truncated: false,
+            phase: None,
+        };
+        let predicate = MessagesPredicate {
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: "127.0.0.2:0".parse().unwrap(),
+            buffer: vec![1],
+            matching: BufferMatch::Exact,
+        };
+
+        assert!(predicate.matches(&event("127.0.0.2:4000")));
+        assert!(predicate.matches(&event("127.0.0.2:5000")));
+        assert!(!predicate.matches(&event("127.0.0.3:4000")));
+    }
+
+    #[test]
+    fn test_connect_match_any_destination_port() {
+        let instant = Instant::now();
+        let connect_event = |to: &str| ConnectEvent {
+            instant: instant + Duration::from_millis(1),
+            seq: 0,
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: to.parse().unwrap(),
+            phase: None,
+        };
+        let from: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let to: SocketAddr = "127.0.0.2:0".parse().unwrap();
+
+        assert!(connect_match(
+            from,
+            to,
+            instant,
+            &[connect_event("127.0.0.2:4000")]
+        ));
+        assert!(connect_match(
+            from,
+            to,
+            instant,
+            &[connect_event("127.0.0.2:5000")]
+        ));
+        assert!(!connect_match(
+            from,
+            to,
+            instant,
+            &[connect_event("127.0.0.3:4000")]
+        ));
+    }
+
+    #[test]
+    fn test_connect_predicate_from_cidr() {
+        let since = Instant::now();
+        let to: SocketAddr = "10.255.0.1:3000".parse().unwrap();
+        let connect_event = |from: &str| ConnectEvent {
+            instant: since + Duration::from_millis(1),
+            seq: 0,
+            from: from.parse().unwrap(),
+            to,
+            phase: None,
+        };
+        let predicate = ConnectPredicate::new("0.0.0.0:0".parse().unwrap(), to)
+            .with_from_cidr("10.0.0.0/8".parse().unwrap());
+
+        assert!(predicate.evaluate(&[connect_event("10.4.2.1:5000")], since));
+        assert!(!predicate.evaluate(&[connect_event("192.168.1.1:5000")], since));
+
+        // The port of `from` still applies
+        let predicate = ConnectPredicate {
+            from: "0.0.0.0:4000".parse().unwrap(),
+            ..predicate
+        };
+        assert!(predicate.evaluate(&[connect_event("10.4.2.1:4000")], since));
+        assert!(!predicate.evaluate(&[connect_event("10.4.2.1:5000")], since));
+
+        let json = serde_json::to_string(&predicate).unwrap();
+        let parsed: ConnectPredicate = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, predicate);
+    }
+
+    #[tokio::test]
+    async fn test_wait_any() {
+        let server: SocketAddr = "127.0.1.111:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.112:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let any = Wait::new(WaitEvent::Any(vec![
+            WaitEvent::Messages(ReceivePredicate::new(vec![MessagesPredicate {
+                from: client,
+                to: server,
+                buffer: b"never sent".to_vec(),
+                matching: BufferMatch::Exact,
+            }])),
+            WaitEvent::Connection(ConnectPredicate::new(client, server)),
+        ]));
+        let wait = tokio::spawn({
+            let ctx = ctx.clone();
+            async move { any.perform(ctx).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!wait.is_finished());
+
+        Connect::new(client, server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        let result = tokio::time::timeout(Duration::from_millis(200), wait)
+            .await
+            .expect("the connection should end the wait");
+        assert_eq!(result.unwrap(), Ok(()));
+
+        // Fails once every sub-event failed
+        let none = Wait::new(WaitEvent::Any(vec![
+            WaitEvent::Connection(ConnectPredicate::new(client, server).with_timeout(20)),
+            WaitEvent::StreamsDrained { timeout_ms: 50 },
+        ]));
+        assert_eq!(none.perform(ctx).await, Err(ActionError::WaitError));
+    }
+
+    #[tokio::test]
+    async fn test_wait_all() {
+        let tcp_server: SocketAddr = "127.0.1.113:3000".parse().unwrap();
+        let udp_server: SocketAddr = "127.0.1.113:3001".parse().unwrap();
+        let client: SocketAddr = "127.0.1.114:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        for (server, protocol) in [(tcp_server, Protocol::Tcp), (udp_server, Protocol::Udp)] {
+            Bind::new(server)
+                .with_protocol(protocol)
+                .perform(ctx.clone())
+                .await
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let connection = WaitEvent::Connection(ConnectPredicate::new(client, tcp_server));
+        let message = WaitEvent::Messages(ReceivePredicate::new(vec![MessagesPredicate {
+            from: client,
+            to: udp_server,
+            buffer: b"first".to_vec(),
+            matching: BufferMatch::Exact,
+        }]));
+        let all = Wait::new(WaitEvent::All(vec![connection.clone(), message]));
+        let wait = tokio::spawn({
+            let ctx = ctx.clone();
+            async move { all.perform(ctx).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The message comes first, then the connection
+        Send::new(SendMode::UdpUnicast, client, udp_server, b"first".to_vec())
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!wait.is_finished());
+
+        Connect::new(client, tcp_server, 1000)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        let result = tokio::time::timeout(Duration::from_millis(200), wait)
+            .await
+            .expect("the connection should end the wait");
+        assert_eq!(result.unwrap(), Ok(()));
+
+        // A sub-event timing out fails the whole wait
+        let missing = Wait::new(WaitEvent::All(vec![
+            WaitEvent::Messages(
+                ReceivePredicate::new(vec![MessagesPredicate {
+                    from: client,
+                    to: udp_server,
+                    buffer: b"never sent".to_vec(),
+                    matching: BufferMatch::Exact,
+                }])
+                .with_timeout(50),
+            ),
+            connection,
+        ]));
+        assert_eq!(missing.perform(ctx).await, Err(ActionError::WaitError));
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_send() {
+        let server: SocketAddr = "127.0.1.109:3000".parse().unwrap();
+        let client: SocketAddr = "127.0.1.110:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Udp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        let sent = SendPredicate::new(client, server, b"go".to_vec());
+        let timed_out = Wait::new(WaitEvent::Sent(sent.clone().with_timeout(50)));
+        assert_eq!(
+            timed_out.perform(ctx.clone()).await,
+            Err(ActionError::WaitError)
+        );
+
+        let wait = tokio::spawn({
+            let ctx = ctx.clone();
+            async move {
+                Wait::new(WaitEvent::Sent(sent.with_timeout(1000)))
+                    .perform(ctx)
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!wait.is_finished());
+
+        Send::new(SendMode::UdpUnicast, client, server, b"go".to_vec())
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(wait.await.unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn test_regex_buffer_match() {
+        let from: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:2000".parse().unwrap();
+        let predicate = MessagesPredicate {
+            from,
+            to,
+            buffer: vec![],
+            matching: BufferMatch::Regex(BytePattern::new(r"SEQ=\d+").unwrap()),
+        };
+        let event = |buffer: &[u8]| ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            truncated: false,
+            phase: None,
+        };
+
+        assert!(predicate.matches(&event(b"SEQ=42")));
+        assert!(!predicate.matches(&event(b"SEQ=")));
+        assert!(!predicate.matches(&event(b"ACK=42")));
+
+        let json = serde_json::to_string(&predicate).unwrap();
+        let parsed: MessagesPredicate = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, predicate);
+    }
+
+    #[test]
+    fn test_prefix_and_suffix_buffer_match() {
+        let from: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:2000".parse().unwrap();
+        let predicate = |matching| MessagesPredicate {
+            from,
+            to,
+            buffer: vec![],
+            matching,
+        };
+        let event = ReceiveEvent {
+            instant: Instant::now(),
+            seq: 0,
+            from,
+            to,
+            buffer: b"HDR:body;".to_vec(),
+            truncated: false,
+            phase: None,
+        };
+
+        assert!(predicate(BufferMatch::Prefix(b"HDR:".to_vec())).matches(&event));
+        assert!(!predicate(BufferMatch::Prefix(b"BODY".to_vec())).matches(&event));
+        assert!(predicate(BufferMatch::Suffix(b";".to_vec())).matches(&event));
+        assert!(!predicate(BufferMatch::Suffix(b"HDR:".to_vec())).matches(&event));
+        // A prefix longer than the buffer never matches
+        assert!(!predicate(BufferMatch::Prefix(b"HDR:body;trailer".to_vec())).matches(&event));
+    }
+
+    #[tokio::test]
+    async fn test_connect_then_disconnect() {
+        let server: SocketAddr = "127.0.1.103:3000".parse().unwrap();
+        let peer: SocketAddr = "127.0.1.104:0".parse().unwrap();
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        Bind::new(server)
+            .with_protocol(Protocol::Tcp)
+            .perform(ctx.clone())
+            .await
+            .unwrap();
+
+        // The first peer closes its connection quickly, the second one keeps it open
+        for (hold_ms, expected) in [(20, Ok(())), (300, Err(ActionError::WaitError))] {
+            let wait = tokio::spawn({
+                let ctx = ctx.clone();
+                async move {
+                    Wait::new(WaitEvent::ConnectThenDisconnect {
+                        peer,
+                        within_ms: 100,
+                    })
+                    .perform(ctx)
+                    .await
+                }
+            });
+            tokio::time::sleep(Duration::from_millis(20)).await;
+
+            let socket = TcpSocket::new_v4().unwrap();
+            socket.bind(peer).unwrap();
+            let stream = socket.connect(server).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(hold_ms)).await;
+            drop(stream);
+
+            assert_eq!(wait.await.unwrap(), expected, "held {}ms", hold_ms);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_count_predicate() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let matcher = MessagesPredicate {
+            from,
+            to,
+            buffer: vec![1],
+            matching: BufferMatch::Exact,
+        };
+
+        // Under the minimum, in range, over the maximum, exactly and one over exactly
+        for (received, min, max, expected) in [
+            (1, 2, None, Err(ActionError::WaitError)),
+            (3, 2, Some(4), Ok(())),
+            (3, 1, Some(2), Err(ActionError::WaitError)),
+            (2, 2, Some(2), Ok(())),
+            (3, 2, Some(2), Err(ActionError::WaitError)),
+        ] {
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+            let sender = ctx.clone();
+            tokio::spawn(async move {
+                // A non matching message is not counted
+                for buffer in std::iter::repeat_n(vec![1], received).chain([vec![2]]) {
+                    // The surplus messages arrive after the minimum was reached
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    let mut context = sender.lock().await;
+                    let event = ReceiveEvent {
+                        instant: context.clock.now(),
+                        seq: context.next_seq(),
+                        from,
+                        to,
+                        buffer,
+                        truncated: false,
+                        phase: None,
+                    };
+                    context.push_receive_event(event);
+                    context.receive_notifier.notify_waiters();
+                }
+            });
+
+            let predicate = CountPredicate::new(matcher.clone(), min, max).with_timeout(200);
+            let result = Wait::new(WaitEvent::Count(predicate)).perform(ctx).await;
+            assert_eq!(result, expected, "{} received", received);
+        }
+
+        // A maximum cannot be observed without a window
+        let predicate = CountPredicate::new(matcher, 1, Some(1));
+        assert_eq!(
+            Wait::new(WaitEvent::Count(predicate))
+                .perform(Node::new("test-node").ctx())
+                .await,
+            Err(ActionError::WaitError)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_not_received() {
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let not_received = |buffer: &[u8]| {
+            Wait::new(WaitEvent::NotReceived {
+                message: MessagesPredicate {
+                    from,
+                    to,
+                    buffer: buffer.to_vec(),
+                    matching: BufferMatch::Exact,
+                },
+                duration_ms: 200,
+            })
+        };
+
+        for (blocked, expected) in [(b"other", Ok(())), (b"block", Err(ActionError::WaitError))] {
+            let node = Node::new("test-node");
+            let ctx = node.ctx();
+            let sender = ctx.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                let mut context = sender.lock().await;
+                let event = ReceiveEvent {
+                    instant: context.clock.now(),
+                    seq: context.next_seq(),
+                    from,
+                    to,
+                    buffer: b"block".to_vec(),
+                    truncated: false,
+                    phase: None,
+                };
+                context.push_receive_event(event);
+                context.receive_notifier.notify_waiters();
+            });
+
+            let start = Instant::now();
+            assert_eq!(not_received(blocked).perform(ctx).await, expected);
+            // A matching message fails the wait before the end of the window
+            assert_eq!(
+                start.elapsed() >= Duration::from_millis(200),
+                expected.is_ok()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_predicate_timeout() {
+        let node = Node::new("test-node");
+        let predicate = ConnectPredicate::new(
+            "127.0.0.1:3000".parse().unwrap(),
+            "127.0.0.1:4000".parse().unwrap(),
+        )
+        .with_timeout(50);
+
+        let start = Instant::now();
+        let result = Wait::new(WaitEvent::Connection(predicate))
+            .perform(node.ctx())
+            .await;
+        assert_eq!(result, Err(ActionError::WaitError));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_receive_predicate_timeout() {
+        let node = Node::new("test-node");
+        let predicate = ReceivePredicate::new(vec![MessagesPredicate {
+            from: "127.0.0.1:3000".parse().unwrap(),
+            to: "127.0.0.1:4000".parse().unwrap(),
+            buffer: vec![1],
+            matching: BufferMatch::Exact,
+        }])
+        .with_timeout(50);
+
+        let start = Instant::now();
+        let result = Wait::new(WaitEvent::Messages(predicate))
+            .perform(node.ctx())
+            .await;
+        assert_eq!(result, Err(ActionError::WaitError));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_unordered_receive_predicate() {
+        let since = Instant::now();
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let event = |offset_ms, buffer: &[u8]| ReceiveEvent {
+            instant: since + Duration::from_millis(offset_ms),
+            seq: offset_ms,
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            truncated: false,
+            phase: None,
+        };
+        let message = |buffer: &[u8]| MessagesPredicate {
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            matching: BufferMatch::Exact,
+        };
+        let reversed = vec![event(1, b"second"), event(2, b"first")];
+        let predicate = ReceivePredicate::new(vec![message(b"first"), message(b"second")]);
+
+        assert!(!predicate.evaluate(&reversed, since));
+        let unordered = predicate.with_order(OrderType::Unordered);
+        assert!(unordered.evaluate(&reversed, since));
+
+        // A received message only satisfies one expected message
+        let twice = ReceivePredicate::new(vec![message(b"first"), message(b"first")])
+            .with_order(OrderType::Unordered);
+        assert!(!twice.evaluate(&reversed, since));
+        assert!(twice.evaluate(
+            &[reversed.clone(), vec![event(3, b"first")]].concat(),
+            since
+        ));
+
+        // The first message fits both predicates, it is moved to the prefix one
+        let prefix = MessagesPredicate {
+            matching: BufferMatch::Prefix(b"fir".to_vec()),
+            ..message(b"")
+        };
+        let overlapping =
+            ReceivePredicate::new(vec![prefix, message(b"first")]).with_order(OrderType::Unordered);
+        let events = vec![event(1, b"first"), event(2, b"firsts")];
+        assert!(overlapping.evaluate(&events, since));
+
+        // Progress is kept between scans of a growing log
+        let mut matcher = ReceiveMatcher::new(OrderType::Unordered);
+        let expected = [message(b"first"), message(b"second")];
+        assert!(!matcher.advance(&reversed[..1], &expected, |_| true));
+        assert_eq!(matcher.missing(&expected), vec![&expected[0]]);
+        assert!(matcher.advance(&reversed, &expected, |_| true));
+        assert_eq!(matcher.scanned, 2);
+    }
+
+    #[test]
+    fn test_evaluate_against_snapshot() {
+        let since = Instant::now();
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+        let event = |offset_ms, buffer: &[u8]| ReceiveEvent {
+            instant: since + Duration::from_millis(offset_ms),
+            seq: offset_ms,
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            truncated: false,
+            phase: None,
+        };
+        let captured = vec![event(0, b"old"), event(1, b"HELLO"), event(2, b"WORLD")];
+
+        let message = |buffer: &[u8]| MessagesPredicate {
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            matching: BufferMatch::Exact,
+        };
+        let wait_event = |buffers: &[&[u8]]| {
+            WaitEvent::Messages(ReceivePredicate::new(
+                buffers.iter().map(|buffer| message(buffer)).collect(),
+            ))
+        };
+
+        assert!(wait_event(&[b"HELLO", b"WORLD"]).evaluate_against(&captured, &[], since));
+        assert!(!wait_event(&[b"WORLD", b"HELLO"]).evaluate_against(&captured, &[], since));
+        // The event at the start instant is not considered
+        assert!(!wait_event(&[b"old"]).evaluate_against(&captured, &[], since));
+    }
+
+    #[tokio::test]
+    async fn test_equal_instant_events_match_in_recording_order() {
+        let node = Node::new("test-node");
+        let ctx = node.ctx();
+        let from: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        let to: SocketAddr = "127.0.0.1:4000".parse().unwrap();
+
+        {
+            let mut context = ctx.lock().await;
+            // Both events are recorded at the same instant
+            context.clock = Arc::new(MockClock::new());
+            // The log holds them out of recording order
+            let instant = context.clock.now();
+            for (seq, buffer) in [(1, b"later"), (0, b"first")] {
+                context.receive_events.push(ReceiveEvent {
+                    instant,
+                    seq,
+                    from,
+                    to,
+                    buffer: buffer.to_vec(),
+                    truncated: false,
+                    phase: None,
+                });
+            }
+        }
+
+        let context = ctx.lock().await;
+        let events = &context.receive_events;
+        assert_eq!(events[0].instant, events[1].instant);
+        assert!(events[0].seq > events[1].seq);
+
+        let message = |buffer: &[u8]| MessagesPredicate {
+            from,
+            to,
+            buffer: buffer.to_vec(),
+            matching: BufferMatch::Exact,
+        };
+        let mut matcher = ReceiveMatcher::default();
+        assert!(matcher.advance(events, &[message(b"first"), message(b"later")], |_| true));
+        let mut matcher = ReceiveMatcher::default();
+        assert!(!matcher.advance(events, &[message(b"later"), message(b"first")], |_| true));
+    }
 }
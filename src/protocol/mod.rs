@@ -1 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 pub mod ip;
+
+/// Transport protocol used to exchange data between nodes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
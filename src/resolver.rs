@@ -0,0 +1,89 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io,
+    net::{IpAddr, SocketAddr},
+};
+
+use crate::node::Ctx;
+
+/// Resolves the host names of the endpoints given to the actions of a node.
+#[async_trait::async_trait]
+pub trait Resolver: Debug + Send + Sync {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<SocketAddr>;
+}
+
+/// Resolver querying the system, through the hosts file and DNS.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemResolver;
+
+#[async_trait::async_trait]
+impl Resolver for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<SocketAddr> {
+        tokio::net::lookup_host((host, port))
+            .await?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No address found for {}", host),
+                )
+            })
+    }
+}
+
+/// Resolver answering from a fixed table, so that tests do not depend on DNS.
+#[derive(Debug, Default, Clone)]
+pub struct StaticResolver {
+    hosts: HashMap<String, IpAddr>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        StaticResolver::default()
+    }
+
+    /// Resolves `host` to `ip`.
+    pub fn with_host(mut self, host: &str, ip: IpAddr) -> Self {
+        self.hosts.insert(host.to_string(), ip);
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl Resolver for StaticResolver {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<SocketAddr> {
+        self.hosts
+            .get(host)
+            .map(|ip| SocketAddr::new(*ip, port))
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("Unknown host {}", host))
+            })
+    }
+}
+
+/// Resolves a `host:port` endpoint with the resolver of the node.
+///
+/// Socket addresses, such as `[::1]:8080`, are used as is.
+pub(crate) async fn resolve_endpoint(ctx: &Ctx, endpoint: &str) -> io::Result<SocketAddr> {
+    if let Ok(addr) = endpoint.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+
+    let (host, port) = endpoint
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Expected host:port, got {}", endpoint),
+            )
+        })?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
+    let resolver = ctx.lock().await.resolver.clone();
+    resolver.resolve(host, port).await
+}
@@ -0,0 +1,810 @@
+use std::{io, net::SocketAddr, path::Path, sync::Arc};
+
+use tokio::{sync::Barrier, task::JoinSet};
+use tracing::{Instrument, event};
+
+use crate::{
+    Node,
+    action::{ActionError, Sleep},
+    node::PhaseBarriers,
+    protocol::{
+        Protocol,
+        ip::{
+            Bind, BufferMatch, Connect, MessagesPredicate, ReceivePredicate, Send, SendMode, Wait,
+            WaitEvent, addr_match,
+        },
+    },
+};
+
+/// Represents a set of nodes running their actions concurrently.
+#[derive(Debug, Default)]
+pub struct Scenario {
+    nodes: Vec<Node>,
+    phase_barriers: PhaseBarriers,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Scenario {
+            nodes: Vec::new(),
+            phase_barriers: PhaseBarriers::default(),
+        }
+    }
+
+    /// Creates the barrier of the phase `name`, released once `count` nodes
+    /// performed a [`crate::action::PhaseBarrier`] action for it.
+    pub fn phase_barrier(&mut self, name: &str, count: usize) {
+        self.phase_barriers
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), Arc::new(Barrier::new(count)));
+    }
+
+    pub fn add_node(&mut self, node: Node) {
+        self.nodes.push(node);
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Returns the node with the given name, if any.
+    pub fn node(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.name() == name)
+    }
+
+    /// Loads a two-node UDP scenario replaying the conversation of a transcript file.
+    ///
+    /// The transcript names the address of both nodes, then lists the messages in order,
+    /// `>` going from the client to the server and `<` the other way, each sent after
+    /// waiting the given number of milliseconds:
+    ///
+    /// ```text
+    /// client 127.0.0.1:4000
+    /// server 127.0.0.1:3000
+    /// > 0 HELLO
+    /// < 10 WELCOME
+    /// ```
+    ///
+    /// Each node sends its messages and waits for the ones of its peer before going on.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn from_transcript(path: impl AsRef<Path>) -> io::Result<Scenario> {
+        let transcript = std::fs::read_to_string(path)?;
+        let invalid = |line: usize, reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid transcript line {}: {}", line + 1, reason),
+            )
+        };
+
+        let mut client = None;
+        let mut server = None;
+        // (client to server, delay, payload)
+        let mut messages: Vec<(bool, u64, Vec<u8>)> = Vec::new();
+        for (idx, line) in transcript.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (head, rest) = line.split_once(' ').ok_or_else(|| invalid(idx, line))?;
+            match head {
+                "client" | "server" => {
+                    let addr: SocketAddr = rest
+                        .trim()
+                        .parse()
+                        .map_err(|_| invalid(idx, "expected a socket address"))?;
+                    if head == "client" {
+                        client = Some(addr);
+                    } else {
+                        server = Some(addr);
+                    }
+                }
+                ">" | "<" => {
+                    let (delay, payload) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let delay = delay
+                        .parse()
+                        .map_err(|_| invalid(idx, "expected a delay in milliseconds"))?;
+                    messages.push((head == ">", delay, payload.as_bytes().to_vec()));
+                }
+                _ => return Err(invalid(idx, "expected client, server, > or <")),
+            }
+        }
+
+        let missing = |name: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Transcript has no {} address", name),
+            )
+        };
+        let client = client.ok_or_else(|| missing("client"))?;
+        let server = server.ok_or_else(|| missing("server"))?;
+
+        // Leave both nodes time to bind before the first message
+        let mut client_steps = vec![ScenarioStep::Bind(client), ScenarioStep::Sleep(100)];
+        let mut server_steps = vec![ScenarioStep::Bind(server), ScenarioStep::Sleep(100)];
+
+        // Consecutive messages in the same direction are waited for at once,
+        // the later ones may arrive before the first is matched
+        for group in messages.chunk_by(|a, b| a.0 == b.0) {
+            let to_server = group[0].0;
+            let (from, to) = if to_server {
+                (client, server)
+            } else {
+                (server, client)
+            };
+            let from_any = SocketAddr::new(from.ip(), 0);
+
+            let (sender, receiver) = if to_server {
+                (&mut client_steps, &mut server_steps)
+            } else {
+                (&mut server_steps, &mut client_steps)
+            };
+            for (_, delay, payload) in group {
+                if *delay > 0 {
+                    sender.push(ScenarioStep::Sleep(*delay));
+                }
+                sender.push(ScenarioStep::Send {
+                    from: from_any,
+                    to,
+                    buffer: payload.clone(),
+                });
+            }
+            receiver.push(ScenarioStep::Wait(ReceivePredicate::new(
+                group
+                    .iter()
+                    .map(|(_, _, payload)| MessagesPredicate {
+                        from: from_any,
+                        to,
+                        buffer: payload.clone(),
+                        matching: BufferMatch::Exact,
+                    })
+                    .collect(),
+            )));
+        }
+
+        Ok(ScenarioBuilder::new()
+            .node("client", client_steps)
+            .node("server", server_steps)
+            .build(Protocol::Udp))
+    }
+
+    /// Starts every node and waits for all of them to finish.
+    pub async fn run(&mut self) {
+        let mut set = JoinSet::new();
+
+        for node in &self.nodes {
+            node.ctx().lock().await.phase_barriers = Arc::clone(&self.phase_barriers);
+        }
+
+        for (idx, mut node) in self.nodes.drain(..).enumerate() {
+            let span = tracing::info_span!("node", name = %node.name());
+            set.spawn(
+                async move {
+                    node.start().await;
+                    (idx, node)
+                }
+                .instrument(span),
+            );
+        }
+
+        let mut nodes = Vec::new();
+        while let Some(res) = set.join_next().await {
+            match res {
+                Ok(node) => nodes.push(node),
+                Err(e) => event!(tracing::Level::ERROR, "Node failed: {:?}", e),
+            }
+        }
+
+        // Keep the nodes in the order they were added
+        nodes.sort_by_key(|(idx, _)| *idx);
+        self.nodes = nodes.into_iter().map(|(_, node)| node).collect();
+    }
+
+    /// Asserts every byte sent by the `sender` node from `from` to `to` was received
+    /// by the `receiver` node, reporting the missing bytes and messages otherwise.
+    ///
+    /// A port of 0 in `from` or `to` matches any port.
+    pub async fn assert_no_loss(
+        &self,
+        sender: &str,
+        receiver: &str,
+        from: SocketAddr,
+        to: SocketAddr,
+    ) -> Result<(), ActionError> {
+        let node = |name: &str| {
+            self.node(name)
+                .ok_or_else(|| ActionError::AssertError(format!("Unknown node {}", name)))
+        };
+        let (sender, receiver) = (node(sender)?, node(receiver)?);
+
+        let (sent_bytes, sent_messages) = {
+            let context = sender.ctx();
+            let context = context.lock().await;
+            let sent: Vec<usize> = context
+                .send_events
+                .iter()
+                .filter(|e| addr_match(from, e.from) && addr_match(to, e.to))
+                .map(|e| e.len)
+                .collect();
+            (sent.iter().sum::<usize>(), sent.len())
+        };
+        let (received_bytes, received_messages) = {
+            let context = receiver.ctx();
+            let context = context.lock().await;
+            let received: Vec<usize> = context
+                .receive_events
+                .iter()
+                .filter(|e| addr_match(from, e.from) && addr_match(to, e.to))
+                .map(|e| e.buffer.len())
+                .collect();
+            (received.iter().sum::<usize>(), received.len())
+        };
+
+        event!(
+            tracing::Level::INFO,
+            "{} sent {} bytes ({} messages) from {} to {}, {} received {} bytes ({} messages)",
+            sender.name(),
+            sent_bytes,
+            sent_messages,
+            from,
+            to,
+            receiver.name(),
+            received_bytes,
+            received_messages
+        );
+
+        if received_bytes < sent_bytes {
+            return Err(ActionError::AssertError(format!(
+                "{} bytes ({} messages) sent from {} to {} were lost",
+                sent_bytes - received_bytes,
+                sent_messages.saturating_sub(received_messages),
+                from,
+                to
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Asserts every node in `listeners` received the `payload` broadcast by the
+    /// `broadcaster` node, reporting the listeners that missed it otherwise.
+    ///
+    /// A datagram matches when its source is one the broadcaster sent `payload` from,
+    /// a port of 0 on the broadcaster side matching the ephemeral port seen by the listeners.
+    pub async fn assert_broadcast_received(
+        &self,
+        broadcaster: &str,
+        listeners: &[&str],
+        payload: &[u8],
+    ) -> Result<(), ActionError> {
+        let node = |name: &str| {
+            self.node(name)
+                .ok_or_else(|| ActionError::AssertError(format!("Unknown node {}", name)))
+        };
+        let broadcaster = node(broadcaster)?;
+
+        let sources: Vec<SocketAddr> = broadcaster
+            .ctx()
+            .lock()
+            .await
+            .send_events
+            .iter()
+            .filter(|e| e.buffer == payload)
+            .map(|e| e.from)
+            .collect();
+        if sources.is_empty() {
+            return Err(ActionError::AssertError(format!(
+                "{} never sent the broadcast payload",
+                broadcaster.name()
+            )));
+        }
+
+        let mut missed = Vec::new();
+        for name in listeners {
+            let listener = node(name)?;
+            let received = listener
+                .ctx()
+                .lock()
+                .await
+                .receive_events
+                .iter()
+                .any(|e| e.buffer == payload && sources.iter().any(|s| addr_match(*s, e.from)));
+            if !received {
+                missed.push(listener.name());
+            }
+        }
+
+        event!(
+            tracing::Level::INFO,
+            "{} broadcast {} bytes from {:?}, {} of {} listeners received it",
+            broadcaster.name(),
+            payload.len(),
+            sources,
+            listeners.len() - missed.len(),
+            listeners.len()
+        );
+
+        if !missed.is_empty() {
+            return Err(ActionError::AssertError(format!(
+                "Broadcast from {} was missed by {}",
+                broadcaster.name(),
+                missed.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Asserts the last connection of the `client` node to `to` was accepted by the
+    /// `server` node from the same source, reporting what each side observed otherwise.
+    ///
+    /// The source is compared with its port, `Connect` recording the port the system picked.
+    pub async fn assert_connection_consistent(
+        &self,
+        client: &str,
+        server: &str,
+        to: SocketAddr,
+    ) -> Result<(), ActionError> {
+        let node = |name: &str| {
+            self.node(name)
+                .ok_or_else(|| ActionError::AssertError(format!("Unknown node {}", name)))
+        };
+        let (client, server) = (node(client)?, node(server)?);
+
+        let outbound = client
+            .ctx()
+            .lock()
+            .await
+            .connect_events
+            .iter()
+            .filter(|e| addr_match(to, e.to))
+            .max_by_key(|e| (e.instant, e.seq))
+            .cloned()
+            .ok_or_else(|| {
+                ActionError::AssertError(format!("{} never connected to {}", client.name(), to))
+            })?;
+
+        let inbound: Vec<(SocketAddr, SocketAddr)> = server
+            .ctx()
+            .lock()
+            .await
+            .connect_events
+            .iter()
+            .filter(|e| addr_match(to, e.to))
+            .map(|e| (e.from, e.to))
+            .collect();
+
+        event!(
+            tracing::Level::INFO,
+            "{} connected from {} to {}, {} accepted {:?}",
+            client.name(),
+            outbound.from,
+            outbound.to,
+            server.name(),
+            inbound
+        );
+
+        if !inbound
+            .iter()
+            .any(|(from, to)| addr_match(outbound.from, *from) && addr_match(outbound.to, *to))
+        {
+            return Err(ActionError::AssertError(format!(
+                "{} connected from {} to {}, but {} accepted {:?}",
+                client.name(),
+                outbound.from,
+                outbound.to,
+                server.name(),
+                inbound
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// A transport agnostic step of a scenario built by [`ScenarioBuilder`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScenarioStep {
+    /// Binds a listener for the chosen protocol.
+    Bind(SocketAddr),
+    /// Connects to a remote host. Ignored for UDP, which is connectionless.
+    Connect {
+        from: SocketAddr,
+        to: SocketAddr,
+        timeout_ms: u64,
+    },
+    /// Sends a message on the connected stream (TCP) or as a datagram (UDP).
+    Send {
+        from: SocketAddr,
+        to: SocketAddr,
+        buffer: Vec<u8>,
+    },
+    /// Waits for the given messages.
+    Wait(ReceivePredicate),
+    /// Sleeps for the given duration in milliseconds.
+    Sleep(u64),
+}
+
+/// Builds a [`Scenario`] from transport agnostic steps, so that the same
+/// sequence can be run over TCP and UDP.
+#[derive(Debug, Default, Clone)]
+pub struct ScenarioBuilder {
+    nodes: Vec<(String, Vec<ScenarioStep>)>,
+}
+
+impl ScenarioBuilder {
+    pub fn new() -> Self {
+        ScenarioBuilder { nodes: Vec::new() }
+    }
+
+    /// Adds a node performing the given steps.
+    pub fn node(mut self, name: &str, steps: Vec<ScenarioStep>) -> Self {
+        self.nodes.push((name.to_string(), steps));
+        self
+    }
+
+    /// Instantiates the scenario for the given protocol.
+    pub fn build(&self, protocol: Protocol) -> Scenario {
+        let mut scenario = Scenario::new();
+
+        for (name, steps) in &self.nodes {
+            let mut node = Node::new(name);
+
+            for step in steps {
+                match step.clone() {
+                    ScenarioStep::Bind(to) => {
+                        node.add_action(Bind::new(to).with_protocol(protocol))
+                    }
+                    ScenarioStep::Connect {
+                        from,
+                        to,
+                        timeout_ms,
+                    } => {
+                        if protocol == Protocol::Tcp {
+                            node.add_action(Connect::new(from, to, timeout_ms));
+                        }
+                    }
+                    ScenarioStep::Send { from, to, buffer } => {
+                        let mode = match protocol {
+                            Protocol::Tcp => SendMode::Unicast,
+                            Protocol::Udp => SendMode::UdpUnicast,
+                        };
+                        node.add_action(Send::new(mode, from, to, buffer));
+                    }
+                    ScenarioStep::Wait(predicate) => {
+                        node.add_action(Wait::new(WaitEvent::Messages(predicate)))
+                    }
+                    ScenarioStep::Sleep(duration_ms) => node.add_action(Sleep::new(duration_ms)),
+                }
+            }
+
+            scenario.add_node(node);
+        }
+
+        scenario
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        Node,
+        action::{ActionError, PhaseBarrier, Sleep},
+        protocol::{
+            Protocol,
+            ip::{Bind, BufferMatch, Connect, MessagesPredicate, ReceivePredicate, Send, SendMode},
+        },
+    };
+
+    use super::{Scenario, ScenarioBuilder, ScenarioStep};
+
+    #[tokio::test]
+    async fn test_echo_scenario_over_tcp_and_udp() {
+        let client: SocketAddr = "127.0.1.2:3000".parse().unwrap();
+        let client_any: SocketAddr = "127.0.1.2:0".parse().unwrap();
+        let server: SocketAddr = "127.0.1.3:3000".parse().unwrap();
+        let server_any: SocketAddr = "127.0.1.3:0".parse().unwrap();
+
+        let builder = ScenarioBuilder::new()
+            .node(
+                "client",
+                vec![
+                    ScenarioStep::Bind(client),
+                    ScenarioStep::Sleep(100),
+                    ScenarioStep::Connect {
+                        from: client_any,
+                        to: server,
+                        timeout_ms: 1000,
+                    },
+                    ScenarioStep::Send {
+                        from: client_any,
+                        to: server,
+                        buffer: b"ping".to_vec(),
+                    },
+                    ScenarioStep::Wait(ReceivePredicate::new(vec![MessagesPredicate {
+                        from: server_any,
+                        to: client,
+                        buffer: b"ping".to_vec(),
+                        matching: BufferMatch::Exact,
+                    }])),
+                ],
+            )
+            .node(
+                "server",
+                vec![
+                    ScenarioStep::Bind(server),
+                    ScenarioStep::Wait(ReceivePredicate::new(vec![MessagesPredicate {
+                        from: client_any,
+                        to: server,
+                        buffer: b"ping".to_vec(),
+                        matching: BufferMatch::Exact,
+                    }])),
+                    ScenarioStep::Sleep(100),
+                    ScenarioStep::Connect {
+                        from: server_any,
+                        to: client,
+                        timeout_ms: 1000,
+                    },
+                    ScenarioStep::Send {
+                        from: server_any,
+                        to: client,
+                        buffer: b"ping".to_vec(),
+                    },
+                ],
+            );
+
+        for protocol in [Protocol::Tcp, Protocol::Udp] {
+            let mut scenario = builder.build(protocol);
+            tokio::time::timeout(Duration::from_secs(5), scenario.run())
+                .await
+                .unwrap_or_else(|_| panic!("{:?} scenario timed out", protocol));
+
+            let client_node = scenario.node("client").unwrap();
+            let received = client_node.ctx().lock().await.receive_events.clone();
+            assert_eq!(received.len(), 1, "{:?}", protocol);
+            assert_eq!(received[0].buffer, b"ping".to_vec());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assert_no_loss() {
+        let sender_addr: SocketAddr = "127.0.1.37:0".parse().unwrap();
+        let receiver_addr: SocketAddr = "127.0.1.36:3000".parse().unwrap();
+
+        let mut receiver = Node::new("receiver");
+        receiver.add_action(Sleep::new(100));
+        receiver.add_action(Bind::new(receiver_addr).with_protocol(Protocol::Udp));
+        receiver.add_action(Sleep::new(300));
+
+        // The first datagram is sent before the receiver is bound and is lost
+        let mut sender = Node::new("sender");
+        for (delay, buffer) in [(0, b"lost"), (200, b"sent")] {
+            sender.add_action(Sleep::new(delay));
+            sender.add_action(Send::new(
+                SendMode::UdpUnicast,
+                sender_addr,
+                receiver_addr,
+                buffer.to_vec(),
+            ));
+        }
+
+        let mut scenario = Scenario::new();
+        scenario.add_node(sender);
+        scenario.add_node(receiver);
+        scenario.run().await;
+
+        match scenario
+            .assert_no_loss("sender", "receiver", sender_addr, receiver_addr)
+            .await
+        {
+            Err(ActionError::AssertError(report)) => {
+                assert!(report.starts_with("4 bytes (1 messages)"), "{}", report)
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        // Only the second datagram was sent after the receiver bound
+        let received = scenario.node("receiver").unwrap().ctx();
+        assert_eq!(received.lock().await.receive_events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_assert_broadcast_received() {
+        let broadcaster_addr: SocketAddr = "127.0.1.121:0".parse().unwrap();
+
+        // Each listener binds the loopback broadcast address on its own port,
+        // the broadcaster skipping the port of the last one
+        let listen = |port: u16| -> SocketAddr { SocketAddr::from(([127, 255, 255, 255], port)) };
+        let broadcast = |ports: &[u16]| {
+            let mut scenario = Scenario::new();
+            let mut broadcaster = Node::new("broadcaster");
+            broadcaster.add_action(Sleep::new(100));
+            for port in ports {
+                broadcaster.add_action(Send::new(
+                    SendMode::Broadcast,
+                    broadcaster_addr,
+                    listen(*port),
+                    b"announce".to_vec(),
+                ));
+            }
+            scenario.add_node(broadcaster);
+            scenario
+        };
+
+        let mut scenario = broadcast(&[4121, 4122]);
+        for (name, port) in [("left", 4121), ("right", 4122)] {
+            let mut listener = Node::new(name);
+            listener.add_action(Bind::new(listen(port)).with_protocol(Protocol::Udp));
+            listener.add_action(Sleep::new(300));
+            scenario.add_node(listener);
+        }
+        scenario.run().await;
+        scenario
+            .assert_broadcast_received("broadcaster", &["left", "right"], b"announce")
+            .await
+            .unwrap();
+
+        let mut scenario = broadcast(&[4123]);
+        for (name, port) in [("left", 4123), ("right", 4124)] {
+            let mut listener = Node::new(name);
+            listener.add_action(Bind::new(listen(port)).with_protocol(Protocol::Udp));
+            listener.add_action(Sleep::new(300));
+            scenario.add_node(listener);
+        }
+        scenario.run().await;
+        match scenario
+            .assert_broadcast_received("broadcaster", &["left", "right"], b"announce")
+            .await
+        {
+            Err(ActionError::AssertError(report)) => {
+                assert_eq!(report, "Broadcast from broadcaster was missed by right")
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_assert_connection_consistent() {
+        let server_addr: SocketAddr = "127.0.1.95:3000".parse().unwrap();
+        let client_addr: SocketAddr = "127.0.1.96:0".parse().unwrap();
+
+        let mut server = Node::new("server");
+        server.add_action(Bind::new(server_addr).with_protocol(Protocol::Tcp));
+        server.add_action(Sleep::new(300));
+
+        let mut client = Node::new("client");
+        client.add_action(Sleep::new(100));
+        client.add_action(Connect::new(client_addr, server_addr, 1000));
+        client.add_action(Sleep::new(100));
+
+        let mut scenario = Scenario::new();
+        scenario.add_node(server);
+        scenario.add_node(client);
+        scenario.run().await;
+
+        assert_eq!(
+            scenario
+                .assert_connection_consistent("client", "server", server_addr)
+                .await,
+            Ok(())
+        );
+        assert!(matches!(
+            scenario
+                .assert_connection_consistent(
+                    "client",
+                    "server",
+                    "127.0.1.95:3001".parse().unwrap()
+                )
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+
+        // The server accepted the connection from another port than the client used
+        {
+            let client = scenario.node("client").unwrap().ctx();
+            let mut client = client.lock().await;
+            let outbound = client.connect_events.last_mut().unwrap();
+            assert_ne!(outbound.from.port(), 0);
+            outbound.from.set_port(outbound.from.port().wrapping_add(1));
+        }
+        assert!(matches!(
+            scenario
+                .assert_connection_consistent("client", "server", server_addr)
+                .await,
+            Err(ActionError::AssertError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_phase_barrier() {
+        let sink: SocketAddr = "127.0.1.52:3000".parse().unwrap();
+        let _sink = tokio::net::UdpSocket::bind(sink).await.unwrap();
+
+        let mut scenario = Scenario::new();
+        scenario.phase_barrier("load", 3);
+
+        // The nodes take different times to set up
+        for (name, setup) in [("a", vec![]), ("b", vec![100]), ("c", vec![100, 100])] {
+            let mut node = Node::new(name);
+            for duration_ms in setup {
+                node.add_action(Sleep::new(duration_ms));
+            }
+            node.add_action(PhaseBarrier::new("load"));
+            node.add_action(Send::new(
+                SendMode::UdpUnicast,
+                "127.0.1.53:0".parse().unwrap(),
+                sink,
+                vec![1],
+            ));
+            scenario.add_node(node);
+        }
+
+        let start = tokio::time::Instant::now();
+        tokio::time::timeout(Duration::from_secs(5), scenario.run())
+            .await
+            .unwrap();
+
+        for node in scenario.nodes() {
+            let context = node.ctx();
+            let context = context.lock().await;
+            assert_eq!(context.phase.as_deref(), Some("load"));
+            assert_eq!(context.send_events.len(), 1, "{}", node.name());
+
+            let sent = context.send_events[0].instant.duration_since(start);
+            assert!(sent >= Duration::from_millis(200), "{}", node.name());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_transcript() {
+        let path =
+            std::env::temp_dir().join(format!("nseqe-test-transcript-{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "# Login exchange\n\
+             client 127.0.1.73:4000\n\
+             server 127.0.1.74:3000\n\
+             \n\
+             > 0 HELLO\n\
+             > 0 USER alice\n\
+             < 20 WELCOME alice\n\
+             > 20 QUIT\n",
+        )
+        .unwrap();
+
+        let mut scenario = Scenario::from_transcript(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), scenario.run())
+            .await
+            .expect("Transcript scenario timed out");
+
+        let buffers = |name: &str| {
+            let ctx = scenario.node(name).unwrap().ctx();
+            async move {
+                ctx.lock()
+                    .await
+                    .receive_events
+                    .iter()
+                    .map(|e| e.buffer.clone())
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(
+            buffers("server").await,
+            vec![b"HELLO".to_vec(), b"USER alice".to_vec(), b"QUIT".to_vec()]
+        );
+        assert_eq!(buffers("client").await, vec![b"WELCOME alice".to_vec()]);
+
+        let path = std::env::temp_dir().join(format!(
+            "nseqe-test-invalid-transcript-{}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, "client 127.0.1.73:4000\n> soon HELLO\n").unwrap();
+        let error = Scenario::from_transcript(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+}
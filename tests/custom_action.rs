@@ -0,0 +1,47 @@
+use nseqe::{
+    Ctx, Node,
+    action::{Action, ActionError, Sleep},
+};
+use serde::{Deserialize, Serialize};
+
+/// An action defined outside of the crate, entering a phase named after its label.
+#[derive(Debug, Serialize, Deserialize)]
+struct Mark {
+    label: String,
+}
+
+#[async_trait::async_trait]
+#[typetag::serde]
+impl Action for Mark {
+    fn name(&self) -> String {
+        "MARK".into()
+    }
+
+    async fn perform(&self, ctx: Ctx) -> Result<(), ActionError> {
+        ctx.lock().await.phase = Some(self.label.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_custom_action_json_round_trip() {
+    let mut node = Node::new("custom");
+    node.add_action(Sleep::new(1));
+    node.add_action(Mark {
+        label: "marked".into(),
+    });
+
+    let json = serde_json::to_string(node.actions()).unwrap();
+    let actions: Vec<Box<dyn Action>> = serde_json::from_str(&json).unwrap();
+
+    let mut restored = Node::new("restored");
+    for action in actions {
+        restored.add_boxed_action(action);
+    }
+
+    let names: Vec<String> = restored.actions().iter().map(|a| a.name()).collect();
+    assert_eq!(names, vec!["SLEEP", "MARK"]);
+
+    restored.start().await;
+    assert_eq!(restored.ctx().lock().await.phase.as_deref(), Some("marked"));
+}